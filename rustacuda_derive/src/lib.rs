@@ -6,8 +6,8 @@ extern crate syn;
 
 use proc_macro2::{Ident, Span, TokenStream};
 use syn::{
-    parse_str, Data, DataEnum, DataStruct, DataUnion, DeriveInput, Field, Fields, Generics,
-    TypeParamBound,
+    parse_str, Data, DataEnum, DataStruct, DataUnion, DeriveInput, Error, Field, Fields, Generics,
+    Index, TypeParamBound,
 };
 
 use proc_macro::TokenStream as BaseTokenStream;
@@ -60,6 +60,67 @@ fn impl_device_copy(input: &DeriveInput) -> TokenStream {
     TokenStream::from(generated_code)
 }
 
+#[proc_macro_derive(KernelArgs)]
+pub fn derive_kernel_args(input: BaseTokenStream) -> BaseTokenStream {
+    let ast = syn::parse(input).unwrap();
+    let gen = impl_kernel_args(&ast);
+    BaseTokenStream::from(gen)
+}
+
+fn impl_kernel_args(input: &DeriveInput) -> TokenStream {
+    let input_type = &input.ident;
+
+    // Each field is one kernel parameter, in declaration order.
+    let fields: Vec<TokenStream> = match input.data {
+        Data::Struct(ref data_struct) => match data_struct.fields {
+            Fields::Named(ref named_fields) => named_fields
+                .named
+                .iter()
+                .map(|field| {
+                    let name = &field.ident;
+                    quote!(#name)
+                })
+                .collect(),
+            Fields::Unnamed(ref unnamed_fields) => (0..unnamed_fields.unnamed.len())
+                .map(|index| {
+                    let index = Index::from(index);
+                    quote!(#index)
+                })
+                .collect(),
+            Fields::Unit => vec![],
+        },
+        _ => {
+            return Error::new(
+                Span::call_site(),
+                "KernelArgs can only be derived for structs, whose fields are the kernel parameters",
+            )
+            .to_compile_error();
+        }
+    };
+
+    // `ParamBuffer::push` requires DeviceCopy, so fields which don't implement it fail to compile.
+    let generics = add_bound_to_generics(&input.generics);
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let param_fields = fields.iter();
+    let buffer_fields = fields.iter();
+    let generated_code = quote! {
+        unsafe impl#impl_generics ::rustacuda::function::KernelArgs for #input_type#type_generics #where_clause {
+            fn kernel_params(&self) -> ::std::vec::Vec<*mut ::std::ffi::c_void> {
+                vec![#(&self.#param_fields as *const _ as *mut ::std::ffi::c_void),*]
+            }
+
+            fn param_buffer(&self) -> ::std::option::Option<::rustacuda::function::ParamBuffer> {
+                let mut buffer = ::rustacuda::function::ParamBuffer::new();
+                #(buffer.push(&self.#buffer_fields);)*
+                ::std::option::Option::Some(buffer)
+            }
+        }
+    };
+
+    TokenStream::from(generated_code)
+}
+
 fn add_bound_to_generics(generics: &Generics) -> Generics {
     let mut new_generics = generics.clone();
     let bound: TypeParamBound =
@@ -114,6 +114,7 @@
 
 use crate::device::Device;
 use crate::error::{CudaResult, DropResult, ToResult};
+use crate::ffi;
 use crate::private::Sealed;
 use crate::CudaApiVersion;
 use cuda_sys::cuda::{self, CUcontext};
@@ -841,7 +842,7 @@ impl CurrentContext {
     /// Block to wait for a context's tasks to complete.
     pub fn synchronize() -> CudaResult<()> {
         unsafe {
-            cuda::cuCtxSynchronize().to_result()?;
+            ffi::cuCtxSynchronize().to_result()?;
             Ok(())
         }
     }
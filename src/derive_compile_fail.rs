@@ -1,5 +1,6 @@
 //! This module is a dummy module. It contains doctests that should fail to compile. It's used for
-//! testing the DeriveCopy custom-derive macro and should not contain any actual code.
+//! testing the DeriveCopy and KernelArgs custom-derive macros and should not contain any actual
+//! code.
 //!
 //! ```compile_fail
 //! #[macro_use]
@@ -64,3 +65,31 @@
 //!
 //! fn main() {}
 //! ```
+//!
+//! ```compile_fail
+//! #[macro_use]
+//! extern crate rustacuda;
+//! extern crate rustacuda_core;
+//!
+//! #[derive(KernelArgs)]
+//! struct ShouldFailKernelArgs {
+//!     len: u32,
+//!     v: Vec<u64>,
+//! }
+//!
+//! fn main() {}
+//! ```
+//!
+//! ```compile_fail
+//! #[macro_use]
+//! extern crate rustacuda;
+//! extern crate rustacuda_core;
+//!
+//! #[derive(Clone, Copy, DeviceCopy, KernelArgs)]
+//! enum ShouldFailKernelArgsEnum {
+//!     A(u32),
+//!     B(u64),
+//! }
+//!
+//! fn main() {}
+//! ```
@@ -1,6 +1,7 @@
 //! Functions and types for enumerating CUDA devices and retrieving information about them.
 
 use crate::error::{CudaResult, ToResult};
+use crate::ffi;
 use cuda_sys::cuda::*;
 use std::ffi::CStr;
 use std::ops::Range;
@@ -192,8 +193,22 @@ pub enum DeviceAttribute {
     ComputePreemptionSupported = 90,
     /// Device can access host registered memory at the same virtual address as the CPU
     CanUseHostPointerForRegisteredMem = 91,
+    /// Device supports stream memory operations such as `Stream::wait_value` and
+    /// `Stream::write_value`
+    CanUseStreamMemOps = 92,
+    /// Device supports 64-bit stream memory operations
+    CanUse64BitStreamMemOps = 93,
+    /// Device supports `WaitCondition::Nor` in stream memory operations
+    CanUseStreamWaitValueNor = 94,
+    /// Device supports launching cooperative kernels
+    CooperativeLaunch = 95,
+    /// Device supports launching cooperative kernels on multiple devices at once
+    CooperativeMultiDeviceLaunch = 96,
+    /// Maximum shared memory per block which a function can opt into with
+    /// `FunctionAttributeValue::MaxDynamicSharedSizeBytes`
+    MaxSharedMemoryPerBlockOptin = 97,
     #[doc(hidden)]
-    __NonExhaustive = 92,
+    __NonExhaustive = 98,
 }
 
 /// Opaque handle to a CUDA device.
@@ -346,13 +361,10 @@ impl Device {
     pub fn get_attribute(self, attr: DeviceAttribute) -> CudaResult<i32> {
         unsafe {
             let mut val = 0i32;
-            cuDeviceGetAttribute(
-                &mut val as *mut i32,
-                // This should be safe, as the repr and values of DeviceAttribute should match.
-                ::std::mem::transmute(attr),
-                self.device,
-            )
-            .to_result()?;
+            // The values of DeviceAttribute match the driver's, but newer attributes are missing
+            // from the cuda-sys enum, so pass the raw value.
+            ffi::cuDeviceGetAttribute(&mut val as *mut i32, attr as u32, self.device)
+                .to_result()?;
             Ok(val)
         }
     }
@@ -421,12 +433,25 @@ mod test {
         Ok(())
     }
 
-    // Ensure that the two enums always stay aligned.
+    // Ensure that the two enums always stay aligned. DeviceAttribute also includes attributes
+    // from newer CUDA versions than cuda-sys, which continue the numbering from its maximum.
     #[test]
     fn test_enums_align() {
         assert_eq!(
-            DeviceAttribute::__NonExhaustive as u32,
+            DeviceAttribute::CanUseHostPointerForRegisteredMem as u32,
+            CUdevice_attribute_enum::CU_DEVICE_ATTRIBUTE_CAN_USE_HOST_POINTER_FOR_REGISTERED_MEM
+                as u32
+        );
+        assert_eq!(
+            DeviceAttribute::CanUseStreamMemOps as u32,
             CUdevice_attribute_enum::CU_DEVICE_ATTRIBUTE_MAX as u32
         );
+        assert_eq!(DeviceAttribute::CanUseStreamMemOps as u32, 92);
+        assert_eq!(DeviceAttribute::CanUse64BitStreamMemOps as u32, 93);
+        assert_eq!(DeviceAttribute::CanUseStreamWaitValueNor as u32, 94);
+        assert_eq!(DeviceAttribute::CooperativeLaunch as u32, 95);
+        assert_eq!(DeviceAttribute::CooperativeMultiDeviceLaunch as u32, 96);
+        assert_eq!(DeviceAttribute::MaxSharedMemoryPerBlockOptin as u32, 97);
+        assert_eq!(DeviceAttribute::__NonExhaustive as u32, 98);
     }
 }
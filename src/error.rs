@@ -7,11 +7,11 @@
 //! RustaCUDA) can fail. Even those functions which have no normal failure conditions can return
 //! errors related to previous asynchronous launches.
 
-use cuda_sys::cuda::{self, cudaError_t};
+use crate::ffi;
+use cuda_sys::cuda::cudaError_t;
 use std::error::Error;
 use std::ffi::CStr;
 use std::fmt;
-use std::mem;
 use std::os::raw::c_char;
 use std::ptr;
 use std::result::Result;
@@ -77,12 +77,25 @@ pub enum CudaError {
     InvalidAddressSpace = 717,
     InvalidProgramCounter = 718,
     LaunchFailed = 719,
+    CooperativeLaunchTooLarge = 720,
     NotPermitted = 800,
     NotSupported = 801,
+    StreamCaptureUnsupported = 900,
+    StreamCaptureInvalidated = 901,
+    StreamCaptureMerge = 902,
+    StreamCaptureUnmatched = 903,
+    StreamCaptureUnjoined = 904,
+    StreamCaptureIsolation = 905,
+    StreamCaptureImplicit = 906,
+    CapturedEvent = 907,
+    StreamCaptureWrongThread = 908,
+    Timeout = 909,
+    GraphExecUpdateFailure = 910,
     UnknownError = 999,
 
     // RustaCUDA errors
     InvalidMemoryAllocation = 100_100,
+    HostFunctionPanicked = 100_101,
 
     #[doc(hidden)]
     __Nonexhaustive,
@@ -91,12 +104,13 @@ impl fmt::Display for CudaError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             CudaError::InvalidMemoryAllocation => write!(f, "Invalid memory allocation"),
+            CudaError::HostFunctionPanicked => write!(f, "Host function panicked"),
             CudaError::__Nonexhaustive => write!(f, "__Nonexhaustive"),
             other if (other as u32) <= 999 => {
                 let value = other as u32;
                 let mut ptr: *const c_char = ptr::null();
                 unsafe {
-                    cuda::cuGetErrorString(mem::transmute(value), &mut ptr as *mut *const c_char)
+                    ffi::cuGetErrorString(value, &mut ptr as *mut *const c_char)
                         .to_result()
                         .map_err(|_| fmt::Error)?;
                     let cstr = CStr::from_ptr(ptr);
@@ -110,6 +124,43 @@ impl fmt::Display for CudaError {
 }
 impl Error for CudaError {}
 
+/// Error returned by the `launch!` macro, which records the name of the kernel which failed.
+///
+/// Converts into the underlying `CudaError`, so `?` can still be used in functions returning
+/// `CudaResult`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LaunchError {
+    /// The name of the kernel, as written in the `launch!` call.
+    pub kernel: &'static str,
+    /// The error returned while looking up or launching the kernel.
+    pub error: CudaError,
+}
+impl LaunchError {
+    #[doc(hidden)]
+    pub fn new(kernel: &'static str, error: CudaError) -> Self {
+        LaunchError { kernel, error }
+    }
+}
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Failed to launch kernel `{}`: {}",
+            self.kernel, self.error
+        )
+    }
+}
+impl Error for LaunchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+impl From<LaunchError> for CudaError {
+    fn from(error: LaunchError) -> CudaError {
+        error.error
+    }
+}
+
 /// Result type for most CUDA functions.
 pub type CudaResult<T> = Result<T, CudaError>;
 
@@ -208,3 +259,110 @@ impl ToResult for cudaError_t {
         }
     }
 }
+impl ToResult for ffi::CUresult {
+    fn to_result(self) -> CudaResult<()> {
+        // The driver errors known to this crate, whose discriminants are the driver's codes.
+        const DRIVER_ERRORS: [CudaError; 70] = [
+            CudaError::InvalidValue,
+            CudaError::OutOfMemory,
+            CudaError::NotInitialized,
+            CudaError::Deinitialized,
+            CudaError::ProfilerDisabled,
+            CudaError::ProfilerNotInitialized,
+            CudaError::ProfilerAlreadyStarted,
+            CudaError::ProfilerAlreadyStopped,
+            CudaError::NoDevice,
+            CudaError::InvalidDevice,
+            CudaError::InvalidImage,
+            CudaError::InvalidContext,
+            CudaError::ContextAlreadyCurrent,
+            CudaError::MapFailed,
+            CudaError::UnmapFailed,
+            CudaError::ArrayIsMapped,
+            CudaError::AlreadyMapped,
+            CudaError::NoBinaryForGpu,
+            CudaError::AlreadyAcquired,
+            CudaError::NotMapped,
+            CudaError::NotMappedAsArray,
+            CudaError::NotMappedAsPointer,
+            CudaError::EccUncorrectable,
+            CudaError::UnsupportedLimit,
+            CudaError::ContextAlreadyInUse,
+            CudaError::PeerAccessUnsupported,
+            CudaError::InvalidPtx,
+            CudaError::InvalidGraphicsContext,
+            CudaError::NvlinkUncorrectable,
+            CudaError::InvalidSouce,
+            CudaError::FileNotFound,
+            CudaError::SharedObjectSymbolNotFound,
+            CudaError::SharedObjectInitFailed,
+            CudaError::OperatingSystemError,
+            CudaError::InvalidHandle,
+            CudaError::NotFound,
+            CudaError::NotReady,
+            CudaError::IllegalAddress,
+            CudaError::LaunchOutOfResources,
+            CudaError::LaunchTimeout,
+            CudaError::LaunchIncompatibleTexturing,
+            CudaError::PeerAccessAlreadyEnabled,
+            CudaError::PeerAccessNotEnabled,
+            CudaError::PrimaryContextActive,
+            CudaError::ContextIsDestroyed,
+            CudaError::AssertError,
+            CudaError::TooManyPeers,
+            CudaError::HostMemoryAlreadyRegistered,
+            CudaError::HostMemoryNotRegistered,
+            CudaError::HardwareStackError,
+            CudaError::IllegalInstruction,
+            CudaError::MisalignedAddress,
+            CudaError::InvalidAddressSpace,
+            CudaError::InvalidProgramCounter,
+            CudaError::LaunchFailed,
+            CudaError::CooperativeLaunchTooLarge,
+            CudaError::NotPermitted,
+            CudaError::NotSupported,
+            CudaError::StreamCaptureUnsupported,
+            CudaError::StreamCaptureInvalidated,
+            CudaError::StreamCaptureMerge,
+            CudaError::StreamCaptureUnmatched,
+            CudaError::StreamCaptureUnjoined,
+            CudaError::StreamCaptureIsolation,
+            CudaError::StreamCaptureImplicit,
+            CudaError::CapturedEvent,
+            CudaError::StreamCaptureWrongThread,
+            CudaError::Timeout,
+            CudaError::GraphExecUpdateFailure,
+            CudaError::UnknownError,
+        ];
+
+        if self == 0 {
+            return Ok(());
+        }
+        Err(DRIVER_ERRORS
+            .iter()
+            .cloned()
+            .find(|&error| error as u32 == self)
+            .unwrap_or(CudaError::UnknownError))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_raw_error_codes() {
+        assert_eq!(Ok(()), 0u32.to_result());
+        assert_eq!(Err(CudaError::InvalidValue), 1u32.to_result());
+        assert_eq!(
+            Err(CudaError::CooperativeLaunchTooLarge),
+            720u32.to_result()
+        );
+        assert_eq!(Err(CudaError::StreamCaptureImplicit), 906u32.to_result());
+        assert_eq!(Err(CudaError::GraphExecUpdateFailure), 910u32.to_result());
+        assert_eq!(Err(CudaError::UnknownError), 999u32.to_result());
+        assert_eq!(Err(CudaError::UnknownError), 12345u32.to_result());
+        // RustaCUDA's own errors are never returned by the driver.
+        assert_eq!(Err(CudaError::UnknownError), 100_100u32.to_result());
+    }
+}
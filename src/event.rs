@@ -9,18 +9,25 @@
 //! yet by RustaCUDA).
 //!
 //! Events may be reused multiple times.
+//!
+//! Events created with `EventFlags::INTERPROCESS` can also be shared with other processes through
+//! an [`IpcEventHandle`](struct.IpcEventHandle.html), allowing work in one process to wait for
+//! work submitted by another.
 
 // TODO: I'm not sure that these events are/can be safe by Rust's model of safety; they inherently
 // create state which can be mutated even while an immutable borrow is held.
 
 use crate::cuda_sys::cuda::{
-    cuEventCreate, cuEventDestroy_v2, cuEventElapsedTime, cuEventQuery, cuEventRecord,
-    cuEventSynchronize, CUevent,
+    cuEventCreate, cuEventDestroy_v2, cuEventElapsedTime, cuIpcGetEventHandle,
+    cuIpcOpenEventHandle, CUevent, CUipcEventHandle,
 };
 use crate::error::{CudaError, CudaResult, DropResult, ToResult};
-use crate::stream::Stream;
+use crate::ffi::{cuEventQuery, cuEventRecord, cuEventSynchronize};
+use crate::stream::{Completion, Stream, StreamWaitEventFlags};
 
+use std::fmt;
 use std::mem;
+use std::os::raw::c_char;
 use std::ptr;
 
 bitflags! {
@@ -39,9 +46,8 @@ bitflags! {
         /// Specify that the created event does not need to record timing data.
         const DISABLE_TIMING = 0x2;
 
-        /// Specify that the created event may be used as an interprocess event.
-        /// (not supported yet by RustaCUDA). This flag requires
-        /// `DISABLE_TIMING` to be set as well.
+        /// Specify that the created event may be used as an interprocess event
+        /// (see `Event::ipc_handle`). This flag requires `DISABLE_TIMING` to be set as well.
         const INTERPROCESS = 0x4;
     }
 }
@@ -60,6 +66,49 @@ pub enum EventStatus {
     NotReady,
 }
 
+/// Handle which allows another process to open an interprocess event.
+///
+/// Handles are obtained with `Event::ipc_handle` and opened with `Event::open_ipc_handle`. They can
+/// be sent to other processes as bytes using `to_bytes` and `from_bytes`.
+///
+/// The serialized form is the driver's 64-byte handle alone. An
+/// [`IpcMemHandle`](../memory/struct.IpcMemHandle.html) is serialized the same way, but its
+/// 64-byte handle is followed by the length of the buffer as a little-endian `u64`.
+#[derive(Clone, Copy)]
+pub struct IpcEventHandle {
+    handle: [u8; IpcEventHandle::SERIALIZED_SIZE],
+}
+impl IpcEventHandle {
+    /// The number of bytes produced by `to_bytes` and expected by `from_bytes`.
+    pub const SERIALIZED_SIZE: usize = 64;
+
+    /// Serialize this handle into bytes which can be sent to another process.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.handle.to_vec()
+    }
+
+    /// Reconstruct a handle from bytes produced by `to_bytes`.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `bytes` is not exactly `SERIALIZED_SIZE` bytes long.
+    pub fn from_bytes(bytes: &[u8]) -> CudaResult<Self> {
+        if bytes.len() != IpcEventHandle::SERIALIZED_SIZE {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut handle = [0u8; IpcEventHandle::SERIALIZED_SIZE];
+        handle.copy_from_slice(bytes);
+        Ok(IpcEventHandle { handle })
+    }
+}
+impl fmt::Debug for IpcEventHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IpcEventHandle")
+            .field("handle", &&self.handle[..])
+            .finish()
+    }
+}
+
 /// An event to track work submitted to a stream.
 ///
 /// See the module-level documentation for more information.
@@ -209,6 +258,44 @@ impl Event {
         }
     }
 
+    /// Returns a future which resolves when the event completes.
+    ///
+    /// This is the non-blocking counterpart of `synchronize`, for use from async code. It makes
+    /// `stream` wait on the event, as `Stream::wait_event` does, and returns the stream's
+    /// `completion`, so the future waits for the work captured by the most recent call to
+    /// `record` at the time `wait_async` is called as well as any work queued on `stream` before
+    /// it. Work queued on `stream` afterwards also waits for the event.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::quick_init;
+    /// # use rustacuda::stream::{Stream, StreamFlags};
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _context = quick_init()?;
+    /// use rustacuda::event::{Event, EventFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let waiter = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let event = Event::new(EventFlags::DEFAULT)?;
+    ///
+    /// // do some work ...
+    ///
+    /// event.record(&stream)?;
+    /// let done = event.wait_async(&waiter);
+    /// // Await `done` from an async function using any executor.
+    /// # drop(done);
+    /// # Ok(())
+    /// }
+    /// ```
+    pub fn wait_async(&self, stream: &Stream) -> Completion {
+        match stream.wait_event(self, StreamWaitEventFlags::DEFAULT) {
+            Ok(()) => stream.completion(),
+            Err(e) => Completion::ready(Err(e)),
+        }
+    }
+
     /// Return the duration between two events.
     ///
     /// The duration is computed in milliseconds with a resolution of
@@ -281,6 +368,88 @@ impl Event {
         }
     }
 
+    /// Get a handle which allows other processes to open this event.
+    ///
+    /// The event must have been created with `EventFlags::INTERPROCESS` and
+    /// `EventFlags::DISABLE_TIMING`. Work recorded on this event in this process can then be waited
+    /// on by streams in the process which opened the handle, and vice versa.
+    ///
+    /// # Errors
+    ///
+    /// `CudaError::InvalidValue` is returned if the event was not created with
+    /// `EventFlags::INTERPROCESS`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::quick_init;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _context = quick_init()?;
+    /// use rustacuda::event::{Event, EventFlags};
+    ///
+    /// let event = Event::new(EventFlags::INTERPROCESS | EventFlags::DISABLE_TIMING)?;
+    /// let handle = event.ipc_handle()?;
+    ///
+    /// // send `handle.to_bytes()` to another process ...
+    /// # Ok(())
+    /// }
+    /// ```
+    pub fn ipc_handle(&self) -> CudaResult<IpcEventHandle> {
+        unsafe {
+            let mut raw = CUipcEventHandle {
+                reserved: [0; IpcEventHandle::SERIALIZED_SIZE],
+            };
+            cuIpcGetEventHandle(&mut raw, self.0).to_result()?;
+            let mut handle = [0u8; IpcEventHandle::SERIALIZED_SIZE];
+            for (dst, src) in handle.iter_mut().zip(raw.reserved.iter()) {
+                *dst = *src as u8;
+            }
+            Ok(IpcEventHandle { handle })
+        }
+    }
+
+    /// Open an interprocess event which was exported by another process.
+    ///
+    /// The returned event behaves like one created with `EventFlags::INTERPROCESS` and
+    /// `EventFlags::DISABLE_TIMING`; it can be recorded, queried, synchronized and waited on, but
+    /// cannot be used for timing. Dropping it closes this process' reference to the event.
+    ///
+    /// # Errors
+    ///
+    /// If a CUDA error occurs, returns the error. A handle cannot be opened by the same process
+    /// which exported it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rustacuda::quick_init;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _context = quick_init()?;
+    /// use rustacuda::event::{Event, IpcEventHandle};
+    ///
+    /// # let bytes = [0u8; IpcEventHandle::SERIALIZED_SIZE];
+    /// // `bytes` were received from the exporting process.
+    /// let event = Event::open_ipc_handle(&IpcEventHandle::from_bytes(&bytes)?)?;
+    /// event.synchronize()?;
+    /// # Ok(())
+    /// }
+    /// ```
+    pub fn open_ipc_handle(handle: &IpcEventHandle) -> CudaResult<Event> {
+        unsafe {
+            let mut raw = CUipcEventHandle {
+                reserved: [0; IpcEventHandle::SERIALIZED_SIZE],
+            };
+            for (dst, src) in raw.reserved.iter_mut().zip(handle.handle.iter()) {
+                *dst = *src as c_char;
+            }
+            let mut event: CUevent = ptr::null_mut();
+            cuIpcOpenEventHandle(&mut event, raw).to_result()?;
+            Ok(Event(event))
+        }
+    }
+
     // Get the inner `CUevent` from the `Event`.
     //
     // Necessary for certain CUDA functions outside of this
@@ -355,6 +524,24 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_ipc_handle_requires_interprocess_flag() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let event = Event::new(EventFlags::DISABLE_TIMING)?;
+        assert_eq!(event.ipc_handle().unwrap_err(), CudaError::InvalidValue);
+
+        let event = Event::new(EventFlags::INTERPROCESS | EventFlags::DISABLE_TIMING)?;
+        let handle = event.ipc_handle()?;
+        let bytes = handle.to_bytes();
+        assert_eq!(IpcEventHandle::SERIALIZED_SIZE, bytes.len());
+        assert_eq!(bytes, IpcEventHandle::from_bytes(&bytes)?.to_bytes());
+        assert_eq!(
+            CudaError::InvalidValue,
+            IpcEventHandle::from_bytes(&bytes[1..]).unwrap_err()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_record_with_wrong_context() -> Result<(), Box<dyn Error>> {
         let _context = quick_init()?;
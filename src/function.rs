@@ -1,11 +1,24 @@
 //! Functions and types for working with CUDA kernels.
 
-use crate::context::{CacheConfig, SharedMemoryConfig};
-use crate::error::{CudaResult, ToResult};
+use crate::context::{CacheConfig, CurrentContext, SharedMemoryConfig};
+use crate::device::{Device, DeviceAttribute};
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::ffi;
+use crate::memory::DeviceCopy;
 use crate::module::Module;
+use crate::stream::Stream;
 use cuda_sys::cuda::{self, CUfunction};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::error::Error;
+use std::ffi::c_void;
+use std::fmt;
 use std::marker::PhantomData;
-use std::mem::transmute;
+use std::mem::{self, transmute};
+use std::os::raw::{c_int, c_uint, c_ulong};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
 
 /// Dimensions of a grid, or the number of thread blocks in a kernel launch.
 ///
@@ -149,8 +162,118 @@ pub enum FunctionAttribute {
     /// option "-Xptxas --dlcm=ca" set.
     CacheModeCa = 7,
 
+    /// The maximum size in bytes of dynamically-allocated shared memory which can be used by this
+    /// function. Launches with more dynamic shared memory than this fail.
+    MaxDynamicSharedSizeBytes = 8,
+
+    /// The preferred shared memory carveout, as a percentage of the maximum shared memory, or -1
+    /// if no preference has been set. See `SharedMemoryCarveout`.
+    PreferredSharedMemoryCarveout = 9,
+
+    /// Whether the function can only be launched with a thread block cluster size set.
+    ClusterSizeMustBeSet = 10,
+
+    /// The required width of a thread block cluster in blocks, or 0 if the function does not
+    /// require a cluster size.
+    RequiredClusterWidth = 11,
+
+    /// The required height of a thread block cluster in blocks, or 0 if the function does not
+    /// require a cluster size.
+    RequiredClusterHeight = 12,
+
+    /// The required depth of a thread block cluster in blocks, or 0 if the function does not
+    /// require a cluster size.
+    RequiredClusterDepth = 13,
+
+    /// Whether the function can be launched with a cluster size larger than the portable maximum
+    /// of 8 blocks.
+    NonPortableClusterSizeAllowed = 14,
+
+    /// The block scheduling policy of a function whose clusters have more blocks than fit on a
+    /// multiprocessor.
+    ClusterSchedulingPolicyPreference = 15,
+
     #[doc(hidden)]
-    __Nonexhaustive = 8,
+    __Nonexhaustive = 16,
+}
+
+/// The preferred split of a multiprocessor's unified L1 cache and shared memory for a function.
+///
+/// Only a hint; the driver may choose a different carveout if required to run the function.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SharedMemoryCarveout {
+    /// No preference.
+    Default,
+    /// Prefer the maximum L1 cache size, and the minimum shared memory.
+    MaxL1,
+    /// Prefer the maximum shared memory size, and the minimum L1 cache.
+    MaxShared,
+    /// Prefer this percentage of the maximum shared memory, from 0 to 100.
+    Percent(u32),
+}
+
+/// The writable function attributes, with the value to set them to, for
+/// [Function::set_attribute](struct.Function.html#method.set_attribute).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FunctionAttributeValue {
+    /// The maximum size in bytes of dynamically-allocated shared memory the function can be
+    /// launched with. The static shared memory plus this may not exceed
+    /// `DeviceAttribute::MaxSharedMemoryPerBlockOptin`. Kernels using more than 48 KiB of dynamic
+    /// shared memory must opt in with this attribute.
+    MaxDynamicSharedSizeBytes(u32),
+
+    /// The preferred shared memory carveout.
+    PreferredSharedMemoryCarveout(SharedMemoryCarveout),
+
+    /// The required width of a thread block cluster in blocks. Fails if the cluster size was
+    /// set when the function was compiled.
+    RequiredClusterWidth(u32),
+
+    /// The required height of a thread block cluster in blocks. Fails if the cluster size was
+    /// set when the function was compiled.
+    RequiredClusterHeight(u32),
+
+    /// The required depth of a thread block cluster in blocks. Fails if the cluster size was
+    /// set when the function was compiled.
+    RequiredClusterDepth(u32),
+
+    /// Whether the function may be launched with a cluster size larger than the portable maximum
+    /// of 8 blocks, on devices which support it.
+    NonPortableClusterSizeAllowed(bool),
+}
+impl FunctionAttributeValue {
+    // The attribute and raw value to pass to the driver.
+    fn to_raw(self) -> CudaResult<(FunctionAttribute, c_int)> {
+        let clamp = |value: u32| value.min(c_int::MAX as u32) as c_int;
+        Ok(match self {
+            FunctionAttributeValue::MaxDynamicSharedSizeBytes(bytes) => {
+                (FunctionAttribute::MaxDynamicSharedSizeBytes, clamp(bytes))
+            }
+            FunctionAttributeValue::PreferredSharedMemoryCarveout(carveout) => {
+                let value = match carveout {
+                    SharedMemoryCarveout::Default => -1,
+                    SharedMemoryCarveout::MaxL1 => 0,
+                    SharedMemoryCarveout::MaxShared => 100,
+                    SharedMemoryCarveout::Percent(percent) if percent <= 100 => percent as c_int,
+                    SharedMemoryCarveout::Percent(_) => return Err(CudaError::InvalidValue),
+                };
+                (FunctionAttribute::PreferredSharedMemoryCarveout, value)
+            }
+            FunctionAttributeValue::RequiredClusterWidth(width) => {
+                (FunctionAttribute::RequiredClusterWidth, clamp(width))
+            }
+            FunctionAttributeValue::RequiredClusterHeight(height) => {
+                (FunctionAttribute::RequiredClusterHeight, clamp(height))
+            }
+            FunctionAttributeValue::RequiredClusterDepth(depth) => {
+                (FunctionAttribute::RequiredClusterDepth, clamp(depth))
+            }
+            FunctionAttributeValue::NonPortableClusterSizeAllowed(allowed) => (
+                FunctionAttribute::NonPortableClusterSizeAllowed,
+                allowed as c_int,
+            ),
+        })
+    }
 }
 
 /// Handle to a global kernel function.
@@ -191,17 +314,59 @@ impl<'a> Function<'a> {
     pub fn get_attribute(&self, attr: FunctionAttribute) -> CudaResult<i32> {
         unsafe {
             let mut val = 0i32;
-            cuda::cuFuncGetAttribute(
-                &mut val as *mut i32,
-                // This should be safe, as the repr and values of FunctionAttribute should match.
-                ::std::mem::transmute(attr),
-                self.inner,
-            )
-            .to_result()?;
+            // The values of FunctionAttribute match the driver's, but newer attributes are missing
+            // from the cuda-sys enum, so pass the raw value.
+            ffi::cuFuncGetAttribute(&mut val as *mut i32, attr as u32, self.inner).to_result()?;
             Ok(val)
         }
     }
 
+    /// Sets one of the writable attributes of this function.
+    ///
+    /// The attribute affects all subsequent launches of this function.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if the value is out of range for the attribute, such as a carveout
+    /// over 100 percent or more dynamic shared memory than the device allows, or `NotSupported` if
+    /// the device does not support the attribute.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::module::Module;
+    /// # use std::ffi::CString;
+    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// # let module = Module::load_from_string(&ptx)?;
+    /// # let name = CString::new("sum")?;
+    /// use rustacuda::context::CurrentContext;
+    /// use rustacuda::device::DeviceAttribute;
+    /// use rustacuda::function::{FunctionAttribute, FunctionAttributeValue, SharedMemoryCarveout};
+    ///
+    /// let mut function = module.get_function(&name)?;
+    ///
+    /// // Allow launches with as much dynamic shared memory as the device supports.
+    /// let device = CurrentContext::get_device()?;
+    /// let optin = device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)?;
+    /// let static_bytes = function.get_attribute(FunctionAttribute::SharedMemorySizeBytes)?;
+    /// function.set_attribute(FunctionAttributeValue::MaxDynamicSharedSizeBytes(
+    ///     (optin - static_bytes) as u32,
+    /// ))?;
+    /// function.set_attribute(FunctionAttributeValue::PreferredSharedMemoryCarveout(
+    ///     SharedMemoryCarveout::MaxShared,
+    /// ))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_attribute(&mut self, value: FunctionAttributeValue) -> CudaResult<()> {
+        let (attr, value) = value.to_raw()?;
+        unsafe { ffi::cuFuncSetAttribute(self.inner, attr as u32, value).to_result() }
+    }
+
     /// Sets the preferred cache configuration for this function.
     ///
     /// On devices where L1 cache and shared memory use the same hardware resources, this sets the
@@ -235,37 +400,1272 @@ impl<'a> Function<'a> {
         unsafe { cuda::cuFuncSetCacheConfig(self.inner, transmute(config)).to_result() }
     }
 
-    /// Sets the preferred shared memory configuration for this function.
+    /// Sets the preferred shared memory configuration for this function.
+    ///
+    /// On devices with configurable shared memory banks, this function will set this function's
+    /// shared memory bank size which is used for subsequent launches of this function. If not set,
+    /// the context-wide setting will be used instead.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::module::Module;
+    /// # use std::ffi::CString;
+    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// # let module = Module::load_from_string(&ptx)?;
+    /// # let name = CString::new("sum")?;
+    /// use rustacuda::context::SharedMemoryConfig;
+    /// let mut function = module.get_function(&name)?;
+    /// function.set_shared_memory_config(SharedMemoryConfig::EightByteBankSize)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_shared_memory_config(&mut self, cfg: SharedMemoryConfig) -> CudaResult<()> {
+        unsafe { cuda::cuFuncSetSharedMemConfig(self.inner, transmute(cfg)).to_result() }
+    }
+
+    /// Returns the maximum number of blocks of this function which can be resident on one
+    /// multiprocessor at once, when launched with `block_size` threads per block and
+    /// `dynamic_shared_mem_bytes` bytes of dynamic shared memory per block.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::module::Module;
+    /// # use std::ffi::CString;
+    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// # let module = Module::load_from_string(&ptx)?;
+    /// let function = module.get_function(&CString::new("sum")?)?;
+    /// let blocks = function.max_active_blocks_per_multiprocessor(256, 0)?;
+    /// println!("{} blocks of 256 threads fit on each multiprocessor", blocks);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_active_blocks_per_multiprocessor(
+        &self,
+        block_size: u32,
+        dynamic_shared_mem_bytes: usize,
+    ) -> CudaResult<u32> {
+        unsafe {
+            let mut blocks = 0;
+            cuda::cuOccupancyMaxActiveBlocksPerMultiprocessor(
+                &mut blocks,
+                self.inner,
+                block_size as c_int,
+                dynamic_shared_mem_bytes,
+            )
+            .to_result()?;
+            Ok(blocks as u32)
+        }
+    }
+
+    /// Returns the block size which achieves the maximum occupancy for this function, and the
+    /// smallest grid size which reaches that occupancy on the whole device.
+    ///
+    /// Every block uses `dynamic_shared_mem_bytes` bytes of dynamic shared memory. If
+    /// `block_size_limit` is non-zero, no block size larger than it is suggested.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::module::Module;
+    /// # use std::ffi::CString;
+    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// # let module = Module::load_from_string(&ptx)?;
+    /// let function = module.get_function(&CString::new("sum")?)?;
+    /// let suggestion = function.max_potential_block_size(0, 0)?;
+    /// let n = 1_000_000;
+    /// let blocks = (n + suggestion.block_size - 1) / suggestion.block_size;
+    /// println!("Launch {} blocks of {} threads", blocks, suggestion.block_size);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_potential_block_size(
+        &self,
+        dynamic_shared_mem_bytes: usize,
+        block_size_limit: u32,
+    ) -> CudaResult<PotentialBlockSize> {
+        unsafe {
+            let (mut min_grid_size, mut block_size) = (0, 0);
+            cuda::cuOccupancyMaxPotentialBlockSize(
+                &mut min_grid_size,
+                &mut block_size,
+                self.inner,
+                None,
+                dynamic_shared_mem_bytes,
+                block_size_limit as c_int,
+            )
+            .to_result()?;
+            Ok(PotentialBlockSize {
+                min_grid_size: min_grid_size as u32,
+                block_size: block_size as u32,
+            })
+        }
+    }
+
+    /// Like `max_potential_block_size`, but for functions whose dynamic shared memory depends on
+    /// the block size.
+    ///
+    /// `shared_mem_for_block_size` is called with candidate block sizes and must return the number
+    /// of bytes of dynamic shared memory a block of that size needs. It is only called during this
+    /// function.
+    ///
+    /// # Panics:
+    ///
+    /// If `shared_mem_for_block_size` panics, the panic is propagated once the driver returns.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::module::Module;
+    /// # use std::ffi::CString;
+    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// # let module = Module::load_from_string(&ptx)?;
+    /// let function = module.get_function(&CString::new("sum")?)?;
+    /// // Each thread needs one f32 of shared memory.
+    /// let suggestion = function
+    ///     .max_potential_block_size_with(|block_size| block_size as usize * 4, 0)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_potential_block_size_with<F>(
+        &self,
+        mut shared_mem_for_block_size: F,
+        block_size_limit: u32,
+    ) -> CudaResult<PotentialBlockSize>
+    where
+        F: FnMut(u32) -> usize,
+    {
+        let mut callback: &mut dyn FnMut(u32) -> usize = &mut shared_mem_for_block_size;
+        let callback_ptr = NonNull::from(&mut callback).cast::<c_void>();
+
+        // The driver's callback takes no user data, so the closure is passed through a
+        // thread-local. Restore the previous value in case this is a nested call.
+        let previous = SHARED_MEM_CALLBACK.with(|c| c.replace(Some(callback_ptr)));
+        let result = unsafe {
+            let (mut min_grid_size, mut block_size) = (0, 0);
+            cuda::cuOccupancyMaxPotentialBlockSize(
+                &mut min_grid_size,
+                &mut block_size,
+                self.inner,
+                Some(shared_mem_callback),
+                0,
+                block_size_limit as c_int,
+            )
+            .to_result()
+            .map(|()| PotentialBlockSize {
+                min_grid_size: min_grid_size as u32,
+                block_size: block_size as u32,
+            })
+        };
+        SHARED_MEM_CALLBACK.with(|c| c.set(previous));
+
+        if let Some(payload) = SHARED_MEM_CALLBACK_PANIC.with(|p| p.borrow_mut().take()) {
+            panic::resume_unwind(payload);
+        }
+        result
+    }
+
+    /// Returns the number of bytes of dynamic shared memory available to each block when
+    /// `num_blocks` blocks of `block_size` threads are resident on one multiprocessor.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::module::Module;
+    /// # use std::ffi::CString;
+    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// # let module = Module::load_from_string(&ptx)?;
+    /// let function = module.get_function(&CString::new("sum")?)?;
+    /// let bytes = function.available_dynamic_shared_memory_per_block(4, 256)?;
+    /// println!("Each of 4 blocks of 256 threads can use {} bytes", bytes);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn available_dynamic_shared_memory_per_block(
+        &self,
+        num_blocks: u32,
+        block_size: u32,
+    ) -> CudaResult<usize> {
+        unsafe {
+            let mut bytes = 0;
+            ffi::cuOccupancyAvailableDynamicSMemPerBlock(
+                &mut bytes,
+                self.inner,
+                num_blocks as c_int,
+                block_size as c_int,
+            )
+            .to_result()?;
+            Ok(bytes)
+        }
+    }
+
+    /// Returns the registers, memory and architecture versions this function was compiled with.
+    ///
+    /// This bundles the values of several `FunctionAttribute`s.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::module::Module;
+    /// # use std::ffi::CString;
+    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// # let module = Module::load_from_string(&ptx)?;
+    /// let function = module.get_function(&CString::new("sum")?)?;
+    /// println!("{}", function.resource_usage()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resource_usage(&self) -> CudaResult<ResourceUsage> {
+        let get = |attr| -> CudaResult<u32> { Ok(self.get_attribute(attr)? as u32) };
+        Ok(ResourceUsage {
+            registers_per_thread: get(FunctionAttribute::NumRegisters)?,
+            local_bytes_per_thread: get(FunctionAttribute::LocalSizeBytes)?,
+            static_shared_bytes: get(FunctionAttribute::SharedMemorySizeBytes)?,
+            const_bytes: get(FunctionAttribute::ConstSizeBytes)?,
+            max_threads_per_block: get(FunctionAttribute::MaxThreadsPerBlock)?,
+            ptx_version: get(FunctionAttribute::PtxVersion)?,
+            binary_version: get(FunctionAttribute::BinaryVersion)?,
+        })
+    }
+
+    pub(crate) fn to_inner(&self) -> CUfunction {
+        self.inner
+    }
+}
+
+thread_local! {
+    // The closure being used by `max_potential_block_size_with` on this thread, as a pointer to a
+    // `&mut dyn FnMut(u32) -> usize`, and the payload of any panic it raised.
+    static SHARED_MEM_CALLBACK: Cell<Option<NonNull<c_void>>> = const { Cell::new(None) };
+    static SHARED_MEM_CALLBACK_PANIC: RefCell<Option<Box<dyn Any + Send>>> = RefCell::new(None);
+}
+
+unsafe extern "C" fn shared_mem_callback(block_size: c_int) -> c_ulong {
+    if SHARED_MEM_CALLBACK_PANIC.with(|p| p.borrow().is_some()) {
+        return 0;
+    }
+    let callback = match SHARED_MEM_CALLBACK.with(Cell::get) {
+        Some(callback) => callback.cast::<&mut dyn FnMut(u32) -> usize>().as_ptr(),
+        None => return 0,
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| (*callback)(block_size as u32))) {
+        Ok(bytes) => bytes as c_ulong,
+        Err(payload) => {
+            SHARED_MEM_CALLBACK_PANIC.with(|p| *p.borrow_mut() = Some(payload));
+            0
+        }
+    }
+}
+
+/// A block size suggested by `Function::max_potential_block_size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PotentialBlockSize {
+    /// The smallest number of blocks needed to reach maximum occupancy on the whole device.
+    pub min_grid_size: u32,
+    /// The number of threads per block which achieves maximum occupancy.
+    pub block_size: u32,
+}
+
+/// The resources used by a function, as returned by `Function::resource_usage`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ResourceUsage {
+    /// The number of registers used by each thread.
+    pub registers_per_thread: u32,
+    /// The bytes of local memory used by each thread.
+    pub local_bytes_per_thread: u32,
+    /// The bytes of statically-allocated shared memory used by each block.
+    pub static_shared_bytes: u32,
+    /// The bytes of constant memory used by the function.
+    pub const_bytes: u32,
+    /// The maximum number of threads per block the function can be launched with on this device.
+    pub max_threads_per_block: u32,
+    /// The PTX virtual architecture version, encoded as major version * 10 + minor version.
+    pub ptx_version: u32,
+    /// The binary architecture version, encoded as major version * 10 + minor version.
+    pub binary_version: u32,
+}
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} registers, {} bytes local memory per thread; {} bytes static shared memory, {} \
+             bytes constant memory; at most {} threads per block; PTX {}.{}, binary sm_{}",
+            self.registers_per_thread,
+            self.local_bytes_per_thread,
+            self.static_shared_bytes,
+            self.const_bytes,
+            self.max_threads_per_block,
+            self.ptx_version / 10,
+            self.ptx_version % 10,
+            self.binary_version
+        )
+    }
+}
+
+/// Grid size, block size, dynamic shared memory and stream for a kernel launch.
+///
+/// A `LaunchConfig` is created with `new` and adjusted with the builder methods. `grid` and
+/// `block` accept the same values as in `launch!`: `u32` values, tuples of up to three `u32`
+/// values, or `GridSize` and `BlockSize` structures.
+///
+/// Mistakes in a launch configuration are normally only reported by the launch itself, as a bare
+/// `InvalidValue` or `LaunchOutOfResources`. [`validate`](#method.validate) checks the
+/// configuration against the limits of the device and the function beforehand and describes what
+/// is wrong.
+///
+/// # Examples:
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::function::LaunchConfig;
+/// use rustacuda::module::Module;
+/// use rustacuda::stream::{Stream, StreamFlags};
+/// use std::ffi::CString;
+///
+/// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+/// let module = Module::load_from_string(&ptx)?;
+/// let sum = module.get_function(&CString::new("sum")?)?;
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+///
+/// let config = LaunchConfig::new((64, 1), 256)
+///     .shared_mem_bytes(1024)
+///     .stream(&stream);
+/// config.validate(&sum)?;
+///
+/// let too_big = LaunchConfig::new(1, (64, 64));
+/// let error = too_big.validate(&sum).unwrap_err();
+/// println!("{}", error);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LaunchConfig<'a> {
+    grid: GridSize,
+    block: BlockSize,
+    shared_mem_bytes: u32,
+    stream: Option<&'a Stream>,
+}
+impl<'a> LaunchConfig<'a> {
+    /// Create a launch configuration with no dynamic shared memory and no stream.
+    pub fn new<G: Into<GridSize>, B: Into<BlockSize>>(grid: G, block: B) -> Self {
+        LaunchConfig {
+            grid: grid.into(),
+            block: block.into(),
+            shared_mem_bytes: 0,
+            stream: None,
+        }
+    }
+
+    /// Set the number of thread blocks in the grid.
+    pub fn grid<G: Into<GridSize>>(mut self, grid: G) -> Self {
+        self.grid = grid.into();
+        self
+    }
+
+    /// Set the number of threads in each block.
+    pub fn block<B: Into<BlockSize>>(mut self, block: B) -> Self {
+        self.block = block.into();
+        self
+    }
+
+    /// Set the number of bytes of dynamic shared memory per block.
+    pub fn shared_mem_bytes(mut self, bytes: u32) -> Self {
+        self.shared_mem_bytes = bytes;
+        self
+    }
+
+    /// Set the stream to launch on. Without a stream, kernels are launched on the legacy default
+    /// stream.
+    pub fn stream(mut self, stream: &'a Stream) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Returns the number of thread blocks in the grid.
+    pub fn get_grid(&self) -> &GridSize {
+        &self.grid
+    }
+
+    /// Returns the number of threads in each block.
+    pub fn get_block(&self) -> &BlockSize {
+        &self.block
+    }
+
+    /// Returns the number of bytes of dynamic shared memory per block.
+    pub fn get_shared_mem_bytes(&self) -> u32 {
+        self.shared_mem_bytes
+    }
+
+    /// Returns the stream to launch on, if one was set.
+    pub fn get_stream(&self) -> Option<&'a Stream> {
+        self.stream
+    }
+
+    // Launches given an explicit stream reject configurations which name a different one, rather
+    // than silently launching somewhere the caller did not expect.
+    fn check_stream(&self, stream: &Stream) -> CudaResult<()> {
+        match self.stream {
+            Some(own) if own.as_inner() != stream.as_inner() => Err(CudaError::InvalidValue),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that this configuration can be used to launch `function` on the current device.
+    ///
+    /// This checks that every grid and block dimension is non-zero and within the device's
+    /// limits, that the number of threads per block is within the limits of both the device and
+    /// the function, and that the requested dynamic shared memory is within the function's
+    /// `FunctionAttribute::MaxDynamicSharedSizeBytes`.
+    ///
+    /// Passing validation does not guarantee that the launch succeeds, but failing it guarantees
+    /// that the launch would fail.
+    ///
+    /// # Errors:
+    ///
+    /// Returns the first violated limit, or `LaunchConfigError::Cuda` if the limits could not be
+    /// queried.
+    pub fn validate(&self, function: &Function) -> Result<(), LaunchConfigError> {
+        let dimensions = [
+            ("grid.x", self.grid.x, DeviceAttribute::MaxGridDimX),
+            ("grid.y", self.grid.y, DeviceAttribute::MaxGridDimY),
+            ("grid.z", self.grid.z, DeviceAttribute::MaxGridDimZ),
+            ("block.x", self.block.x, DeviceAttribute::MaxBlockDimX),
+            ("block.y", self.block.y, DeviceAttribute::MaxBlockDimY),
+            ("block.z", self.block.z, DeviceAttribute::MaxBlockDimZ),
+        ];
+        for &(dimension, size, _) in &dimensions {
+            if size == 0 {
+                return Err(LaunchConfigError::ZeroSize { dimension });
+            }
+        }
+
+        let device = CurrentContext::get_device()?;
+        let device_limit = |attr| -> CudaResult<u32> { Ok(device.get_attribute(attr)? as u32) };
+        for &(dimension, size, attr) in &dimensions {
+            let max = device_limit(attr)?;
+            if size > max {
+                return Err(LaunchConfigError::DimensionTooLarge {
+                    dimension,
+                    size,
+                    max,
+                });
+            }
+        }
+
+        // Each dimension fits in a u32 and is within the device limits, so this cannot overflow.
+        let threads = u64::from(self.block.x) * u64::from(self.block.y) * u64::from(self.block.z);
+        let max = device_limit(DeviceAttribute::MaxThreadsPerBlock)?;
+        if threads > u64::from(max) {
+            return Err(LaunchConfigError::TooManyThreads {
+                threads: threads as u32,
+                max,
+            });
+        }
+        let max = function.get_attribute(FunctionAttribute::MaxThreadsPerBlock)? as u32;
+        if threads > u64::from(max) {
+            return Err(LaunchConfigError::TooManyThreadsForFunction {
+                threads: threads as u32,
+                max,
+            });
+        }
+
+        // The dynamic shared memory limit defaults to what is left of the device's per-block
+        // limit, and can be raised with `FunctionAttributeValue::MaxDynamicSharedSizeBytes`.
+        let static_bytes = function.get_attribute(FunctionAttribute::SharedMemorySizeBytes)? as u32;
+        let max_dynamic =
+            function.get_attribute(FunctionAttribute::MaxDynamicSharedSizeBytes)? as u32;
+        let max = static_bytes.saturating_add(max_dynamic);
+        if self.shared_mem_bytes > max_dynamic {
+            return Err(LaunchConfigError::TooMuchSharedMemory {
+                static_bytes,
+                dynamic_bytes: self.shared_mem_bytes,
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that this configuration can be used to launch `function` cooperatively on the current
+    /// device.
+    ///
+    /// In addition to the checks made by [`validate`](#method.validate), this checks that the
+    /// device supports cooperative launches (`DeviceAttribute::CooperativeLaunch`) and that every
+    /// block of the grid can be resident on the device at the same time, as reported by
+    /// `Function::max_active_blocks_per_multiprocessor`. Cooperative kernels which synchronize
+    /// the whole grid would otherwise deadlock, so the driver refuses such launches.
+    ///
+    /// # Errors:
+    ///
+    /// Returns the first violated limit, or `LaunchConfigError::Cuda` if the limits could not be
+    /// queried.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::context::CurrentContext;
+    /// use rustacuda::device::DeviceAttribute;
+    /// use rustacuda::function::LaunchConfig;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let sum = module.get_function(&CString::new("sum")?)?;
+    ///
+    /// // Launch one block per multiprocessor, which always fits if the block itself does.
+    /// let device = CurrentContext::get_device()?;
+    /// let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u32;
+    /// let config = LaunchConfig::new(multiprocessors, 128);
+    /// if device.get_attribute(DeviceAttribute::CooperativeLaunch)? != 0 {
+    ///     config.validate_cooperative(&sum)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_cooperative(&self, function: &Function) -> Result<(), LaunchConfigError> {
+        self.validate(function)?;
+        let device = CurrentContext::get_device()?;
+        self.check_co_resident(function, device, DeviceAttribute::CooperativeLaunch)
+    }
+
+    // Checks that `device` supports the kind of cooperative launch given by `attr` and that the
+    // whole grid fits on it at once.
+    pub(crate) fn check_co_resident(
+        &self,
+        function: &Function,
+        device: Device,
+        attr: DeviceAttribute,
+    ) -> Result<(), LaunchConfigError> {
+        if device.get_attribute(attr)? == 0 {
+            return Err(LaunchConfigError::CooperativeLaunchUnsupported);
+        }
+
+        let threads = u64::from(self.block.x) * u64::from(self.block.y) * u64::from(self.block.z);
+        let threads = cmp::min(threads, u64::from(u32::MAX)) as u32;
+        let per_multiprocessor = function
+            .max_active_blocks_per_multiprocessor(threads, self.shared_mem_bytes as usize)?;
+        let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u32;
+        let max = u64::from(per_multiprocessor) * u64::from(multiprocessors);
+        let blocks = u64::from(self.grid.x) * u64::from(self.grid.y) * u64::from(self.grid.z);
+        if blocks > max {
+            return Err(LaunchConfigError::TooManyBlocksForCooperativeLaunch { blocks, max });
+        }
+        Ok(())
+    }
+}
+impl<'a> From<&LaunchConfig<'a>> for LaunchConfig<'a> {
+    fn from(other: &LaunchConfig<'a>) -> LaunchConfig<'a> {
+        other.clone()
+    }
+}
+
+/// A limit violated by a [`LaunchConfig`](struct.LaunchConfig.html), as reported by
+/// `LaunchConfig::validate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LaunchConfigError {
+    /// A grid or block dimension is zero.
+    ZeroSize {
+        /// The dimension, such as `"grid.x"` or `"block.z"`.
+        dimension: &'static str,
+    },
+    /// A grid or block dimension is larger than the device allows.
+    DimensionTooLarge {
+        /// The dimension, such as `"grid.x"` or `"block.z"`.
+        dimension: &'static str,
+        /// The requested size.
+        size: u32,
+        /// The device's limit.
+        max: u32,
+    },
+    /// The block has more threads than the device allows.
+    TooManyThreads {
+        /// The number of threads per block.
+        threads: u32,
+        /// `DeviceAttribute::MaxThreadsPerBlock`.
+        max: u32,
+    },
+    /// The block has more threads than the function can be launched with, usually because of its
+    /// register usage.
+    TooManyThreadsForFunction {
+        /// The number of threads per block.
+        threads: u32,
+        /// `FunctionAttribute::MaxThreadsPerBlock`.
+        max: u32,
+    },
+    /// The dynamic shared memory exceeds what the function may be launched with, so its static
+    /// plus dynamic shared memory exceeds what a block of it may use.
+    TooMuchSharedMemory {
+        /// `FunctionAttribute::SharedMemorySizeBytes`.
+        static_bytes: u32,
+        /// The requested dynamic shared memory.
+        dynamic_bytes: u32,
+        /// `FunctionAttribute::SharedMemorySizeBytes` plus
+        /// `FunctionAttribute::MaxDynamicSharedSizeBytes`.
+        max: u32,
+    },
+    /// The device does not support cooperative launches.
+    CooperativeLaunchUnsupported,
+    /// A cooperative launch has more blocks than can be resident on the device at once.
+    TooManyBlocksForCooperativeLaunch {
+        /// The number of blocks in the grid.
+        blocks: u64,
+        /// The number of blocks of this size which fit on the device at once.
+        max: u64,
+    },
+    /// Querying the device or function limits failed.
+    Cuda(CudaError),
+}
+impl LaunchConfigError {
+    // The error the driver would have returned for the launch.
+    pub(crate) fn into_cuda_error(self) -> CudaError {
+        match self {
+            LaunchConfigError::CooperativeLaunchUnsupported => CudaError::NotSupported,
+            LaunchConfigError::TooManyBlocksForCooperativeLaunch { .. } => {
+                CudaError::CooperativeLaunchTooLarge
+            }
+            LaunchConfigError::Cuda(error) => error,
+            _ => CudaError::InvalidValue,
+        }
+    }
+}
+impl fmt::Display for LaunchConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LaunchConfigError::ZeroSize { dimension } => write!(
+                f,
+                "Launch configuration has {} = 0, but every grid and block dimension must be at \
+                 least 1",
+                dimension
+            ),
+            LaunchConfigError::DimensionTooLarge {
+                dimension,
+                size,
+                max,
+            } => write!(
+                f,
+                "Launch configuration has {} = {}, but the device allows at most {}",
+                dimension, size, max
+            ),
+            LaunchConfigError::TooManyThreads { threads, max } => write!(
+                f,
+                "Launch configuration has {} threads per block, but the device allows at most {}",
+                threads, max
+            ),
+            LaunchConfigError::TooManyThreadsForFunction { threads, max } => write!(
+                f,
+                "Launch configuration has {} threads per block, but the function can be launched \
+                 with at most {}",
+                threads, max
+            ),
+            LaunchConfigError::TooMuchSharedMemory {
+                static_bytes,
+                dynamic_bytes,
+                max,
+            } => write!(
+                f,
+                "Launch configuration needs {} bytes of shared memory per block ({} static + {} \
+                 dynamic), but the function allows at most {}",
+                u64::from(static_bytes) + u64::from(dynamic_bytes),
+                static_bytes,
+                dynamic_bytes,
+                max
+            ),
+            LaunchConfigError::CooperativeLaunchUnsupported => {
+                write!(f, "The device does not support cooperative launches")
+            }
+            LaunchConfigError::TooManyBlocksForCooperativeLaunch { blocks, max } => write!(
+                f,
+                "Cooperative launch has {} blocks, but only {} blocks of this size fit on the \
+                 device at once",
+                blocks, max
+            ),
+            LaunchConfigError::Cuda(error) => {
+                write!(f, "Failed to query launch limits: {}", error)
+            }
+        }
+    }
+}
+impl Error for LaunchConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LaunchConfigError::Cuda(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+impl From<CudaError> for LaunchConfigError {
+    fn from(error: CudaError) -> Self {
+        LaunchConfigError::Cuda(error)
+    }
+}
+
+/// The parameter list of a kernel, used to type-check the arguments of a
+/// [`Kernel`](struct.Kernel.html).
+///
+/// This is implemented for tuples of up to twelve `DeviceCopy` values, where each element is one
+/// kernel parameter, in order. The unit type `()` is a kernel without parameters.
+///
+/// For kernels with many parameters, it can also be derived for a struct whose fields are the
+/// kernel parameters in order. The fields must all implement `DeviceCopy`. A derived struct is
+/// packed into a single parameter buffer, with each field aligned as the kernel expects, and
+/// launched through the driver's `CU_LAUNCH_PARAM_BUFFER_POINTER` mechanism. If it also derives
+/// `Clone` and `Copy`, one value can be reused for several launches.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate rustacuda;
+/// # use std::error::Error;
+/// use rustacuda::function::LaunchConfig;
+/// use rustacuda::memory::*;
+/// use rustacuda::module::Module;
+/// use rustacuda::stream::{Stream, StreamFlags};
+/// use std::ffi::CString;
+///
+/// #[derive(Clone, Copy, KernelArgs)]
+/// struct SumArgs {
+///     x: DevicePointer<f32>,
+///     y: DevicePointer<f32>,
+///     out: DevicePointer<f32>,
+///     len: u32,
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = rustacuda::quick_init()?;
+/// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+/// let module = Module::load_from_string(&ptx)?;
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+/// let sum = module.get_kernel::<SumArgs>("sum")?;
+///
+/// let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+/// let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+/// let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+/// let args = SumArgs {
+///     x: x.as_device_ptr(),
+///     y: y.as_device_ptr(),
+///     out: out.as_device_ptr(),
+///     len: 10,
+/// };
+/// unsafe {
+///     sum.launch(&stream, LaunchConfig::new(1, 10), args)?;
+///     sum.launch(&stream, LaunchConfig::new(1, 10), args)?;
+/// }
+/// stream.synchronize()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Safety
+///
+/// Implementations must return one pointer per kernel parameter, each pointing to a value with the
+/// same size and layout as the parameter, and valid for as long as `self` is borrowed. If a
+/// parameter buffer is returned, it must contain every parameter at the offset the kernel expects.
+pub unsafe trait KernelArgs {
+    #[doc(hidden)]
+    fn kernel_params(&self) -> Vec<*mut c_void>;
+
+    #[doc(hidden)]
+    fn param_buffer(&self) -> Option<ParamBuffer> {
+        None
+    }
+}
+
+// Hidden implementation detail used by `#[derive(KernelArgs)]`. Kernel parameters packed into a
+// single buffer, each at an offset aligned for its type, matching the layout of the kernel's
+// parameter space.
+#[doc(hidden)]
+#[derive(Debug, Default, Clone)]
+pub struct ParamBuffer {
+    bytes: Vec<u8>,
+}
+impl ParamBuffer {
+    #[doc(hidden)]
+    pub fn new() -> Self {
+        ParamBuffer { bytes: Vec::new() }
+    }
+
+    #[doc(hidden)]
+    pub fn push<T: DeviceCopy>(&mut self, value: &T) {
+        // Alignments are powers of two.
+        let align = mem::align_of::<T>();
+        let offset = (self.bytes.len() + align - 1) & !(align - 1);
+        self.bytes.resize(offset + mem::size_of::<T>(), 0);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                value as *const T as *const u8,
+                self.bytes[offset..].as_mut_ptr(),
+                mem::size_of::<T>(),
+            );
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+macro_rules! impl_kernel_args {
+    ($($name:ident : $index:tt),*) => {
+        unsafe impl<$($name: DeviceCopy),*> KernelArgs for ($($name,)*) {
+            fn kernel_params(&self) -> Vec<*mut c_void> {
+                vec![$(&self.$index as *const $name as *mut c_void),*]
+            }
+        }
+    };
+}
+impl_kernel_args!();
+impl_kernel_args!(A: 0);
+impl_kernel_args!(A: 0, B: 1);
+impl_kernel_args!(A: 0, B: 1, C: 2);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+impl_kernel_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11);
+
+/// Handle to a kernel function with a known parameter list.
+///
+/// Created by [`Module::get_kernel`](../module/struct.Module.html#method.get_kernel). `Args` is
+/// a tuple of the kernel's parameter types, and `launch` only accepts arguments of exactly that
+/// type, so passing arguments in the wrong order or of the wrong type is a compile error at each
+/// call site rather than memory corruption at runtime:
+///
+/// ```compile_fail
+/// # use rustacuda::function::LaunchConfig;
+/// # use rustacuda::memory::*;
+/// # use rustacuda::module::Module;
+/// # use rustacuda::stream::*;
+/// # use std::ffi::CString;
+/// # let _ctx = rustacuda::quick_init().unwrap();
+/// # let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+/// # let module = Module::load_from_string(&ptx).unwrap();
+/// # let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+/// let mut buffer = DeviceBuffer::from_slice(&[0.0f32; 10]).unwrap();
+/// let sum = module
+///     .get_kernel::<(DevicePointer<f32>, DevicePointer<f32>, DevicePointer<f32>, u32)>("sum")
+///     .unwrap();
+/// let ptr = buffer.as_device_ptr();
+/// unsafe {
+///     // `len` returns a usize, but the kernel expects a u32.
+///     sum.launch(&stream, LaunchConfig::new(1, 10), (ptr, ptr, ptr, buffer.len()))
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Kernel<'a, Args> {
+    function: Function<'a>,
+    args: PhantomData<fn(Args)>,
+}
+impl<'a, Args: KernelArgs> Kernel<'a, Args> {
+    /// Wrap a function whose parameter list is known to be `Args`.
+    ///
+    /// # Safety
+    ///
+    /// The kernel must take parameters with the same number, order and types as the elements of
+    /// `Args`. This cannot be checked by the driver.
+    pub unsafe fn from_function(function: Function<'a>) -> Self {
+        Kernel {
+            function,
+            args: PhantomData,
+        }
+    }
+
+    /// Returns the untyped function handle, for example to query its attributes.
+    pub fn function(&self) -> &Function<'a> {
+        &self.function
+    }
+
+    /// Returns the untyped function handle mutably, for example to set its cache configuration.
+    pub fn function_mut(&mut self) -> &mut Function<'a> {
+        &mut self.function
+    }
+
+    /// Launch the kernel asynchronously on `stream` with the given arguments.
+    ///
+    /// If `config` also sets a stream, it must be `stream`; use `launch_with` to launch on the
+    /// stream set in `config` alone.
+    ///
+    /// # Safety
+    ///
+    /// As with `launch!`, the kernel itself may perform arbitrary memory accesses. The caller must
+    /// ensure that any memory passed to the kernel is valid for it to use, and that the host does
+    /// not access memory the kernel may write until the stream has been synchronized.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `config` sets a stream other than `stream`. Otherwise, returns an
+    /// error if the launch itself fails, for example because of an invalid launch configuration.
+    /// Errors caused by the kernel are reported by later calls, such as `Stream::synchronize`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::function::LaunchConfig;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::module::Module;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let sum = module
+    ///     .get_kernel::<(DevicePointer<f32>, DevicePointer<f32>, DevicePointer<f32>, u32)>("sum")?;
+    ///
+    /// let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+    /// let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+    /// let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+    /// let args = (x.as_device_ptr(), y.as_device_ptr(), out.as_device_ptr(), 10);
+    /// unsafe {
+    ///     sum.launch(&stream, LaunchConfig::new(1, 10), args)?;
+    /// }
+    /// stream.synchronize()?;
+    ///
+    /// let mut host = [0.0f32; 10];
+    /// out.copy_to(&mut host[..])?;
+    /// assert_eq!([3.0f32; 10], host);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn launch<'s, C: Into<LaunchConfig<'s>>>(
+        &self,
+        stream: &Stream,
+        config: C,
+        args: Args,
+    ) -> CudaResult<()> {
+        let config = config.into();
+        config.check_stream(stream)?;
+        match args.param_buffer() {
+            Some(buffer) => stream.launch_packed(
+                &self.function,
+                config.grid,
+                config.block,
+                config.shared_mem_bytes,
+                buffer.as_bytes(),
+            ),
+            None => stream.launch(
+                &self.function,
+                config.grid,
+                config.block,
+                config.shared_mem_bytes,
+                &args.kernel_params(),
+            ),
+        }
+    }
+
+    /// Launch the kernel asynchronously on the stream set in `config`, or on the legacy default
+    /// stream if it has none.
+    ///
+    /// # Safety
+    ///
+    /// See [`launch`](#method.launch).
+    ///
+    /// # Errors:
+    ///
+    /// See [`launch`](#method.launch).
+    pub unsafe fn launch_with<'s, C: Into<LaunchConfig<'s>>>(
+        &self,
+        config: C,
+        args: Args,
+    ) -> CudaResult<()> {
+        let config = config.into();
+        match config.stream {
+            Some(stream) => self.launch(stream, &config, args),
+            None => self.launch(&Stream::legacy_default(), &config, args),
+        }
+    }
+
+    /// Launch the kernel cooperatively on `stream` with the given arguments.
     ///
-    /// On devices with configurable shared memory banks, this function will set this function's
-    /// shared memory bank size which is used for subsequent launches of this function. If not set,
-    /// the context-wide setting will be used instead.
+    /// Blocks of a cooperative kernel are guaranteed to be resident at the same time, so they can
+    /// synchronize across the whole grid. Before launching, this checks that the device supports
+    /// cooperative launches and that the grid fits on the device at once (see
+    /// `LaunchConfig::validate_cooperative`).
     ///
-    /// # Example:
+    /// If `config` also sets a stream, it must be `stream`.
+    ///
+    /// # Safety
+    ///
+    /// See [`launch`](#method.launch).
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `config` sets a stream other than `stream`, `NotSupported` if the
+    /// device does not support cooperative launches, and `CooperativeLaunchTooLarge` if the grid
+    /// does not fit on the device at once. Otherwise, see [`launch`](#method.launch).
+    ///
+    /// # Examples:
     ///
     /// ```
     /// # use rustacuda::*;
     /// # use std::error::Error;
     /// # fn main() -> Result<(), Box<dyn Error>> {
     /// # let _ctx = quick_init()?;
-    /// # use rustacuda::module::Module;
-    /// # use std::ffi::CString;
-    /// # let ptx = CString::new(include_str!("../resources/add.ptx"))?;
-    /// # let module = Module::load_from_string(&ptx)?;
-    /// # let name = CString::new("sum")?;
-    /// use rustacuda::context::SharedMemoryConfig;
-    /// let mut function = module.get_function(&name)?;
-    /// function.set_shared_memory_config(SharedMemoryConfig::EightByteBankSize)?;
+    /// use rustacuda::context::CurrentContext;
+    /// use rustacuda::device::DeviceAttribute;
+    /// use rustacuda::function::LaunchConfig;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::module::Module;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let sum = module
+    ///     .get_kernel::<(DevicePointer<f32>, DevicePointer<f32>, DevicePointer<f32>, u32)>("sum")?;
+    ///
+    /// let device = CurrentContext::get_device()?;
+    /// if device.get_attribute(DeviceAttribute::CooperativeLaunch)? != 0 {
+    ///     let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+    ///     let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+    ///     let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+    ///     let args = (x.as_device_ptr(), y.as_device_ptr(), out.as_device_ptr(), 10);
+    ///     unsafe {
+    ///         sum.launch_cooperative(&stream, LaunchConfig::new(1, 10), args)?;
+    ///     }
+    ///     stream.synchronize()?;
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn set_shared_memory_config(&mut self, cfg: SharedMemoryConfig) -> CudaResult<()> {
-        unsafe { cuda::cuFuncSetSharedMemConfig(self.inner, transmute(cfg)).to_result() }
+    pub unsafe fn launch_cooperative<'s, C: Into<LaunchConfig<'s>>>(
+        &self,
+        stream: &Stream,
+        config: C,
+        args: Args,
+    ) -> CudaResult<()> {
+        let config = config.into();
+        config.check_stream(stream)?;
+        stream.launch_cooperative(
+            &self.function,
+            config.grid,
+            config.block,
+            config.shared_mem_bytes,
+            &args.kernel_params(),
+        )
     }
 
-    pub(crate) fn to_inner(&self) -> CUfunction {
-        self.inner
+    /// Bundle a cooperative launch of this kernel on `stream` for use with
+    /// [`launch_cooperative_multi_device`](fn.launch_cooperative_multi_device.html).
+    ///
+    /// If `config` also sets a stream, it must be `stream`, or the multi-device launch fails with
+    /// `InvalidValue`.
+    pub fn cooperative_launch<'k, C: Into<LaunchConfig<'k>>>(
+        &'k self,
+        stream: &'k Stream,
+        config: C,
+        args: Args,
+    ) -> CooperativeLaunch<'k>
+    where
+        Args: 'k,
+    {
+        CooperativeLaunch {
+            function: &self.function,
+            config: config.into(),
+            stream,
+            args: Box::new(args),
+        }
+    }
+}
+
+bitflags! {
+    /// Bit flags for `launch_cooperative_multi_device`.
+    pub struct MultiDeviceLaunchFlags: u32 {
+        /// Wait for all prior work on every launch's stream to complete before any of the kernels
+        /// start, and for all of the kernels to complete before later work on any of the streams
+        /// starts.
+        const DEFAULT = 0x0;
+
+        /// Each kernel only waits for prior work on its own stream.
+        const NO_PRE_LAUNCH_SYNC = 0x1;
+
+        /// Later work on each stream only waits for the kernel launched on that stream.
+        const NO_POST_LAUNCH_SYNC = 0x2;
+    }
+}
+
+/// One device's part of a multi-device cooperative launch: a function, its launch configuration,
+/// the stream to launch it on and its arguments.
+///
+/// Created with [`Kernel::cooperative_launch`](struct.Kernel.html#method.cooperative_launch) and
+/// launched with [`launch_cooperative_multi_device`](fn.launch_cooperative_multi_device.html).
+pub struct CooperativeLaunch<'a> {
+    function: &'a Function<'a>,
+    config: LaunchConfig<'a>,
+    stream: &'a Stream,
+    args: Box<dyn KernelArgs + 'a>,
+}
+impl<'a> fmt::Debug for CooperativeLaunch<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CooperativeLaunch")
+            .field("function", &self.function)
+            .field("config", &self.config)
+            .field("stream", &self.stream)
+            .finish()
+    }
+}
+
+/// Launch kernels cooperatively on several devices at once.
+///
+/// Each entry of `launches` is launched on its stream, and all blocks of all the kernels are
+/// resident at the same time, so they can synchronize across devices. Each stream must belong to
+/// a context on a different device. By default, the kernels only start once prior work on all of
+/// the streams has completed; see `MultiDeviceLaunchFlags` to relax this.
+///
+/// Before launching, this checks for each entry that the stream's device supports multi-device
+/// cooperative launches (`DeviceAttribute::CooperativeMultiDeviceLaunch`) and that the grid fits
+/// on it at once.
+///
+/// # Safety
+///
+/// As with `launch!`, the kernels may perform arbitrary memory accesses. The caller must ensure
+/// that any memory passed to them is valid for them to use, and that the host does not access
+/// memory the kernels may write until the streams have been synchronized.
+///
+/// # Errors:
+///
+/// Returns `InvalidValue` if `launches` is empty or one of the configurations sets a stream other
+/// than its launch's, `NotSupported` if one of the devices does not support multi-device
+/// cooperative launches, and `CooperativeLaunchTooLarge` if one of the grids does not fit on its
+/// device at once. Errors from the launch itself are returned as is.
+///
+/// # Examples:
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::context::CurrentContext;
+/// use rustacuda::device::DeviceAttribute;
+/// use rustacuda::function::{launch_cooperative_multi_device, LaunchConfig, MultiDeviceLaunchFlags};
+/// use rustacuda::memory::*;
+/// use rustacuda::module::Module;
+/// use rustacuda::stream::{Stream, StreamFlags};
+/// use std::ffi::CString;
+///
+/// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+/// let module = Module::load_from_string(&ptx)?;
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+/// let sum = module
+///     .get_kernel::<(DevicePointer<f32>, DevicePointer<f32>, DevicePointer<f32>, u32)>("sum")?;
+///
+/// let device = CurrentContext::get_device()?;
+/// if device.get_attribute(DeviceAttribute::CooperativeMultiDeviceLaunch)? != 0 {
+///     let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+///     let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+///     let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+///     let args = (x.as_device_ptr(), y.as_device_ptr(), out.as_device_ptr(), 10);
+///
+///     // With more devices, add one launch per device, each with a stream in that device's
+///     // context.
+///     let launches = [sum.cooperative_launch(&stream, LaunchConfig::new(1, 10), args)];
+///     unsafe {
+///         launch_cooperative_multi_device(&launches, MultiDeviceLaunchFlags::DEFAULT)?;
+///     }
+///     stream.synchronize()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub unsafe fn launch_cooperative_multi_device(
+    launches: &[CooperativeLaunch],
+    flags: MultiDeviceLaunchFlags,
+) -> CudaResult<()> {
+    if launches.is_empty() {
+        return Err(CudaError::InvalidValue);
+    }
+
+    let params: Vec<Vec<*mut c_void>> = launches.iter().map(|l| l.args.kernel_params()).collect();
+    let mut launch_params = Vec::with_capacity(launches.len());
+    for (launch, params) in launches.iter().zip(&params) {
+        launch.config.check_stream(launch.stream)?;
+        in_stream_context(launch.stream, |device| {
+            launch
+                .config
+                .check_co_resident(
+                    launch.function,
+                    device,
+                    DeviceAttribute::CooperativeMultiDeviceLaunch,
+                )
+                .map_err(LaunchConfigError::into_cuda_error)
+        })?;
+
+        launch_params.push(ffi::CUDA_LAUNCH_PARAMS {
+            function: launch.function.to_inner(),
+            gridDimX: launch.config.grid.x,
+            gridDimY: launch.config.grid.y,
+            gridDimZ: launch.config.grid.z,
+            blockDimX: launch.config.block.x,
+            blockDimY: launch.config.block.y,
+            blockDimZ: launch.config.block.z,
+            sharedMemBytes: launch.config.shared_mem_bytes,
+            hStream: launch.stream.as_inner(),
+            kernelParams: params.as_ptr() as *mut _,
+        });
     }
+
+    ffi::cuLaunchCooperativeKernelMultiDevice(
+        launch_params.as_mut_ptr(),
+        launch_params.len() as c_uint,
+        flags.bits(),
+    )
+    .to_result()
+}
+
+// Calls `f` with the context `stream` belongs to made current, passing it that context's device.
+pub(crate) unsafe fn in_stream_context<T, F>(stream: &Stream, f: F) -> CudaResult<T>
+where
+    F: FnOnce(Device) -> CudaResult<T>,
+{
+    let mut context = ptr::null_mut();
+    ffi::cuStreamGetCtx(stream.as_inner(), &mut context).to_result()?;
+    cuda::cuCtxPushCurrent_v2(context).to_result()?;
+    let result = CurrentContext::get_device().and_then(f);
+    let mut popped = ptr::null_mut();
+    cuda::cuCtxPopCurrent_v2(&mut popped).to_result()?;
+    result
 }
 
 /// Launch a kernel function asynchronously.
@@ -280,26 +1680,34 @@ impl<'a> Function<'a> {
 /// ```
 ///
 /// This will load a kernel called `function_name` from the module `module` and launch it with
-/// the given grid/block size on the given stream. Unlike in CUDA C, the shared memory size and
-/// stream parameters are not optional. The shared memory size is a number of bytes per thread for
-/// dynamic shared memory (Note that this uses `extern __shared__ int x[]` in CUDA C, not the
-/// fixed-length arrays created by `__shared__ int x[64]`. This will usually be zero.).
-/// `stream` must be the name of a [`Stream`](stream/struct.Stream.html) value.
+/// the given grid/block size on the given stream. `module` can be any expression which evaluates
+/// to a [`Module`](module/struct.Module.html) or a reference to one, such as `self.module` or
+/// `modules[i]`.
+///
+/// As in CUDA C, the shared memory size and stream are optional. The shared memory size is a
+/// number of bytes per block for dynamic shared memory (Note that this uses
+/// `extern __shared__ int x[]` in CUDA C, not the fixed-length arrays created by
+/// `__shared__ int x[64]`. This will usually be zero.), and defaults to zero. `stream` can be any
+/// expression which evaluates to a [`Stream`](stream/struct.Stream.html) or a reference to one,
+/// such as `streams[i]` or `&self.stream`; if it is omitted, the kernel is launched on the legacy
+/// default stream (see `Stream::legacy_default`).
 /// `grid` can be any value which implements [`Into<GridSize>`](function/struct.GridSize.html) (such as
 /// `u32` values, tuples of up to three `u32` values, and GridSize structures) and likewise `block`
 /// can be any value that implements [`Into<BlockSize>`](function/struct.BlockSize.html).
 ///
-/// NOTE: due to some limitations of Rust's macro system, `module` and `stream` must be local
-/// variable names. Paths or function calls will not work.
-///
 /// The second form is similar:
 ///
 /// ```ignore
 /// let result = launch!(function<<<grid, block, shared_memory_size, stream>>>(parameter1, parameter2...));
 /// ```
 ///
-/// In this variant, the `function` parameter must be a variable. Use this form to avoid looking up
-/// the kernel function for each call.
+/// In this variant, `function` must be a variable or a parenthesised expression, such as
+/// `(self.kernels.sum)`, evaluating to a [`Function`](function/struct.Function.html). Use this form
+/// to avoid looking up the kernel function for each call.
+///
+/// Both forms return a `Result<(), LaunchError>`. A
+/// [`LaunchError`](error/struct.LaunchError.html) holds the `CudaError` along with the name of the
+/// kernel as written in the macro call, and converts into a `CudaError`.
 ///
 /// # Safety:
 ///
@@ -381,41 +1789,153 @@ impl<'a> Function<'a> {
 ///
 #[macro_export]
 macro_rules! launch {
-    ($module:ident . $function:ident <<<$grid:expr, $block:expr, $shared:expr, $stream:ident>>>( $( $arg:expr),* )) => {
+    // Collect the tokens of the module expression up to `.function_name<<<`. `$method` is the
+    // hidden stream method which performs the launch.
+    (@module $method:ident [$($module:tt)+] . $function:ident <<< $($rest:tt)*) => {
         {
-            let name = std::ffi::CString::new(stringify!($function)).unwrap();
-            let function = $module.get_function(&name);
-            match function {
-                Ok(f) => launch!(f<<<$grid, $block, $shared, $stream>>>( $($arg),* ) ),
-                Err(e) => Err(e),
+            let name = ::std::ffi::CString::new(stringify!($function)).unwrap();
+            match ($($module)+).get_function(&name) {
+                Ok(function) => {
+                    $crate::launch!(
+                        @config (stringify!($function), function, $method) [] $($rest)*
+                    )
+                }
+                Err(e) => Err($crate::error::LaunchError::new(stringify!($function), e)),
             }
         }
     };
-    ($function:ident <<<$grid:expr, $block:expr, $shared:expr, $stream:ident>>>( $( $arg:expr),* )) => {
+    (@module $method:ident [$($module:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::launch!(@module $method [$($module)* $next] $($rest)*)
+    };
+
+    // Collect the tokens of the launch configuration up to `>>>`.
+    (@config $kernel:tt [$($config:tt)*] >>> ( $($arg:expr),* $(,)* )) => {
+        $crate::launch!(@launch $kernel ($($config)*) ($($arg),*))
+    };
+    (@config $kernel:tt [$($config:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::launch!(@config $kernel [$($config)* $next] $($rest)*)
+    };
+
+    (@launch $kernel:tt ($grid:expr, $block:expr) $args:tt) => {
+        $crate::launch!(@launch $kernel ($grid, $block, 0) $args)
+    };
+    (@launch $kernel:tt ($grid:expr, $block:expr, $shared:expr) $args:tt) => {
+        $crate::launch!(
+            @launch $kernel ($grid, $block, $shared, $crate::stream::Stream::legacy_default()) $args
+        )
+    };
+    (@launch ($name:expr, $function:expr, $method:ident)
+        ($grid:expr, $block:expr, $shared:expr, $stream:expr) ($($arg:expr),*)) => {
         {
+            #[allow(dead_code)]
             fn assert_impl_devicecopy<T: $crate::memory::DeviceCopy>(_val: T) {};
             if false {
                 $(
+                    // Launch targets may restrict arguments further, as `StreamScope` does.
+                    ($stream).check_launch_arg(&$arg);
                     assert_impl_devicecopy($arg);
                 )*
             };
 
-            $stream.launch(&$function, $grid, $block, $shared,
+            ($stream).$method(&$function, $grid, $block, $shared,
                 &[
                     $(
                         &$arg as *const _ as *mut ::std::ffi::c_void,
                     )*
                 ]
             )
+            .map_err(|e| $crate::error::LaunchError::new($name, e))
         }
     };
+
+    ($function:ident <<< $($rest:tt)*) => {
+        $crate::launch!(@config (stringify!($function), $function, launch) [] $($rest)*)
+    };
+    (($function:expr) <<< $($rest:tt)*) => {
+        $crate::launch!(@config (stringify!($function), $function, launch) [] $($rest)*)
+    };
+    ($($tokens:tt)*) => {
+        $crate::launch!(@module launch [] $($tokens)*)
+    };
+}
+
+/// Launch a kernel function cooperatively.
+///
+/// This takes the same forms as [`launch!`](macro.launch.html), but launches the kernel with
+/// `cuLaunchCooperativeKernel`, which guarantees that all blocks of the grid are resident at the
+/// same time so they can synchronize across the whole grid. It can be used with a stream or a
+/// `StreamScope`, but not in a graph.
+///
+/// Before launching, the macro checks that the device supports cooperative launches
+/// (`DeviceAttribute::CooperativeLaunch`) and that the grid fits on the device at once, using
+/// `Function::max_active_blocks_per_multiprocessor`. Otherwise it returns a `LaunchError` holding
+/// `NotSupported` or `CooperativeLaunchTooLarge` respectively. Use
+/// `LaunchConfig::validate_cooperative` for a description of which limit was exceeded.
+///
+/// # Safety:
+///
+/// See [`launch!`](macro.launch.html).
+///
+/// # Examples:
+///
+/// ```
+/// # #[macro_use]
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// use rustacuda::context::CurrentContext;
+/// use rustacuda::device::DeviceAttribute;
+/// use rustacuda::memory::*;
+/// use rustacuda::module::Module;
+/// use rustacuda::stream::*;
+/// use std::ffi::CString;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let _ctx = rustacuda::quick_init()?;
+/// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+/// let module = Module::load_from_string(&ptx)?;
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+///
+/// let mut in_x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+/// let mut in_y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+/// let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+///
+/// if CurrentContext::get_device()?.get_attribute(DeviceAttribute::CooperativeLaunch)? != 0 {
+///     unsafe {
+///         launch_cooperative!(module.sum<<<1, 10, 0, stream>>>(
+///             in_x.as_device_ptr(),
+///             in_y.as_device_ptr(),
+///             out.as_device_ptr(),
+///             out.len()
+///         ))?;
+///     }
+///     stream.synchronize()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! launch_cooperative {
+    ($function:ident <<< $($rest:tt)*) => {
+        $crate::launch!(
+            @config (stringify!($function), $function, launch_cooperative) [] $($rest)*
+        )
+    };
+    (($function:expr) <<< $($rest:tt)*) => {
+        $crate::launch!(
+            @config (stringify!($function), $function, launch_cooperative) [] $($rest)*
+        )
+    };
+    ($($tokens:tt)*) => {
+        $crate::launch!(@module launch_cooperative [] $($tokens)*)
+    };
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::error::{CudaError, LaunchError};
     use crate::memory::CopyDestination;
-    use crate::memory::DeviceBuffer;
+    use crate::memory::{DeviceBuffer, DevicePointer};
     use crate::quick_init;
     use crate::stream::{Stream, StreamFlags};
     use std::error::Error;
@@ -444,4 +1964,429 @@ mod test {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_launch_syntax() -> Result<(), Box<dyn Error>> {
+        struct Kernels {
+            module: Module,
+            streams: Vec<Stream>,
+        }
+
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let kernels = Kernels {
+            module: Module::load_from_string(&ptx_text)?,
+            streams: vec![Stream::new(StreamFlags::NON_BLOCKING, None)?],
+        };
+        let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 16])?;
+        let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 16])?;
+        let mut outs = Vec::new();
+        for _ in 0..5 {
+            outs.push(DeviceBuffer::from_slice(&[0.0f32; 16])?);
+        }
+        let (x, y) = (in_x.as_device_ptr(), in_y.as_device_ptr());
+        let out: Vec<_> = outs.iter_mut().map(|o| o.as_device_ptr()).collect();
+        let name = CString::new("sum")?;
+        let functions = [kernels.module.get_function(&name)?];
+
+        unsafe {
+            launch!(kernels.module.sum<<<1, 16, 0, kernels.streams[0]>>>(x, y, out[0], 16u32))?;
+            launch!(kernels.module.sum<<<1, 16, 0, &kernels.streams[0]>>>(x, y, out[1], 16u32,))?;
+            launch!((functions[0])<<<1, 16, 0, kernels.streams[0]>>>(x, y, out[2], 16u32))?;
+            launch!(kernels.module.sum<<<1, 16>>>(x, y, out[3], 16u32))?;
+            launch!((&functions[0])<<<GridSize::x(1), BlockSize::x(16), 0>>>(x, y, out[4], 16u32))?;
+        }
+        kernels.streams[0].synchronize()?;
+        Stream::legacy_default().synchronize()?;
+
+        for out in &outs {
+            let mut out_host = [0f32; 16];
+            out.copy_to(&mut out_host[..])?;
+            assert!(out_host.iter().all(|&x| x == 3.0));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_error_names_kernel() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+
+        let error = unsafe { launch!(module.missing<<<1, 1>>>()) }.unwrap_err();
+        assert_eq!(LaunchError::new("missing", CudaError::NotFound), error);
+        assert!(error.to_string().contains("`missing`"));
+
+        let sum = module.get_function(&CString::new("sum")?)?;
+        let error = unsafe { launch!(sum<<<1, 1_000_000>>>(0u64, 0u64, 0u64, 0u32)) }.unwrap_err();
+        assert_eq!("sum", error.kernel);
+        assert_eq!(CudaError::InvalidValue, CudaError::from(error));
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_kernel_launch() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_kernel::<(
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            u32,
+        )>("sum")?;
+
+        let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 128])?;
+        let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 128])?;
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 128])?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let config = LaunchConfig::new(1, 128);
+        unsafe {
+            let args = (
+                in_x.as_device_ptr(),
+                in_y.as_device_ptr(),
+                out.as_device_ptr(),
+                128,
+            );
+            sum.launch(&stream, &config, args)?;
+        }
+        stream.synchronize()?;
+
+        let mut out_host = [0f32; 128];
+        out.copy_to(&mut out_host[..])?;
+        assert!(out_host.iter().all(|&x| x == 3.0));
+        assert!(
+            sum.function()
+                .get_attribute(FunctionAttribute::NumRegisters)?
+                > 0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_launch_config() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_function(&CString::new("sum")?)?;
+        let device = CurrentContext::get_device()?;
+        let max_threads = device.get_attribute(DeviceAttribute::MaxThreadsPerBlock)? as u32;
+        let max_shared = sum.get_attribute(FunctionAttribute::MaxDynamicSharedSizeBytes)? as u32;
+
+        LaunchConfig::new((16, 16), max_threads).validate(&sum)?;
+        assert_eq!(
+            Err(LaunchConfigError::ZeroSize {
+                dimension: "grid.y"
+            }),
+            LaunchConfig::new((1, 0), 1).validate(&sum)
+        );
+        match LaunchConfig::new(1, (1, 1, 1 << 20)).validate(&sum) {
+            Err(LaunchConfigError::DimensionTooLarge {
+                dimension: "block.z",
+                size,
+                ..
+            }) => assert_eq!(1 << 20, size),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            Err(LaunchConfigError::TooManyThreads {
+                threads: max_threads * 2,
+                max: max_threads
+            }),
+            LaunchConfig::new(1, (max_threads, 2)).validate(&sum)
+        );
+        assert_eq!(
+            Err(LaunchConfigError::TooMuchSharedMemory {
+                static_bytes: 0,
+                dynamic_bytes: max_shared + 1,
+                max: max_shared
+            }),
+            LaunchConfig::new(1, 1)
+                .shared_mem_bytes(max_shared + 1)
+                .validate(&sum)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cooperative_launch() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let device = CurrentContext::get_device()?;
+        if device.get_attribute(DeviceAttribute::CooperativeLaunch)? == 0 {
+            return Ok(());
+        }
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_kernel::<(
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            u32,
+        )>("sum")?;
+
+        let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 128])?;
+        let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 128])?;
+        let mut out_1 = DeviceBuffer::from_slice(&[0.0f32; 128])?;
+        let mut out_2 = DeviceBuffer::from_slice(&[0.0f32; 128])?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        unsafe {
+            let args = (
+                in_x.as_device_ptr(),
+                in_y.as_device_ptr(),
+                out_1.as_device_ptr(),
+                128,
+            );
+            sum.launch_cooperative(&stream, LaunchConfig::new(1, 128), args)?;
+            launch_cooperative!(module.sum<<<1, 128, 0, stream>>>(
+                in_x.as_device_ptr(),
+                in_y.as_device_ptr(),
+                out_2.as_device_ptr(),
+                128u32
+            ))?;
+        }
+        stream.synchronize()?;
+
+        let mut out_host = [0f32; 256];
+        out_1.copy_to(&mut out_host[..128])?;
+        out_2.copy_to(&mut out_host[128..])?;
+        assert!(out_host.iter().all(|&x| x == 3.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cooperative_grid_too_large() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let device = CurrentContext::get_device()?;
+        if device.get_attribute(DeviceAttribute::CooperativeLaunch)? == 0 {
+            return Ok(());
+        }
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_function(&CString::new("sum")?)?;
+        let multiprocessors = device.get_attribute(DeviceAttribute::MultiprocessorCount)? as u64;
+        let max = u64::from(sum.max_active_blocks_per_multiprocessor(128, 0)?) * multiprocessors;
+
+        LaunchConfig::new(max as u32, 128).validate_cooperative(&sum)?;
+        let config = LaunchConfig::new(max as u32 + 1, 128);
+        assert_eq!(
+            Err(LaunchConfigError::TooManyBlocksForCooperativeLaunch {
+                blocks: max + 1,
+                max
+            }),
+            config.validate_cooperative(&sum)
+        );
+
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let mut buffer = DeviceBuffer::from_slice(&[0.0f32; 128])?;
+        let ptr = buffer.as_device_ptr();
+        let result = unsafe {
+            launch_cooperative!(sum<<<max as u32 + 1, 128, 0, stream>>>(ptr, ptr, ptr, 0u32))
+        };
+        assert_eq!(
+            Err(LaunchError::new(
+                "sum",
+                CudaError::CooperativeLaunchTooLarge
+            )),
+            result
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cooperative_multi_device_launch() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let device = CurrentContext::get_device()?;
+        if device.get_attribute(DeviceAttribute::CooperativeMultiDeviceLaunch)? == 0 {
+            return Ok(());
+        }
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_kernel::<(
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            u32,
+        )>("sum")?;
+
+        let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 128])?;
+        let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 128])?;
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 128])?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let args = (
+            in_x.as_device_ptr(),
+            in_y.as_device_ptr(),
+            out.as_device_ptr(),
+            128,
+        );
+        let launches = [sum.cooperative_launch(&stream, LaunchConfig::new(1, 128), args)];
+        unsafe {
+            assert_eq!(
+                Err(CudaError::InvalidValue),
+                launch_cooperative_multi_device(&[], MultiDeviceLaunchFlags::DEFAULT)
+            );
+            launch_cooperative_multi_device(&launches, MultiDeviceLaunchFlags::DEFAULT)?;
+        }
+        stream.synchronize()?;
+
+        let mut out_host = [0f32; 128];
+        out.copy_to(&mut out_host[..])?;
+        assert!(out_host.iter().all(|&x| x == 3.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_with_config_stream() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_kernel::<(
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            DevicePointer<f32>,
+            u32,
+        )>("sum")?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 64])?;
+        let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 64])?;
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 64])?;
+        let config = LaunchConfig::new(1, 1).block(64).stream(&stream);
+        config.validate(sum.function())?;
+        assert_eq!(&BlockSize::x(64), config.get_block());
+        unsafe {
+            let args = (
+                in_x.as_device_ptr(),
+                in_y.as_device_ptr(),
+                out.as_device_ptr(),
+                64,
+            );
+            sum.launch_with(&config, args)?;
+            sum.launch(&stream, &config, args)?;
+
+            let other = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+            assert_eq!(
+                Err(CudaError::InvalidValue),
+                sum.launch(&other, &config, args)
+            );
+        }
+        stream.synchronize()?;
+
+        let mut out_host = [0f32; 64];
+        out.copy_to(&mut out_host[..])?;
+        assert!(out_host.iter().all(|&x| x == 3.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_occupancy() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_function(&CString::new("sum")?)?;
+
+        assert!(sum.max_active_blocks_per_multiprocessor(128, 0)? > 0);
+
+        let suggestion = sum.max_potential_block_size(0, 64)?;
+        assert!(suggestion.block_size > 0 && suggestion.block_size <= 64);
+        assert!(suggestion.min_grid_size > 0);
+
+        let mut asked = Vec::new();
+        let suggestion = sum.max_potential_block_size_with(
+            |block_size| {
+                asked.push(block_size);
+                block_size as usize * 4
+            },
+            0,
+        )?;
+        assert!(!asked.is_empty());
+        assert!(suggestion.block_size > 0);
+
+        let available = sum.available_dynamic_shared_memory_per_block(1, 128)?;
+        let device = CurrentContext::get_device()?;
+        let max_shared = device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlock)? as usize;
+        assert!(available >= max_shared);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "block size callback")]
+    fn test_occupancy_callback_panic_is_propagated() {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx_text).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let _ = sum.max_potential_block_size_with(|_| panic!("block size callback"), 0);
+    }
+
+    #[test]
+    fn test_resource_usage() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let sum = module.get_function(&CString::new("sum")?)?;
+
+        let usage = sum.resource_usage()?;
+        assert_eq!(
+            sum.get_attribute(FunctionAttribute::NumRegisters)? as u32,
+            usage.registers_per_thread
+        );
+        assert_eq!(0, usage.static_shared_bytes);
+        assert!(usage.max_threads_per_block > 0);
+        assert!(usage.to_string().contains("registers"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_attribute() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let mut sum = module.get_function(&CString::new("sum")?)?;
+        let device = CurrentContext::get_device()?;
+        let optin = device.get_attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)? as u32;
+
+        sum.set_attribute(FunctionAttributeValue::MaxDynamicSharedSizeBytes(optin))?;
+        assert_eq!(
+            optin as i32,
+            sum.get_attribute(FunctionAttribute::MaxDynamicSharedSizeBytes)?
+        );
+        LaunchConfig::new(1, 1)
+            .shared_mem_bytes(optin)
+            .validate(&sum)?;
+
+        sum.set_attribute(FunctionAttributeValue::PreferredSharedMemoryCarveout(
+            SharedMemoryCarveout::Percent(50),
+        ))?;
+        assert_eq!(
+            50,
+            sum.get_attribute(FunctionAttribute::PreferredSharedMemoryCarveout)?
+        );
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            sum.set_attribute(FunctionAttributeValue::PreferredSharedMemoryCarveout(
+                SharedMemoryCarveout::Percent(101),
+            ))
+        );
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            sum.set_attribute(FunctionAttributeValue::MaxDynamicSharedSizeBytes(optin + 1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_kernel_errors() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        assert_eq!(
+            CudaError::NotFound,
+            module.get_kernel::<()>("missing").unwrap_err()
+        );
+        assert_eq!(
+            CudaError::InvalidValue,
+            module.get_kernel::<()>("sum\0").unwrap_err()
+        );
+        Ok(())
+    }
 }
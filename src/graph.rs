@@ -0,0 +1,1211 @@
+//! Graphs of work which can be launched on a stream with a single call.
+//!
+//! A CUDA graph records a set of operations - kernel launches, memory copies, memsets, host
+//! functions and so on - together with the dependencies between them. Once built, a
+//! [`Graph`](struct.Graph.html) is instantiated into a [`GraphExec`](struct.GraphExec.html),
+//! which can be launched many times. Launching a graph has much lower CPU overhead than launching
+//! each operation separately, which helps when the same sequence of small kernels is run
+//! repeatedly.
+//!
+//! Each node is added with a list of the nodes it depends on. A node does not start until all of
+//! its dependencies have completed, and nodes without a path between them may run concurrently.
+//! Further edges can be added later with `Graph::add_dependency`.
+//!
+//! Graphs record raw pointers to the memory they use, not borrows. For this reason the functions
+//! which add kernels, copies and memsets are `unsafe`; it is up to the caller to keep the memory
+//! alive and correctly sized for as long as the graph may be launched.
+
+use crate::error::{CudaError, CudaResult, DropResult, ToResult};
+use crate::event::{Event, EventFlags};
+use crate::ffi::{
+    self, CUgraph, CUgraphExec, CUgraphNode, CUDA_HOST_NODE_PARAMS, CUDA_KERNEL_NODE_PARAMS,
+    CUDA_MEMSET_NODE_PARAMS,
+};
+use crate::function::{BlockSize, Function, GridSize};
+use crate::memory::{DeviceCopy, DeviceSlice};
+use crate::stream::Stream;
+use cuda_sys::cuda::{self, CUcontext, CUmemorytype, CUDA_MEMCPY3D};
+use std::cell::RefCell;
+use std::error::Error;
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::mem;
+use std::panic;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// A host node's function, shared by the graphs and executable graphs which contain the node.
+struct HostFn {
+    f: Mutex<Box<dyn FnMut() + Send>>,
+    // Set when `f` panics, and reported by the next `GraphExec::launch`.
+    panicked: AtomicBool,
+}
+
+/// Handle to a node within a [`Graph`](struct.Graph.html).
+///
+/// Nodes are returned by the functions which add them to a graph and are used to express
+/// dependencies between them. A node handle is only meaningful for the graph which created it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphNode {
+    inner: CUgraphNode,
+}
+impl fmt::Debug for GraphNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("GraphNode").field(&self.inner).finish()
+    }
+}
+
+/// Description of a one-dimensional memory copy, for use in a memcpy node.
+///
+/// Constructing a `MemcpyParams` only records the addresses of the source and destination; the
+/// copy is performed each time the graph containing the node is launched.
+#[derive(Clone, Copy)]
+pub struct MemcpyParams {
+    raw: CUDA_MEMCPY3D,
+}
+impl MemcpyParams {
+    /// Copy from host memory into device memory.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if `dst` and `src` have different lengths.
+    pub fn host_to_device<T: DeviceCopy>(dst: &mut DeviceSlice<T>, src: &[T]) -> Self {
+        assert_eq!(
+            dst.len(),
+            src.len(),
+            "destination and source slices have different lengths"
+        );
+        let mut raw = Self::raw_1d(mem::size_of_val(src));
+        raw.srcMemoryType = CUmemorytype::CU_MEMORYTYPE_HOST;
+        raw.srcHost = src.as_ptr() as *const c_void;
+        raw.dstMemoryType = CUmemorytype::CU_MEMORYTYPE_DEVICE;
+        raw.dstDevice = dst.as_mut_ptr() as u64;
+        MemcpyParams { raw }
+    }
+
+    /// Copy from device memory into host memory.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if `dst` and `src` have different lengths.
+    pub fn device_to_host<T: DeviceCopy>(dst: &mut [T], src: &DeviceSlice<T>) -> Self {
+        assert_eq!(
+            dst.len(),
+            src.len(),
+            "destination and source slices have different lengths"
+        );
+        let mut raw = Self::raw_1d(mem::size_of::<T>() * src.len());
+        raw.srcMemoryType = CUmemorytype::CU_MEMORYTYPE_DEVICE;
+        raw.srcDevice = src.as_ptr() as u64;
+        raw.dstMemoryType = CUmemorytype::CU_MEMORYTYPE_HOST;
+        raw.dstHost = dst.as_mut_ptr() as *mut c_void;
+        MemcpyParams { raw }
+    }
+
+    /// Copy between two device allocations.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if `dst` and `src` have different lengths.
+    pub fn device_to_device<T: DeviceCopy>(dst: &mut DeviceSlice<T>, src: &DeviceSlice<T>) -> Self {
+        assert_eq!(
+            dst.len(),
+            src.len(),
+            "destination and source slices have different lengths"
+        );
+        let mut raw = Self::raw_1d(mem::size_of::<T>() * src.len());
+        raw.srcMemoryType = CUmemorytype::CU_MEMORYTYPE_DEVICE;
+        raw.srcDevice = src.as_ptr() as u64;
+        raw.dstMemoryType = CUmemorytype::CU_MEMORYTYPE_DEVICE;
+        raw.dstDevice = dst.as_mut_ptr() as u64;
+        MemcpyParams { raw }
+    }
+
+    fn raw_1d(size: usize) -> CUDA_MEMCPY3D {
+        CUDA_MEMCPY3D {
+            srcXInBytes: 0,
+            srcY: 0,
+            srcZ: 0,
+            srcLOD: 0,
+            srcMemoryType: CUmemorytype::CU_MEMORYTYPE_HOST,
+            srcHost: ptr::null(),
+            srcDevice: 0,
+            srcArray: ptr::null_mut(),
+            reserved0: ptr::null_mut(),
+            srcPitch: size,
+            srcHeight: 1,
+            dstXInBytes: 0,
+            dstY: 0,
+            dstZ: 0,
+            dstLOD: 0,
+            dstMemoryType: CUmemorytype::CU_MEMORYTYPE_HOST,
+            dstHost: ptr::null_mut(),
+            dstDevice: 0,
+            dstArray: ptr::null_mut(),
+            reserved1: ptr::null_mut(),
+            dstPitch: size,
+            dstHeight: 1,
+            WidthInBytes: size,
+            Height: 1,
+            Depth: 1,
+        }
+    }
+
+    pub(crate) fn as_raw(&self) -> &CUDA_MEMCPY3D {
+        &self.raw
+    }
+}
+impl fmt::Debug for MemcpyParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemcpyParams")
+            .field("src_memory_type", &self.raw.srcMemoryType)
+            .field("dst_memory_type", &self.raw.dstMemoryType)
+            .field("width_in_bytes", &self.raw.WidthInBytes)
+            .finish()
+    }
+}
+
+/// Values which can be written by a memset node.
+///
+/// CUDA can only set elements of one, two or four bytes, so this trait is implemented for the
+/// primitive types of those sizes. It is sealed and cannot be implemented outside of RustaCUDA.
+pub trait MemsetValue: DeviceCopy + crate::private::Sealed {
+    #[doc(hidden)]
+    fn to_bits(self) -> u32;
+}
+macro_rules! impl_memset_value {
+    ($($t:ty => |$v:ident| $bits:expr),* $(,)*) => {
+        $(
+            impl MemsetValue for $t {
+                fn to_bits(self) -> u32 {
+                    let $v = self;
+                    $bits
+                }
+            }
+        )*
+    };
+}
+impl_memset_value! {
+    u8 => |v| u32::from(v),
+    i8 => |v| u32::from(v as u8),
+    u16 => |v| u32::from(v),
+    i16 => |v| u32::from(v as u16),
+    u32 => |v| v,
+    i32 => |v| v as u32,
+    f32 => |v| v.to_bits(),
+}
+
+bitflags! {
+    /// Bit flags controlling the output of `Graph::write_dot`.
+    pub struct DotFlags: u32 {
+        /// Only the node types and the edges between them.
+        const DEFAULT = 0x0;
+
+        /// Include all available details of every node.
+        const VERBOSE = 0x1;
+    }
+}
+
+/// Reason an executable graph could not be updated, as reported by the driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UpdateRejection {
+    /// The update failed for an unspecified reason, or because of an error unrelated to the
+    /// contents of the graph.
+    Error,
+    /// The graph has different nodes or edges to the one the executable was instantiated from.
+    TopologyChanged,
+    /// A node has a different type.
+    NodeTypeChanged,
+    /// A kernel node uses a different function, which is not supported by this driver.
+    FunctionChanged,
+    /// A node's parameters changed in a way that is not supported, such as a memcpy node
+    /// copying between a different kind of memory.
+    ParametersChanged,
+    /// The graph contains a node whose parameters cannot be updated.
+    NotSupported,
+    /// A kernel node changed function in a way which is not supported, for example to a
+    /// function which uses dynamic parallelism when the original did not.
+    UnsupportedFunctionChange,
+    /// A node's attributes changed in a way that is not supported.
+    AttributesChanged,
+}
+impl UpdateRejection {
+    fn from_raw(result: u32) -> Self {
+        match result {
+            2 => UpdateRejection::TopologyChanged,
+            3 => UpdateRejection::NodeTypeChanged,
+            4 => UpdateRejection::FunctionChanged,
+            5 => UpdateRejection::ParametersChanged,
+            6 => UpdateRejection::NotSupported,
+            7 => UpdateRejection::UnsupportedFunctionChange,
+            8 => UpdateRejection::AttributesChanged,
+            _ => UpdateRejection::Error,
+        }
+    }
+}
+
+/// Error returned when `GraphExec::update` rejects a graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphUpdateError {
+    /// The error returned by the driver, usually `GraphExecUpdateFailure`.
+    pub error: CudaError,
+    /// Why the update was rejected.
+    pub reason: UpdateRejection,
+    /// The node of the new graph which caused the rejection, if the driver identified one.
+    pub node: Option<GraphNode>,
+}
+impl fmt::Display for GraphUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Graph update rejected ({:?}): {}",
+            self.reason, self.error
+        )
+    }
+}
+impl Error for GraphUpdateError {}
+
+/// A graph of operations and the dependencies between them.
+///
+/// See the [module-level documentation](index.html) for more details.
+pub struct Graph {
+    inner: CUgraph,
+    host_fns: Vec<Arc<HostFn>>,
+}
+impl Graph {
+    /// Create a new, empty graph.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::graph::Graph;
+    /// let graph = Graph::new().unwrap();
+    /// assert_eq!(0, graph.node_count().unwrap());
+    /// ```
+    pub fn new() -> CudaResult<Self> {
+        unsafe {
+            let mut graph = ptr::null_mut();
+            ffi::cuGraphCreate(&mut graph, 0).to_result()?;
+            Ok(Graph::from_raw(graph))
+        }
+    }
+
+    pub(crate) unsafe fn from_raw(inner: CUgraph) -> Self {
+        Graph {
+            inner,
+            host_fns: Vec::new(),
+        }
+    }
+
+    /// Returns the number of nodes in the graph, not counting the contents of child graphs.
+    pub fn node_count(&self) -> CudaResult<usize> {
+        unsafe {
+            let mut count = 0;
+            ffi::cuGraphGetNodes(self.inner, ptr::null_mut(), &mut count).to_result()?;
+            Ok(count)
+        }
+    }
+
+    /// Make `to` depend on `from`, in addition to any dependencies it already has.
+    ///
+    /// # Errors:
+    ///
+    /// If either node does not belong to this graph, or if the edge already exists, returns
+    /// `InvalidValue`.
+    pub fn add_dependency(&mut self, from: GraphNode, to: GraphNode) -> CudaResult<()> {
+        unsafe { ffi::cuGraphAddDependencies(self.inner, &from.inner, &to.inner, 1).to_result() }
+    }
+
+    /// Add a node which does nothing.
+    ///
+    /// Empty nodes are useful for joining several dependencies into one, which can reduce the
+    /// number of edges needed between two groups of nodes.
+    pub fn add_empty_node(&mut self, dependencies: &[GraphNode]) -> CudaResult<GraphNode> {
+        let deps = raw_nodes(dependencies);
+        self.add_node(|node, graph| unsafe {
+            ffi::cuGraphAddEmptyNode(node, graph, deps.as_ptr(), deps.len())
+        })
+    }
+
+    /// Add a kernel launch node.
+    ///
+    /// This is the graph equivalent of launching a kernel on a stream; the arguments are the same
+    /// as those taken by the `launch!` macro. The argument values are copied into the node when it
+    /// is added, so they need not outlive this call. In most cases it is more convenient to use
+    /// `launch!` with [`kernel_node`](#method.kernel_node).
+    ///
+    /// # Safety
+    ///
+    /// The argument list must match the kernel's parameters, and any memory the kernel accesses
+    /// must remain valid for as long as the graph may be launched.
+    pub unsafe fn add_kernel_node<G, B>(
+        &mut self,
+        dependencies: &[GraphNode],
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<GraphNode>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        let deps = raw_nodes(dependencies);
+        let params = kernel_params(func, grid_size, block_size, shared_mem_bytes, args);
+        self.add_node(|node, graph| {
+            ffi::cuGraphAddKernelNode(node, graph, deps.as_ptr(), deps.len(), &params)
+        })
+    }
+
+    /// Returns a target for adding a kernel node with the `launch!` macro.
+    ///
+    /// The macro evaluates to a `CudaResult<GraphNode>` for the new node, instead of launching the
+    /// kernel.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate rustacuda;
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # use std::ffi::CString;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::graph::Graph;
+    /// use rustacuda::memory::DeviceBuffer;
+    /// use rustacuda::module::Module;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+    /// let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+    /// let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+    ///
+    /// let mut graph = Graph::new()?;
+    /// let mut target = graph.kernel_node(&[]);
+    /// let _node = unsafe {
+    ///     launch!(module.sum<<<1, 10, 0, target>>>(
+    ///         x.as_device_ptr(),
+    ///         y.as_device_ptr(),
+    ///         out.as_device_ptr(),
+    ///         out.len()
+    ///     ))?
+    /// };
+    ///
+    /// let exec = graph.instantiate()?;
+    /// exec.launch(&stream)?;
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn kernel_node<'g>(&'g mut self, dependencies: &'g [GraphNode]) -> KernelNodeTarget<'g> {
+        KernelNodeTarget {
+            graph: self,
+            dependencies,
+        }
+    }
+
+    /// Add a memory copy node.
+    ///
+    /// # Safety
+    ///
+    /// The source and destination memory recorded in `params` must remain valid for as long as
+    /// the graph may be launched.
+    pub unsafe fn add_memcpy_node(
+        &mut self,
+        dependencies: &[GraphNode],
+        params: &MemcpyParams,
+    ) -> CudaResult<GraphNode> {
+        let deps = raw_nodes(dependencies);
+        let ctx = current_context()?;
+        self.add_node(|node, graph| {
+            ffi::cuGraphAddMemcpyNode(node, graph, deps.as_ptr(), deps.len(), params.as_raw(), ctx)
+        })
+    }
+
+    /// Add a node which sets every element of `dst` to `value`.
+    ///
+    /// # Safety
+    ///
+    /// The memory of `dst` must remain valid for as long as the graph may be launched.
+    pub unsafe fn add_memset_node<T: MemsetValue>(
+        &mut self,
+        dependencies: &[GraphNode],
+        dst: &mut DeviceSlice<T>,
+        value: T,
+    ) -> CudaResult<GraphNode> {
+        let deps = raw_nodes(dependencies);
+        let ctx = current_context()?;
+        let params = CUDA_MEMSET_NODE_PARAMS {
+            dst: dst.as_mut_ptr() as u64,
+            pitch: 0,
+            value: value.to_bits(),
+            elementSize: mem::size_of::<T>() as u32,
+            width: dst.len(),
+            height: 1,
+        };
+        self.add_node(|node, graph| {
+            ffi::cuGraphAddMemsetNode(node, graph, deps.as_ptr(), deps.len(), &params, ctx)
+        })
+    }
+
+    /// Add a node which calls a function on the host.
+    ///
+    /// The function is called once every time the graph is launched, on a thread owned by the
+    /// CUDA driver. It must not call any CUDA functions. Panics cannot unwind into the driver, so
+    /// they are caught, and the next `GraphExec::launch` of a graph containing the node returns
+    /// `CudaError::HostFunctionPanicked`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::graph::Graph;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let counter = Arc::new(AtomicUsize::new(0));
+    /// let mut graph = Graph::new().unwrap();
+    /// let c = counter.clone();
+    /// graph.add_host_node(&[], move || {
+    ///     c.fetch_add(1, Ordering::SeqCst);
+    /// }).unwrap();
+    /// ```
+    pub fn add_host_node<F>(&mut self, dependencies: &[GraphNode], f: F) -> CudaResult<GraphNode>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let deps = raw_nodes(dependencies);
+        let host_fn = Arc::new(HostFn {
+            f: Mutex::new(Box::new(f)),
+            panicked: AtomicBool::new(false),
+        });
+        let params = CUDA_HOST_NODE_PARAMS {
+            fn_: Some(host_fn_wrapper),
+            userData: Arc::as_ptr(&host_fn) as *mut c_void,
+        };
+        let node = self.add_node(|node, graph| unsafe {
+            ffi::cuGraphAddHostNode(node, graph, deps.as_ptr(), deps.len(), &params)
+        })?;
+        self.host_fns.push(host_fn);
+        Ok(node)
+    }
+
+    /// Add a node which executes a copy of `child`.
+    ///
+    /// The child graph is copied into this graph, so later changes to `child` do not affect it.
+    pub fn add_child_graph_node(
+        &mut self,
+        dependencies: &[GraphNode],
+        child: &Graph,
+    ) -> CudaResult<GraphNode> {
+        let deps = raw_nodes(dependencies);
+        let node = self.add_node(|node, graph| unsafe {
+            ffi::cuGraphAddChildGraphNode(node, graph, deps.as_ptr(), deps.len(), child.inner)
+        })?;
+        self.host_fns.extend(child.host_fns.iter().cloned());
+        Ok(node)
+    }
+
+    /// Add a node which records `event`, like `Event::record`.
+    ///
+    /// # Safety
+    ///
+    /// The event must not be destroyed while the graph may be launched.
+    pub unsafe fn add_event_record_node(
+        &mut self,
+        dependencies: &[GraphNode],
+        event: &Event,
+    ) -> CudaResult<GraphNode> {
+        let deps = raw_nodes(dependencies);
+        self.add_node(|node, graph| {
+            ffi::cuGraphAddEventRecordNode(node, graph, deps.as_ptr(), deps.len(), event.as_inner())
+        })
+    }
+
+    /// Add a node which waits for `event` to complete, like `Stream::wait_event`.
+    ///
+    /// # Safety
+    ///
+    /// The event must not be destroyed while the graph may be launched.
+    pub unsafe fn add_event_wait_node(
+        &mut self,
+        dependencies: &[GraphNode],
+        event: &Event,
+    ) -> CudaResult<GraphNode> {
+        let deps = raw_nodes(dependencies);
+        self.add_node(|node, graph| {
+            ffi::cuGraphAddEventWaitNode(node, graph, deps.as_ptr(), deps.len(), event.as_inner())
+        })
+    }
+
+    /// Write a description of the graph in Graphviz DOT format to the file at `path`.
+    ///
+    /// This is intended for debugging; the exact output depends on the driver version.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `path` is not valid UTF-8 or contains a NUL byte, or
+    /// `OperatingSystemError` if the file cannot be written.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::graph::{DotFlags, Graph};
+    ///
+    /// let mut graph = Graph::new().unwrap();
+    /// let first = graph.add_empty_node(&[]).unwrap();
+    /// let _second = graph.add_empty_node(&[first]).unwrap();
+    ///
+    /// let path = std::env::temp_dir().join("rustacuda_graph.dot");
+    /// graph.write_dot(&path, DotFlags::VERBOSE).unwrap();
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn write_dot<P: AsRef<Path>>(&self, path: P, flags: DotFlags) -> CudaResult<()> {
+        let path = path.as_ref().to_str().ok_or(CudaError::InvalidValue)?;
+        let path = CString::new(path).map_err(|_| CudaError::InvalidValue)?;
+        unsafe { ffi::cuGraphDebugDotPrint(self.inner, path.as_ptr(), flags.bits()).to_result() }
+    }
+
+    /// Instantiate the graph into an executable graph which can be launched.
+    ///
+    /// The executable graph is a snapshot; changes made to this graph afterwards are not reflected
+    /// in it.
+    ///
+    /// # Errors:
+    ///
+    /// If the graph is not valid, for example because it contains a kernel node with an invalid
+    /// launch configuration, returns the error.
+    pub fn instantiate(&self) -> CudaResult<GraphExec> {
+        unsafe {
+            let mut exec = ptr::null_mut();
+            ffi::cuGraphInstantiateWithFlags(&mut exec, self.inner, 0).to_result()?;
+            Ok(GraphExec {
+                inner: exec,
+                host_fns: self.host_fns.clone(),
+                last_launch: RefCell::new(None),
+            })
+        }
+    }
+
+    /// Destroy a `Graph`, returning an error.
+    ///
+    /// Destroying a graph can return errors from previous asynchronous work. This function
+    /// destroys the given graph and returns the error and the un-destroyed graph on failure.
+    pub fn drop(mut graph: Graph) -> DropResult<Graph> {
+        if graph.inner.is_null() {
+            return Ok(());
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut graph.inner, ptr::null_mut());
+            match ffi::cuGraphDestroy(inner).to_result() {
+                Ok(()) => {
+                    mem::forget(graph);
+                    Ok(())
+                }
+                Err(e) => Err((
+                    e,
+                    Graph {
+                        inner,
+                        host_fns: mem::take(&mut graph.host_fns),
+                    },
+                )),
+            }
+        }
+    }
+
+    fn add_node<F>(&mut self, add: F) -> CudaResult<GraphNode>
+    where
+        F: FnOnce(*mut CUgraphNode, CUgraph) -> ffi::CUresult,
+    {
+        let mut node = ptr::null_mut();
+        add(&mut node, self.inner).to_result()?;
+        Ok(GraphNode { inner: node })
+    }
+}
+impl fmt::Debug for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Graph")
+            .field("inner", &self.inner)
+            .field("host_fns", &self.host_fns.len())
+            .finish()
+    }
+}
+impl Drop for Graph {
+    fn drop(&mut self) {
+        if self.inner.is_null() {
+            return;
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut self.inner, ptr::null_mut());
+            ffi::cuGraphDestroy(inner)
+                .to_result()
+                .expect("Failed to destroy CUDA graph");
+        }
+    }
+}
+
+/// Target for adding a kernel node to a graph with the `launch!` macro.
+///
+/// Created by [`Graph::kernel_node`](struct.Graph.html#method.kernel_node).
+#[derive(Debug)]
+pub struct KernelNodeTarget<'g> {
+    graph: &'g mut Graph,
+    dependencies: &'g [GraphNode],
+}
+impl<'g> KernelNodeTarget<'g> {
+    // Hidden implementation detail function used by the `launch!` macro to check the type of
+    // each kernel argument.
+    #[doc(hidden)]
+    pub fn check_launch_arg<T: DeviceCopy>(&self, _arg: &T) {}
+
+    // Hidden implementation detail function used by the `launch!` macro.
+    #[doc(hidden)]
+    pub unsafe fn launch<G, B>(
+        &mut self,
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<GraphNode>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        self.graph.add_kernel_node(
+            self.dependencies,
+            func,
+            grid_size,
+            block_size,
+            shared_mem_bytes,
+            args,
+        )
+    }
+}
+
+/// An instantiated graph, ready to be launched.
+///
+/// Created by [`Graph::instantiate`](struct.Graph.html#method.instantiate).
+pub struct GraphExec {
+    inner: CUgraphExec,
+    host_fns: Vec<Arc<HostFn>>,
+    // Recorded after each launch of a graph with host functions, so that they can be kept alive
+    // until the driver is done calling them.
+    last_launch: RefCell<Option<Event>>,
+}
+impl GraphExec {
+    /// Launch the graph on `stream`.
+    ///
+    /// The graph starts once all work previously queued on the stream has completed, and later
+    /// work on the stream waits for the whole graph. An executable graph may be launched any
+    /// number of times; launches of the same executable graph do not overlap.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `HostFunctionPanicked`, without launching the graph, if one of its host nodes
+    /// panicked during an earlier launch. The panic is only reported once.
+    pub fn launch(&self, stream: &Stream) -> CudaResult<()> {
+        let mut panicked = false;
+        for host_fn in &self.host_fns {
+            panicked |= host_fn.panicked.swap(false, Ordering::SeqCst);
+        }
+        if panicked {
+            return Err(CudaError::HostFunctionPanicked);
+        }
+
+        unsafe { ffi::cuGraphLaunch(self.inner, stream.as_inner()).to_result()? };
+        if self.host_fns.is_empty() {
+            return Ok(());
+        }
+
+        // Launches of the same executable graph are ordered, so the latest one completes last.
+        let mut last_launch = self.last_launch.borrow_mut();
+        if last_launch.is_none() {
+            *last_launch = Some(Event::new(EventFlags::DISABLE_TIMING)?);
+        }
+        last_launch.as_ref().unwrap().record(stream)
+    }
+
+    /// Change the kernel, launch configuration and arguments of a kernel node.
+    ///
+    /// `node` is the node in the graph this executable graph was instantiated from. The change
+    /// applies to launches made after this call; launches already queued are not affected. The
+    /// arguments follow the same conventions as `Graph::add_kernel_node`, and are copied during
+    /// this call.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `node` is not a kernel node of this graph, or if the new kernel
+    /// is in a different context.
+    ///
+    /// # Safety
+    ///
+    /// The argument list must match the kernel's parameters, and any memory the kernel accesses
+    /// must remain valid for as long as the graph may be launched.
+    pub unsafe fn set_kernel_node_params<G, B>(
+        &mut self,
+        node: GraphNode,
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<()>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        let params = kernel_params(func, grid_size, block_size, shared_mem_bytes, args);
+        ffi::cuGraphExecKernelNodeSetParams(self.inner, node.inner, &params).to_result()
+    }
+
+    /// Change the source and destination of a memcpy node.
+    ///
+    /// `node` is the node in the graph this executable graph was instantiated from. The change
+    /// applies to launches made after this call. The kind of copy (host to device, device to host
+    /// or device to device) and the devices involved must not change.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `node` is not a memcpy node of this graph, or if the new copy is
+    /// not compatible with the original.
+    ///
+    /// # Safety
+    ///
+    /// The source and destination memory recorded in `params` must remain valid for as long as
+    /// the graph may be launched.
+    pub unsafe fn set_memcpy_node_params(
+        &mut self,
+        node: GraphNode,
+        params: &MemcpyParams,
+    ) -> CudaResult<()> {
+        let ctx = current_context()?;
+        ffi::cuGraphExecMemcpyNodeSetParams(self.inner, node.inner, params.as_raw(), ctx)
+            .to_result()
+    }
+
+    /// Update this executable graph with the node parameters of `graph`.
+    ///
+    /// This is a cheaper alternative to instantiating `graph` from scratch when it has the same
+    /// topology as the graph this executable was instantiated from, and only the parameters of
+    /// its nodes differ - for example, a graph recorded again by stream capture with different
+    /// pointers.
+    ///
+    /// # Errors:
+    ///
+    /// If the update is rejected, returns a [`GraphUpdateError`](struct.GraphUpdateError.html)
+    /// describing why and, where applicable, which node caused it. The executable graph is left
+    /// unchanged in that case.
+    ///
+    /// # Safety
+    ///
+    /// Any memory used by the nodes of `graph` must remain valid for as long as this executable
+    /// graph may be launched.
+    pub unsafe fn update(&mut self, graph: &Graph) -> Result<(), GraphUpdateError> {
+        let mut error_node = ptr::null_mut();
+        let mut update_result = 0;
+        let result =
+            ffi::cuGraphExecUpdate(self.inner, graph.inner, &mut error_node, &mut update_result)
+                .to_result();
+        match result {
+            Ok(()) => {
+                self.host_fns.extend(graph.host_fns.iter().cloned());
+                Ok(())
+            }
+            Err(error) => Err(GraphUpdateError {
+                error,
+                reason: UpdateRejection::from_raw(update_result),
+                node: if error_node.is_null() {
+                    None
+                } else {
+                    Some(GraphNode { inner: error_node })
+                },
+            }),
+        }
+    }
+
+    /// Destroy a `GraphExec`, returning an error.
+    ///
+    /// Destroying an executable graph can return errors from previous asynchronous work. This
+    /// function destroys the given graph and returns the error and the un-destroyed graph on
+    /// failure.
+    pub fn drop(mut exec: GraphExec) -> DropResult<GraphExec> {
+        if exec.inner.is_null() {
+            return Ok(());
+        }
+
+        unsafe {
+            if let Err(e) = exec.wait_for_host_fns() {
+                return Err((e, exec));
+            }
+            let inner = mem::replace(&mut exec.inner, ptr::null_mut());
+            match ffi::cuGraphExecDestroy(inner).to_result() {
+                Ok(()) => {
+                    mem::forget(exec);
+                    Ok(())
+                }
+                Err(e) => Err((
+                    e,
+                    GraphExec {
+                        inner,
+                        host_fns: mem::take(&mut exec.host_fns),
+                        last_launch: RefCell::new(exec.last_launch.get_mut().take()),
+                    },
+                )),
+            }
+        }
+    }
+
+    // The driver frees an executable graph asynchronously if it is still running, but the host
+    // functions are owned here, so wait for the latest launch before they are dropped.
+    fn wait_for_host_fns(&self) -> CudaResult<()> {
+        match *self.last_launch.borrow() {
+            Some(ref event) => event.synchronize(),
+            None => Ok(()),
+        }
+    }
+}
+impl fmt::Debug for GraphExec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GraphExec")
+            .field("inner", &self.inner)
+            .field("host_fns", &self.host_fns.len())
+            .finish()
+    }
+}
+impl Drop for GraphExec {
+    fn drop(&mut self) {
+        if self.inner.is_null() {
+            return;
+        }
+
+        unsafe {
+            // An error here comes from earlier work, and the event has completed either way.
+            let _ = self.wait_for_host_fns();
+            let inner = mem::replace(&mut self.inner, ptr::null_mut());
+            ffi::cuGraphExecDestroy(inner)
+                .to_result()
+                .expect("Failed to destroy CUDA graph executable");
+        }
+    }
+}
+
+pub(crate) fn kernel_params<G, B>(
+    func: &Function,
+    grid_size: G,
+    block_size: B,
+    shared_mem_bytes: u32,
+    args: &[*mut c_void],
+) -> CUDA_KERNEL_NODE_PARAMS
+where
+    G: Into<GridSize>,
+    B: Into<BlockSize>,
+{
+    let grid_size: GridSize = grid_size.into();
+    let block_size: BlockSize = block_size.into();
+    CUDA_KERNEL_NODE_PARAMS {
+        func: func.to_inner(),
+        gridDimX: grid_size.x,
+        gridDimY: grid_size.y,
+        gridDimZ: grid_size.z,
+        blockDimX: block_size.x,
+        blockDimY: block_size.y,
+        blockDimZ: block_size.z,
+        sharedMemBytes: shared_mem_bytes,
+        kernelParams: args.as_ptr() as *mut _,
+        extra: ptr::null_mut(),
+    }
+}
+
+fn raw_nodes(nodes: &[GraphNode]) -> Vec<CUgraphNode> {
+    nodes.iter().map(|node| node.inner).collect()
+}
+
+fn current_context() -> CudaResult<CUcontext> {
+    unsafe {
+        let mut ctx = ptr::null_mut();
+        cuda::cuCtxGetCurrent(&mut ctx).to_result()?;
+        Ok(ctx)
+    }
+}
+
+unsafe extern "C" fn host_fn_wrapper(user_data: *mut c_void) {
+    let host_fn = &*(user_data as *const HostFn);
+    // Stop panics from unwinding across the FFI
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let mut f = host_fn.f.lock().unwrap_or_else(|e| e.into_inner());
+        f();
+    }));
+    if result.is_err() {
+        host_fn.panicked.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::EventFlags;
+    use crate::launch;
+    use crate::memory::*;
+    use crate::module::Module;
+    use crate::quick_init;
+    use crate::stream::StreamFlags;
+    use std::error::Error;
+    use std::ffi::CString;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_graph_kernel_and_copies() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let x_host = [1.0f32; 16];
+        let mut out_host = [0.0f32; 16];
+        let mut x = DeviceBuffer::from_slice(&[0.0f32; 16])?;
+        let mut y = DeviceBuffer::from_slice(&[0.0f32; 16])?;
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 16])?;
+
+        let mut graph = Graph::new()?;
+        unsafe {
+            let upload =
+                graph.add_memcpy_node(&[], &MemcpyParams::host_to_device(&mut x, &x_host))?;
+            let fill = graph.add_memset_node(&[], &mut y, 2.0f32)?;
+            let deps = [upload, fill];
+            let mut target = graph.kernel_node(&deps);
+            let sum = launch!(module.sum<<<1, 16, 0, target>>>(
+                x.as_device_ptr(),
+                y.as_device_ptr(),
+                out.as_device_ptr(),
+                16usize
+            ))?;
+            let _ = graph
+                .add_memcpy_node(&[sum], &MemcpyParams::device_to_host(&mut out_host, &out))?;
+        }
+        assert_eq!(4, graph.node_count()?);
+
+        let exec = graph.instantiate()?;
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        for value in out_host.iter() {
+            assert_eq!(3.0, *value);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_host_and_child_nodes() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let mut child = Graph::new()?;
+        let c = counter.clone();
+        let _ = child.add_host_node(&[], move || {
+            let _ = c.fetch_add(1, Ordering::SeqCst);
+        })?;
+
+        let mut graph = Graph::new()?;
+        let first = graph.add_child_graph_node(&[], &child)?;
+        let join = graph.add_empty_node(&[])?;
+        graph.add_dependency(first, join)?;
+        drop(child);
+
+        let exec = graph.instantiate()?;
+        drop(graph);
+        exec.launch(&stream)?;
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        assert_eq!(2, counter.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_node_panic_is_reported() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let mut graph = Graph::new()?;
+        let _ = graph.add_host_node(&[], || panic!("host node"))?;
+        let exec = graph.instantiate()?;
+
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        assert_eq!(Err(CudaError::HostFunctionPanicked), exec.launch(&stream));
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_waits_for_host_nodes() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let mut graph = Graph::new()?;
+        let c = counter.clone();
+        let _ = graph.add_host_node(&[], move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            let _ = c.fetch_add(1, Ordering::SeqCst);
+        })?;
+        let exec = graph.instantiate()?;
+        drop(graph);
+        exec.launch(&stream)?;
+        drop(exec);
+        assert_eq!(1, counter.load(Ordering::SeqCst));
+        assert_eq!(1, Arc::strong_count(&counter));
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_event_nodes() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let event = Event::new(EventFlags::DEFAULT)?;
+        let mut graph = Graph::new()?;
+        unsafe {
+            let record = graph.add_event_record_node(&[], &event)?;
+            let _ = graph.add_event_wait_node(&[record], &event)?;
+        }
+        let exec = graph.instantiate()?;
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        assert_eq!(crate::event::EventStatus::Ready, event.query()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_node_params() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+        let sum = module.get_function(&CString::new("sum")?)?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let mut x = DeviceBuffer::from_slice(&[1.0f32; 8])?;
+        let mut y = DeviceBuffer::from_slice(&[2.0f32; 8])?;
+        let mut first = DeviceBuffer::from_slice(&[0.0f32; 8])?;
+        let mut second = DeviceBuffer::from_slice(&[0.0f32; 8])?;
+        let mut host = [0.0f32; 8];
+        let mut other_host = [0.0f32; 8];
+
+        let mut graph = Graph::new()?;
+        let (kernel, copy) = unsafe {
+            let args = [x.as_device_ptr(), y.as_device_ptr(), first.as_device_ptr()];
+            let n = 8usize;
+            let kernel = graph.add_kernel_node(
+                &[],
+                &sum,
+                1,
+                8,
+                0,
+                &[
+                    &args[0] as *const _ as *mut c_void,
+                    &args[1] as *const _ as *mut c_void,
+                    &args[2] as *const _ as *mut c_void,
+                    &n as *const _ as *mut c_void,
+                ],
+            )?;
+            let copy = graph
+                .add_memcpy_node(&[kernel], &MemcpyParams::device_to_host(&mut host, &first))?;
+            (kernel, copy)
+        };
+        let mut exec = graph.instantiate()?;
+        unsafe {
+            let args = [x.as_device_ptr(), x.as_device_ptr(), second.as_device_ptr()];
+            let n = 8usize;
+            exec.set_kernel_node_params(
+                kernel,
+                &sum,
+                1,
+                8,
+                0,
+                &[
+                    &args[0] as *const _ as *mut c_void,
+                    &args[1] as *const _ as *mut c_void,
+                    &args[2] as *const _ as *mut c_void,
+                    &n as *const _ as *mut c_void,
+                ],
+            )?;
+            exec.set_memcpy_node_params(
+                copy,
+                &MemcpyParams::device_to_host(&mut other_host, &second),
+            )?;
+        }
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        assert_eq!([0.0f32; 8], host);
+        assert_eq!([2.0f32; 8], other_host);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let mut a = DeviceBuffer::from_slice(&[0u32; 4])?;
+        let mut b = DeviceBuffer::from_slice(&[0u32; 4])?;
+
+        let mut graph = Graph::new()?;
+        let _ = unsafe { graph.add_memset_node(&[], &mut a, 1u32)? };
+        let mut exec = graph.instantiate()?;
+
+        let mut same_shape = Graph::new()?;
+        let _ = unsafe { same_shape.add_memset_node(&[], &mut b, 2u32)? };
+        unsafe { exec.update(&same_shape)? };
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        let mut host = [0u32; 4];
+        b.copy_to(&mut host[..])?;
+        assert_eq!([2u32; 4], host);
+
+        let mut different = Graph::new()?;
+        let _ = different.add_empty_node(&[])?;
+        let _ = different.add_empty_node(&[])?;
+        let err = unsafe { exec.update(&different).unwrap_err() };
+        assert_eq!(CudaError::GraphExecUpdateFailure, err.error);
+        assert_eq!(UpdateRejection::TopologyChanged, err.reason);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_dot() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let mut graph = Graph::new()?;
+        let first = graph.add_empty_node(&[])?;
+        let _ = graph.add_empty_node(&[first])?;
+
+        let path = std::env::temp_dir().join("rustacuda_test_write_dot.dot");
+        graph.write_dot(&path, DotFlags::DEFAULT)?;
+        let dot = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        assert!(dot.contains("digraph"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_dependency_is_rejected() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let mut graph = Graph::new()?;
+        let a = graph.add_empty_node(&[])?;
+        let b = graph.add_empty_node(&[a])?;
+        assert_eq!(
+            Err(crate::error::CudaError::InvalidValue),
+            graph.add_dependency(a, b)
+        );
+        Ok(())
+    }
+}
@@ -166,12 +166,16 @@ pub mod device;
 pub mod error;
 pub mod event;
 pub mod function;
+pub mod graph;
 pub mod memory;
 pub mod module;
 pub mod prelude;
+pub mod profiler;
+pub mod ptx;
 pub mod stream;
 
 mod derive_compile_fail;
+mod ffi;
 
 use crate::context::{Context, ContextFlags};
 use crate::device::Device;
@@ -261,4 +265,14 @@ mod test {
 // Fake module with a private trait used to prevent outside code from implementing certain traits.
 pub(crate) mod private {
     pub trait Sealed {}
+
+    // Primitive element types accepted by memset nodes and stream memory operations.
+    impl Sealed for u8 {}
+    impl Sealed for i8 {}
+    impl Sealed for u16 {}
+    impl Sealed for i16 {}
+    impl Sealed for u32 {}
+    impl Sealed for i32 {}
+    impl Sealed for u64 {}
+    impl Sealed for f32 {}
 }
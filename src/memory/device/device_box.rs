@@ -1,11 +1,11 @@
 use crate::error::{CudaResult, DropResult, ToResult};
+use crate::ffi;
 use crate::memory::device::AsyncCopyDestination;
 use crate::memory::device::CopyDestination;
 use crate::memory::malloc::{cuda_free, cuda_malloc};
 use crate::memory::DeviceCopy;
 use crate::memory::DevicePointer;
 use crate::stream::Stream;
-use cuda_sys::cuda;
 use std::fmt::{self, Pointer};
 use std::mem;
 
@@ -93,7 +93,7 @@ impl<T> DeviceBox<T> {
     pub unsafe fn zeroed() -> CudaResult<Self> {
         let mut new_box = DeviceBox::uninitialized()?;
         if mem::size_of::<T>() != 0 {
-            cuda::cuMemsetD8_v2(
+            ffi::cuMemsetD8_v2(
                 new_box.as_device_ptr().as_raw_mut() as u64,
                 0,
                 mem::size_of::<T>(),
@@ -200,6 +200,11 @@ impl<T> DeviceBox<T> {
         self.ptr
     }
 
+    // Used by `ScopedRef`, which only has shared access to the box.
+    pub(crate) fn device_ptr(&self) -> DevicePointer<T> {
+        self.ptr
+    }
+
     /// Destroy a `DeviceBox`, returning an error.
     ///
     /// Deallocating device memory can return errors from previous asynchronous work. This function
@@ -260,7 +265,7 @@ impl<T: DeviceCopy> CopyDestination<T> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyHtoD_v2(
+                ffi::cuMemcpyHtoD_v2(
                     self.ptr.as_raw_mut() as u64,
                     val as *const T as *const c_void,
                     size,
@@ -275,7 +280,7 @@ impl<T: DeviceCopy> CopyDestination<T> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyDtoH_v2(
+                ffi::cuMemcpyDtoH_v2(
                     val as *const T as *mut c_void,
                     self.ptr.as_raw() as u64,
                     size,
@@ -291,7 +296,7 @@ impl<T: DeviceCopy> CopyDestination<DeviceBox<T>> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyDtoD_v2(self.ptr.as_raw_mut() as u64, val.ptr.as_raw() as u64, size)
+                ffi::cuMemcpyDtoD_v2(self.ptr.as_raw_mut() as u64, val.ptr.as_raw() as u64, size)
                     .to_result()?
             }
         }
@@ -302,7 +307,7 @@ impl<T: DeviceCopy> CopyDestination<DeviceBox<T>> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyDtoD_v2(val.ptr.as_raw_mut() as u64, self.ptr.as_raw() as u64, size)
+                ffi::cuMemcpyDtoD_v2(val.ptr.as_raw_mut() as u64, self.ptr.as_raw() as u64, size)
                     .to_result()?
             }
         }
@@ -313,7 +318,7 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceBox<T>> for DeviceBox<T> {
     unsafe fn async_copy_from(&mut self, val: &DeviceBox<T>, stream: &Stream) -> CudaResult<()> {
         let size = mem::size_of::<T>();
         if size != 0 {
-            cuda::cuMemcpyDtoDAsync_v2(
+            ffi::cuMemcpyDtoDAsync_v2(
                 self.ptr.as_raw_mut() as u64,
                 val.ptr.as_raw() as u64,
                 size,
@@ -327,7 +332,7 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceBox<T>> for DeviceBox<T> {
     unsafe fn async_copy_to(&self, val: &mut DeviceBox<T>, stream: &Stream) -> CudaResult<()> {
         let size = mem::size_of::<T>();
         if size != 0 {
-            cuda::cuMemcpyDtoDAsync_v2(
+            ffi::cuMemcpyDtoDAsync_v2(
                 val.ptr.as_raw_mut() as u64,
                 self.ptr.as_raw() as u64,
                 size,
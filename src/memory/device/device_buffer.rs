@@ -1,5 +1,6 @@
 use crate::error::{CudaError, CudaResult, DropResult, ToResult};
-use crate::memory::device::{AsyncCopyDestination, CopyDestination, DeviceSlice};
+use crate::ffi;
+use crate::memory::device::{AsyncCopyDestination, CopyDestination, DeviceSlice, IpcMemHandle};
 use crate::memory::malloc::{cuda_free, cuda_malloc};
 use crate::memory::DeviceCopy;
 use crate::memory::DevicePointer;
@@ -85,7 +86,7 @@ impl<T> DeviceBuffer<T> {
 
         let ptr = if bytes > 0 {
             let mut ptr = cuda_malloc(bytes)?;
-            cuda::cuMemsetD8_v2(ptr.as_raw_mut() as u64, 0, size * mem::size_of::<T>())
+            ffi::cuMemsetD8_v2(ptr.as_raw_mut() as u64, 0, size * mem::size_of::<T>())
                 .to_result()?;
             ptr
         } else {
@@ -231,6 +232,38 @@ impl<T: DeviceCopy> DeviceBuffer<T> {
         uninit.async_copy_from(slice, stream)?;
         Ok(uninit)
     }
+
+    /// Get a handle which allows other processes to map this buffer into their address space.
+    ///
+    /// The handle can be serialized with `IpcMemHandle::to_bytes` and opened in another process
+    /// using [`IpcDeviceBuffer::open`](struct.IpcDeviceBuffer.html#method.open). This buffer must
+    /// not be dropped until every other process has closed its mapping.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error. Zero-sized buffers have no backing allocation
+    /// and cannot be shared, so they return `InvalidMemoryAllocation`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let buffer = DeviceBuffer::from_slice(&[0u64; 5]).unwrap();
+    /// let handle = buffer.ipc_handle().unwrap();
+    /// assert_eq!(5, handle.len());
+    /// ```
+    pub fn ipc_handle(&self) -> CudaResult<IpcMemHandle<T>> {
+        if self.capacity == 0 || mem::size_of::<T>() == 0 {
+            return Err(CudaError::InvalidMemoryAllocation);
+        }
+
+        unsafe {
+            let mut handle = cuda::CUipcMemHandle { reserved: [0; 64] };
+            cuda::cuIpcGetMemHandle(&mut handle, self.buf.as_raw() as u64).to_result()?;
+            Ok(IpcMemHandle::new(&handle, self.capacity))
+        }
+    }
 }
 impl<T> Deref for DeviceBuffer<T> {
     type Target = DeviceSlice<T>;
@@ -1,10 +1,10 @@
 use crate::error::{CudaResult, ToResult};
+use crate::ffi;
 use crate::memory::device::AsyncCopyDestination;
-use crate::memory::device::{CopyDestination, DeviceBuffer};
+use crate::memory::device::{CopyDestination, DeviceBuffer, DeviceVec};
 use crate::memory::DeviceCopy;
 use crate::memory::DevicePointer;
 use crate::stream::Stream;
-use cuda_sys::cuda;
 use std::iter::{ExactSizeIterator, FusedIterator};
 use std::mem;
 use std::ops::{
@@ -440,7 +440,7 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> CopyDestination<I> for
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyHtoD_v2(
+                ffi::cuMemcpyHtoD_v2(
                     self.0.as_mut_ptr() as u64,
                     val.as_ptr() as *const c_void,
                     size,
@@ -460,7 +460,7 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> CopyDestination<I> for
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyDtoH_v2(val.as_mut_ptr() as *mut c_void, self.as_ptr() as u64, size)
+                ffi::cuMemcpyDtoH_v2(val.as_mut_ptr() as *mut c_void, self.as_ptr() as u64, size)
                     .to_result()?
             }
         }
@@ -476,7 +476,7 @@ impl<T: DeviceCopy> CopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyDtoD_v2(self.0.as_mut_ptr() as u64, val.as_ptr() as u64, size)
+                ffi::cuMemcpyDtoD_v2(self.0.as_mut_ptr() as u64, val.as_ptr() as u64, size)
                     .to_result()?
             }
         }
@@ -491,7 +491,7 @@ impl<T: DeviceCopy> CopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda::cuMemcpyDtoD_v2(val.as_mut_ptr() as u64, self.as_ptr() as u64, size)
+                ffi::cuMemcpyDtoD_v2(val.as_mut_ptr() as u64, self.as_ptr() as u64, size)
                     .to_result()?
             }
         }
@@ -507,6 +507,15 @@ impl<T: DeviceCopy> CopyDestination<DeviceBuffer<T>> for DeviceSlice<T> {
         self.copy_to(val as &mut DeviceSlice<T>)
     }
 }
+impl<T: DeviceCopy> CopyDestination<DeviceVec<T>> for DeviceSlice<T> {
+    fn copy_from(&mut self, val: &DeviceVec<T>) -> CudaResult<()> {
+        self.copy_from(val as &DeviceSlice<T>)
+    }
+
+    fn copy_to(&self, val: &mut DeviceVec<T>) -> CudaResult<()> {
+        self.copy_to(val as &mut DeviceSlice<T>)
+    }
+}
 impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> AsyncCopyDestination<I>
     for DeviceSlice<T>
 {
@@ -518,7 +527,7 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> AsyncCopyDestination<I>
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda::cuMemcpyHtoDAsync_v2(
+            ffi::cuMemcpyHtoDAsync_v2(
                 self.0.as_mut_ptr() as u64,
                 val.as_ptr() as *const c_void,
                 size,
@@ -537,7 +546,7 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> AsyncCopyDestination<I>
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda::cuMemcpyDtoHAsync_v2(
+            ffi::cuMemcpyDtoHAsync_v2(
                 val.as_mut_ptr() as *mut c_void,
                 self.as_ptr() as u64,
                 size,
@@ -556,7 +565,7 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda::cuMemcpyDtoDAsync_v2(
+            ffi::cuMemcpyDtoDAsync_v2(
                 self.0.as_mut_ptr() as u64,
                 val.as_ptr() as u64,
                 size,
@@ -574,7 +583,7 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda::cuMemcpyDtoDAsync_v2(
+            ffi::cuMemcpyDtoDAsync_v2(
                 val.as_mut_ptr() as u64,
                 self.as_ptr() as u64,
                 size,
@@ -594,3 +603,12 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceBuffer<T>> for DeviceSlice<T> {
         self.async_copy_to(val as &mut DeviceSlice<T>, stream)
     }
 }
+impl<T: DeviceCopy> AsyncCopyDestination<DeviceVec<T>> for DeviceSlice<T> {
+    unsafe fn async_copy_from(&mut self, val: &DeviceVec<T>, stream: &Stream) -> CudaResult<()> {
+        self.async_copy_from(val as &DeviceSlice<T>, stream)
+    }
+
+    unsafe fn async_copy_to(&self, val: &mut DeviceVec<T>, stream: &Stream) -> CudaResult<()> {
+        self.async_copy_to(val as &mut DeviceSlice<T>, stream)
+    }
+}
@@ -0,0 +1,682 @@
+use crate::error::{CudaError, CudaResult, DropResult, ToResult};
+use crate::ffi;
+use crate::memory::device::{AsyncCopyDestination, CopyDestination, DeviceSlice};
+use crate::memory::malloc::{cuda_free, cuda_malloc};
+use crate::memory::DeviceCopy;
+use crate::memory::DevicePointer;
+use crate::stream::Stream;
+use std::cmp;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::c_void;
+use std::ptr;
+use std::slice;
+
+/// Growable device-side buffer, similar to `Vec<T>`.
+///
+/// Unlike [`DeviceBuffer`](struct.DeviceBuffer.html), a `DeviceVec` keeps track of a length and a
+/// capacity separately, and reallocates its backing memory when elements are added beyond the
+/// capacity. This makes it useful for collecting outputs of unknown size. New elements are copied
+/// from host memory.
+///
+/// `DeviceVec` dereferences to a [`DeviceSlice`](struct.DeviceSlice.html) containing the
+/// initialized elements, so it can be used anywhere a device slice can.
+///
+/// Reallocating the buffer invalidates any `DevicePointer` previously taken from it.
+#[derive(Debug)]
+pub struct DeviceVec<T: DeviceCopy> {
+    buf: DevicePointer<T>,
+    len: usize,
+    capacity: usize,
+}
+impl<T: DeviceCopy> DeviceVec<T> {
+    /// Create a new, empty `DeviceVec`. This does not allocate any device memory.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let vec: DeviceVec<u64> = DeviceVec::new();
+    /// assert!(vec.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        DeviceVec {
+            buf: dangling(),
+            len: 0,
+            capacity: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+        }
+    }
+
+    /// Create a new, empty `DeviceVec` with space for at least `capacity` elements.
+    ///
+    /// # Errors:
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `capacity` is large enough that
+    /// `capacity * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let vec: DeviceVec<u64> = DeviceVec::with_capacity(10).unwrap();
+    /// assert_eq!(0, vec.len());
+    /// assert!(vec.capacity() >= 10);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> CudaResult<Self> {
+        let mut vec = DeviceVec::new();
+        vec.reserve(capacity)?;
+        Ok(vec)
+    }
+
+    /// Allocate a new `DeviceVec` containing a copy of the data in `slice`.
+    ///
+    /// # Errors:
+    ///
+    /// If the allocation fails, returns the error from CUDA.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let vec = DeviceVec::from_slice(&[1u64, 2, 3]).unwrap();
+    /// assert_eq!(3, vec.len());
+    /// ```
+    pub fn from_slice(slice: &[T]) -> CudaResult<Self> {
+        let mut vec = DeviceVec::with_capacity(slice.len())?;
+        vec.extend_from_slice(slice)?;
+        Ok(vec)
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Reserve capacity for at least `additional` more elements.
+    ///
+    /// Like `Vec::reserve`, this may reserve more space than requested to avoid frequent
+    /// reallocations. Existing elements are copied to the new allocation.
+    ///
+    /// # Errors:
+    ///
+    /// If the allocation or the copy fails, returns the error from CUDA. If the new capacity would
+    /// overflow usize, returns InvalidMemoryAllocation.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = DeviceVec::from_slice(&[1u64]).unwrap();
+    /// vec.reserve(10).unwrap();
+    /// assert!(vec.capacity() >= 11);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) -> CudaResult<()> {
+        if let Some(new_capacity) = self.grown_capacity(additional)? {
+            unsafe { self.reallocate(new_capacity, None)? };
+        }
+        Ok(())
+    }
+
+    /// Copy `value` from the host to the end of the vector, growing it if necessary.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = DeviceVec::new();
+    /// vec.push(&5u64).unwrap();
+    /// vec.push(&6u64).unwrap();
+    ///
+    /// let mut host = [0u64; 2];
+    /// vec.copy_to(&mut host[..]).unwrap();
+    /// assert_eq!([5, 6], host);
+    /// ```
+    pub fn push(&mut self, value: &T) -> CudaResult<()> {
+        self.extend_from_slice(slice::from_ref(value))
+    }
+
+    /// Copy all elements of `other` from the host to the end of the vector, growing it if
+    /// necessary.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = DeviceVec::from_slice(&[1u64, 2]).unwrap();
+    /// vec.extend_from_slice(&[3, 4]).unwrap();
+    ///
+    /// let mut host = [0u64; 4];
+    /// vec.copy_to(&mut host[..]).unwrap();
+    /// assert_eq!([1, 2, 3, 4], host);
+    /// ```
+    pub fn extend_from_slice(&mut self, other: &[T]) -> CudaResult<()> {
+        self.reserve(other.len())?;
+        let len = self.len;
+        self.spare_mut(other.len()).copy_from(other)?;
+        self.len = len + other.len();
+        Ok(())
+    }
+
+    /// Resize the vector to `new_len` elements.
+    ///
+    /// If `new_len` is greater than the current length, the new elements are set to copies of
+    /// `value`. If it is less, the vector is truncated.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = DeviceVec::from_slice(&[1u64]).unwrap();
+    /// vec.resize(4, &7).unwrap();
+    ///
+    /// let mut host = [0u64; 4];
+    /// vec.copy_to(&mut host[..]).unwrap();
+    /// assert_eq!([1, 7, 7, 7], host);
+    /// ```
+    pub fn resize(&mut self, new_len: usize, value: &T) -> CudaResult<()> {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return Ok(());
+        }
+
+        let additional = new_len - self.len;
+        self.reserve(additional)?;
+        unsafe { self.fill_spare(additional, value, None)? };
+        self.len = new_len;
+        Ok(())
+    }
+
+    /// Shorten the vector to `len` elements. Has no effect if `len` is greater than the current
+    /// length. The capacity is not changed.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = DeviceVec::from_slice(&[1u64, 2, 3]).unwrap();
+    /// vec.truncate(1);
+    /// assert_eq!(1, vec.len());
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        self.len = cmp::min(self.len, len);
+    }
+
+    /// Remove all elements from the vector. The capacity is not changed.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Reallocate the vector so that its capacity is equal to its length.
+    ///
+    /// If the vector is empty, the backing memory is freed.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec: DeviceVec<u64> = DeviceVec::with_capacity(10).unwrap();
+    /// vec.push(&1).unwrap();
+    /// vec.shrink_to_fit().unwrap();
+    /// assert_eq!(1, vec.capacity());
+    /// ```
+    pub fn shrink_to_fit(&mut self) -> CudaResult<()> {
+        if self.capacity > self.len && mem::size_of::<T>() != 0 {
+            unsafe { self.reallocate(self.len, None)? };
+        }
+        Ok(())
+    }
+
+    /// Asynchronously reserve capacity for at least `additional` more elements.
+    ///
+    /// Existing elements are copied to the new allocation on `stream`, so this is ordered after
+    /// previously-queued work which writes to the vector. If a reallocation is needed, the stream
+    /// is synchronized before the old allocation is freed.
+    ///
+    /// # Safety
+    ///
+    /// See [AsyncCopyDestination](trait.AsyncCopyDestination.html). Pointers to the old
+    /// allocation must not be used by any work queued on other streams.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    pub unsafe fn reserve_async(&mut self, additional: usize, stream: &Stream) -> CudaResult<()> {
+        if let Some(new_capacity) = self.grown_capacity(additional)? {
+            self.reallocate(new_capacity, Some(stream))?;
+        }
+        Ok(())
+    }
+
+    /// Asynchronously copy `value` from the host to the end of the vector, growing it if
+    /// necessary.
+    ///
+    /// # Safety
+    ///
+    /// For why this function is unsafe, see [AsyncCopyDestination](trait.AsyncCopyDestination.html).
+    /// `value` must not be modified or deallocated until the stream is synchronized.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    /// let mut vec = DeviceVec::new();
+    /// let value = 5u64;
+    /// unsafe {
+    ///     vec.push_async(&value, &stream).unwrap();
+    /// }
+    /// stream.synchronize().unwrap();
+    /// ```
+    pub unsafe fn push_async(&mut self, value: &T, stream: &Stream) -> CudaResult<()> {
+        self.extend_from_slice_async(slice::from_ref(value), stream)
+    }
+
+    /// Asynchronously copy all elements of `other` from the host to the end of the vector, growing
+    /// it if necessary.
+    ///
+    /// # Safety
+    ///
+    /// For why this function is unsafe, see [AsyncCopyDestination](trait.AsyncCopyDestination.html).
+    /// `other` must not be modified or deallocated until the stream is synchronized.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    pub unsafe fn extend_from_slice_async(
+        &mut self,
+        other: &[T],
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        self.reserve_async(other.len(), stream)?;
+        let len = self.len;
+        self.spare_mut(other.len()).async_copy_from(other, stream)?;
+        self.len = len + other.len();
+        Ok(())
+    }
+
+    /// Asynchronously resize the vector to `new_len` elements, filling new elements with copies of
+    /// `value`.
+    ///
+    /// # Safety
+    ///
+    /// For why this function is unsafe, see [AsyncCopyDestination](trait.AsyncCopyDestination.html).
+    /// `value` must not be modified or deallocated until the stream is synchronized.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    pub unsafe fn resize_async(
+        &mut self,
+        new_len: usize,
+        value: &T,
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return Ok(());
+        }
+
+        let additional = new_len - self.len;
+        self.reserve_async(additional, stream)?;
+        self.fill_spare(additional, value, Some(stream))?;
+        self.len = new_len;
+        Ok(())
+    }
+
+    /// Asynchronously reallocate the vector so that its capacity is equal to its length.
+    ///
+    /// # Safety
+    ///
+    /// See [`reserve_async`](#method.reserve_async).
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    pub unsafe fn shrink_to_fit_async(&mut self, stream: &Stream) -> CudaResult<()> {
+        if self.capacity > self.len && mem::size_of::<T>() != 0 {
+            self.reallocate(self.len, Some(stream))?;
+        }
+        Ok(())
+    }
+
+    /// Destroy a `DeviceVec`, returning an error.
+    ///
+    /// Deallocating device memory can return errors from previous asynchronous work. This function
+    /// destroys the given vector and returns the error and the un-destroyed vector on failure.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let x = DeviceVec::from_slice(&[10, 20, 30]).unwrap();
+    /// match DeviceVec::drop(x) {
+    ///     Ok(()) => println!("Successfully destroyed"),
+    ///     Err((e, vec)) => {
+    ///         println!("Failed to destroy vector: {:?}", e);
+    ///         // Do something with vec
+    ///     },
+    /// }
+    /// ```
+    pub fn drop(mut vec: DeviceVec<T>) -> DropResult<DeviceVec<T>> {
+        if vec.capacity == 0 || mem::size_of::<T>() == 0 {
+            return Ok(());
+        }
+
+        let ptr = mem::replace(&mut vec.buf, DevicePointer::null());
+        unsafe {
+            match cuda_free(ptr) {
+                Ok(()) => {
+                    mem::forget(vec);
+                    Ok(())
+                }
+                Err(e) => {
+                    vec.buf = ptr;
+                    Err((e, vec))
+                }
+            }
+        }
+    }
+
+    // Returns the capacity required to hold `additional` more elements, or `None` if the current
+    // allocation is already large enough.
+    fn grown_capacity(&self, additional: usize) -> CudaResult<Option<usize>> {
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(CudaError::InvalidMemoryAllocation)?;
+        if required <= self.capacity {
+            return Ok(None);
+        }
+        let doubled = self.capacity.saturating_mul(2);
+        Ok(Some(cmp::max(doubled, required)))
+    }
+
+    // Moves the elements into a new allocation of `new_capacity` elements. If `stream` is given,
+    // the copy is queued on it and the stream is synchronized before freeing the old allocation.
+    unsafe fn reallocate(
+        &mut self,
+        new_capacity: usize,
+        stream: Option<&Stream>,
+    ) -> CudaResult<()> {
+        debug_assert!(new_capacity >= self.len);
+        let bytes = new_capacity
+            .checked_mul(mem::size_of::<T>())
+            .ok_or(CudaError::InvalidMemoryAllocation)?;
+        let mut new_buf = if bytes > 0 {
+            cuda_malloc(new_capacity)?
+        } else {
+            dangling()
+        };
+
+        let copy_bytes = self.len * mem::size_of::<T>();
+        if copy_bytes > 0 {
+            let result = match stream {
+                Some(stream) => ffi::cuMemcpyDtoDAsync_v2(
+                    new_buf.as_raw_mut() as u64,
+                    self.buf.as_raw() as u64,
+                    copy_bytes,
+                    stream.as_inner(),
+                )
+                .to_result()
+                .and_then(|()| stream.synchronize()),
+                None => ffi::cuMemcpyDtoD_v2(
+                    new_buf.as_raw_mut() as u64,
+                    self.buf.as_raw() as u64,
+                    copy_bytes,
+                )
+                .to_result(),
+            };
+            if let Err(e) = result {
+                if bytes > 0 {
+                    // The copy failed, so the new buffer is discarded and the old one is kept.
+                    let _ = cuda_free(new_buf);
+                }
+                return Err(e);
+            }
+        }
+
+        let old_buf = mem::replace(&mut self.buf, new_buf);
+        let old_capacity = mem::replace(&mut self.capacity, new_capacity);
+        if old_capacity > 0 {
+            cuda_free(old_buf)?;
+        }
+        Ok(())
+    }
+
+    // Returns the `count` uninitialized elements past the end of the vector.
+    fn spare_mut(&mut self, count: usize) -> &mut DeviceSlice<T> {
+        debug_assert!(self.len + count <= self.capacity);
+        unsafe { DeviceSlice::from_raw_parts_mut(self.buf.wrapping_add(self.len), count) }
+    }
+
+    // Fills the `count` elements past the end of the vector with copies of `value`. The value is
+    // copied from the host once, then the filled region is doubled with device-to-device copies.
+    unsafe fn fill_spare(
+        &mut self,
+        count: usize,
+        value: &T,
+        stream: Option<&Stream>,
+    ) -> CudaResult<()> {
+        let elem_size = mem::size_of::<T>();
+        if count == 0 || elem_size == 0 {
+            return Ok(());
+        }
+
+        let mut start = self.buf.add(self.len);
+        match stream {
+            Some(stream) => ffi::cuMemcpyHtoDAsync_v2(
+                start.as_raw_mut() as u64,
+                value as *const T as *const c_void,
+                elem_size,
+                stream.as_inner(),
+            ),
+            None => ffi::cuMemcpyHtoD_v2(
+                start.as_raw_mut() as u64,
+                value as *const T as *const c_void,
+                elem_size,
+            ),
+        }
+        .to_result()?;
+
+        let mut filled = 1;
+        while filled < count {
+            let chunk = cmp::min(filled, count - filled);
+            let dst = start.add(filled).as_raw_mut() as u64;
+            let src = start.as_raw() as u64;
+            match stream {
+                Some(stream) => {
+                    ffi::cuMemcpyDtoDAsync_v2(dst, src, chunk * elem_size, stream.as_inner())
+                }
+                None => ffi::cuMemcpyDtoD_v2(dst, src, chunk * elem_size),
+            }
+            .to_result()?;
+            filled += chunk;
+        }
+        Ok(())
+    }
+}
+impl<T: DeviceCopy> Default for DeviceVec<T> {
+    fn default() -> Self {
+        DeviceVec::new()
+    }
+}
+impl<T: DeviceCopy> Deref for DeviceVec<T> {
+    type Target = DeviceSlice<T>;
+
+    fn deref(&self) -> &DeviceSlice<T> {
+        unsafe { DeviceSlice::from_slice(slice::from_raw_parts(self.buf.as_raw(), self.len)) }
+    }
+}
+impl<T: DeviceCopy> DerefMut for DeviceVec<T> {
+    fn deref_mut(&mut self) -> &mut DeviceSlice<T> {
+        unsafe {
+            DeviceSlice::from_slice_mut(slice::from_raw_parts_mut(self.buf.as_raw_mut(), self.len))
+        }
+    }
+}
+impl<T: DeviceCopy> Drop for DeviceVec<T> {
+    fn drop(&mut self) {
+        if self.capacity > 0 && mem::size_of::<T>() > 0 {
+            // No choice but to panic if this fails.
+            let ptr = mem::replace(&mut self.buf, DevicePointer::null());
+            unsafe {
+                cuda_free(ptr).expect("Failed to deallocate CUDA Device memory.");
+            }
+        }
+        self.capacity = 0;
+        self.len = 0;
+    }
+}
+
+fn dangling<T>() -> DevicePointer<T> {
+    unsafe { DevicePointer::wrap(ptr::NonNull::dangling().as_ptr()) }
+}
+
+#[cfg(test)]
+mod test_device_vec {
+    use super::*;
+    use crate::stream::StreamFlags;
+
+    #[derive(Clone, Debug)]
+    struct ZeroSizedType;
+    unsafe impl DeviceCopy for ZeroSizedType {}
+
+    fn to_host(vec: &DeviceVec<u64>) -> Vec<u64> {
+        let mut host = vec![0u64; vec.len()];
+        vec.copy_to(&mut host[..]).unwrap();
+        host
+    }
+
+    #[test]
+    fn test_new_does_not_allocate() {
+        let _context = crate::quick_init().unwrap();
+        let vec: DeviceVec<u64> = DeviceVec::new();
+        assert_eq!(0, vec.capacity());
+        drop(vec);
+    }
+
+    #[test]
+    fn test_push_grows() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = DeviceVec::new();
+        for i in 0..10u64 {
+            vec.push(&i).unwrap();
+        }
+        assert!(vec.capacity() >= 10);
+        assert_eq!((0..10).collect::<Vec<u64>>(), to_host(&vec));
+    }
+
+    #[test]
+    fn test_extend_from_slice() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = DeviceVec::from_slice(&[0u64, 1]).unwrap();
+        vec.extend_from_slice(&[2, 3, 4]).unwrap();
+        assert_eq!(vec![0u64, 1, 2, 3, 4], to_host(&vec));
+    }
+
+    #[test]
+    fn test_resize_and_truncate() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = DeviceVec::from_slice(&[1u64]).unwrap();
+        vec.resize(7, &9).unwrap();
+        assert_eq!(vec![1u64, 9, 9, 9, 9, 9, 9], to_host(&vec));
+        vec.resize(2, &0).unwrap();
+        assert_eq!(vec![1u64, 9], to_host(&vec));
+        vec.truncate(5);
+        assert_eq!(2, vec.len());
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = DeviceVec::with_capacity(16).unwrap();
+        vec.extend_from_slice(&[1u64, 2, 3]).unwrap();
+        vec.shrink_to_fit().unwrap();
+        assert_eq!(3, vec.capacity());
+        assert_eq!(vec![1u64, 2, 3], to_host(&vec));
+
+        vec.clear();
+        vec.shrink_to_fit().unwrap();
+        assert_eq!(0, vec.capacity());
+    }
+
+    #[test]
+    fn test_async_push_and_resize() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let mut vec = DeviceVec::new();
+        let values = [1u64, 2, 3];
+        let fill = 4u64;
+        unsafe {
+            vec.extend_from_slice_async(&values, &stream).unwrap();
+            vec.push_async(&values[0], &stream).unwrap();
+            vec.resize_async(6, &fill, &stream).unwrap();
+        }
+        stream.synchronize().unwrap();
+        assert_eq!(vec![1u64, 2, 3, 1, 4, 4], to_host(&vec));
+    }
+
+    #[test]
+    fn test_copy_between_buffer_and_vec() {
+        let _context = crate::quick_init().unwrap();
+        let vec = DeviceVec::from_slice(&[1u64, 2, 3]).unwrap();
+        let mut buf = crate::memory::DeviceBuffer::from_slice(&[0u64; 3]).unwrap();
+        buf.copy_from(&vec).unwrap();
+        let mut host = [0u64; 3];
+        buf.copy_to(&mut host).unwrap();
+        assert_eq!([1, 2, 3], host);
+    }
+
+    #[test]
+    fn test_zero_sized_type() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = DeviceVec::new();
+        vec.push(&ZeroSizedType).unwrap();
+        vec.resize(10, &ZeroSizedType).unwrap();
+        assert_eq!(10, vec.len());
+    }
+
+    #[test]
+    fn test_overflows_usize() {
+        let _context = crate::quick_init().unwrap();
+        let err = DeviceVec::<u64>::with_capacity(usize::MAX - 1).unwrap_err();
+        assert_eq!(CudaError::InvalidMemoryAllocation, err);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::error::{CudaError, CudaResult, DropResult, ToResult};
+use crate::memory::device::DeviceSlice;
+use crate::memory::DeviceCopy;
+use crate::memory::DevicePointer;
+use cuda_sys::cuda::{self, CUipcMemHandle, CUipcMem_flags};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::c_char;
+use std::slice;
+
+const HANDLE_BYTES: usize = 64;
+
+/// Handle which allows another process to map a [`DeviceBuffer`](struct.DeviceBuffer.html) into
+/// its own address space.
+///
+/// A handle is obtained with `DeviceBuffer::ipc_handle` and passed to the other process, usually by
+/// serializing it with `to_bytes` and sending the bytes over a pipe or socket. The other process
+/// then reconstructs the handle with `from_bytes` and maps the buffer with
+/// [`IpcDeviceBuffer::open`](struct.IpcDeviceBuffer.html#method.open). The handle records the length
+/// of the buffer as well as the driver's opaque handle, so the receiver does not need to know it.
+///
+/// The serialized form is `SERIALIZED_SIZE` (72) bytes: the driver's 64-byte handle followed by the
+/// length of the buffer as a little-endian `u64`. An
+/// [`IpcEventHandle`](../event/struct.IpcEventHandle.html) is serialized the same way, but
+/// consists of the driver's 64-byte handle alone.
+pub struct IpcMemHandle<T> {
+    handle: [u8; HANDLE_BYTES],
+    len: usize,
+    _marker: PhantomData<T>,
+}
+impl<T: DeviceCopy> IpcMemHandle<T> {
+    /// The number of bytes produced by `to_bytes` and expected by `from_bytes`.
+    pub const SERIALIZED_SIZE: usize = HANDLE_BYTES + 8;
+
+    pub(crate) fn new(handle: &CUipcMemHandle, len: usize) -> Self {
+        let mut bytes = [0u8; HANDLE_BYTES];
+        for (dst, src) in bytes.iter_mut().zip(handle.reserved.iter()) {
+            *dst = *src as u8;
+        }
+        IpcMemHandle {
+            handle: bytes,
+            len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of `T`'s in the buffer this handle refers to.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the buffer this handle refers to has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Serialize this handle into bytes which can be sent to another process.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let buffer = DeviceBuffer::from_slice(&[1u32, 2, 3]).unwrap();
+    /// let handle = buffer.ipc_handle().unwrap();
+    /// let bytes = handle.to_bytes();
+    /// assert_eq!(IpcMemHandle::<u32>::SERIALIZED_SIZE, bytes.len());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SERIALIZED_SIZE);
+        bytes.extend_from_slice(&self.handle);
+        bytes.extend_from_slice(&(self.len as u64).to_le_bytes());
+        bytes
+    }
+
+    /// Reconstruct a handle from bytes produced by `to_bytes`.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `bytes` is not exactly `SERIALIZED_SIZE` bytes long.
+    ///
+    /// # Safety
+    ///
+    /// The bytes must have been produced by `to_bytes` on an `IpcMemHandle<T>` of the same type `T`
+    /// and the buffer must still be allocated in the exporting process. The driver cannot check
+    /// the element type, and a handle of the wrong type may expose bit-patterns which are not
+    /// valid for `T`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let buffer = DeviceBuffer::from_slice(&[1u32, 2, 3]).unwrap();
+    /// let bytes = buffer.ipc_handle().unwrap().to_bytes();
+    /// let handle = unsafe { IpcMemHandle::<u32>::from_bytes(&bytes) }.unwrap();
+    /// assert_eq!(3, handle.len());
+    /// ```
+    pub unsafe fn from_bytes(bytes: &[u8]) -> CudaResult<Self> {
+        if bytes.len() != Self::SERIALIZED_SIZE {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut handle = [0u8; HANDLE_BYTES];
+        handle.copy_from_slice(&bytes[..HANDLE_BYTES]);
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&bytes[HANDLE_BYTES..]);
+        Ok(IpcMemHandle {
+            handle,
+            len: u64::from_le_bytes(len) as usize,
+            _marker: PhantomData,
+        })
+    }
+
+    fn to_raw(&self) -> CUipcMemHandle {
+        let mut raw = CUipcMemHandle {
+            reserved: [0; HANDLE_BYTES],
+        };
+        for (dst, src) in raw.reserved.iter_mut().zip(self.handle.iter()) {
+            *dst = *src as c_char;
+        }
+        raw
+    }
+}
+impl<T> Clone for IpcMemHandle<T> {
+    fn clone(&self) -> Self {
+        IpcMemHandle {
+            handle: self.handle,
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<T> fmt::Debug for IpcMemHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IpcMemHandle")
+            .field("handle", &&self.handle[..])
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// Device buffer exported by another process and mapped into this one.
+///
+/// Dereferences to a [`DeviceSlice`](struct.DeviceSlice.html), so it can be copied to and from like
+/// any other device memory. The mapping is closed when the `IpcDeviceBuffer` is dropped; the memory
+/// itself remains owned by the exporting process, which must keep the original buffer alive until
+/// all importers have closed their mappings.
+#[derive(Debug)]
+pub struct IpcDeviceBuffer<T> {
+    buf: DevicePointer<T>,
+    len: usize,
+}
+impl<T: DeviceCopy> IpcDeviceBuffer<T> {
+    /// Map a buffer exported by another process into the current context.
+    ///
+    /// Peer access to the exporting device is enabled lazily if the buffer lives on a different
+    /// device than the current context.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error. In particular, a handle cannot be opened by the
+    /// same process which exported it; this returns `InvalidContext`.
+    ///
+    /// # Examples:
+    ///
+    /// ```no_run
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// # let bytes = DeviceBuffer::from_slice(&[0u32; 4]).unwrap().ipc_handle().unwrap().to_bytes();
+    /// // `bytes` were received from the exporting process.
+    /// let handle = unsafe { IpcMemHandle::<u32>::from_bytes(&bytes) }.unwrap();
+    /// let buffer = IpcDeviceBuffer::open(&handle).unwrap();
+    /// let mut host = [0u32; 4];
+    /// buffer.copy_to(&mut host[..]).unwrap();
+    /// ```
+    pub fn open(handle: &IpcMemHandle<T>) -> CudaResult<Self> {
+        unsafe {
+            let mut ptr: u64 = 0;
+            cuda::cuIpcOpenMemHandle(
+                &mut ptr as *mut u64,
+                handle.to_raw(),
+                CUipcMem_flags::CU_IPC_MEM_LAZY_ENABLE_PEER_ACCESS as u32,
+            )
+            .to_result()?;
+            Ok(IpcDeviceBuffer {
+                buf: DevicePointer::wrap(ptr as *mut T),
+                len: handle.len,
+            })
+        }
+    }
+
+    /// Close the mapping of an `IpcDeviceBuffer`, returning an error.
+    ///
+    /// Closing the mapping can return errors from previous asynchronous work. This function closes
+    /// the given mapping and returns the error and the un-closed buffer on failure.
+    pub fn drop(mut ipc_buf: IpcDeviceBuffer<T>) -> DropResult<IpcDeviceBuffer<T>> {
+        if ipc_buf.buf.is_null() {
+            return Ok(());
+        }
+
+        let len = ipc_buf.len;
+        let mut ptr = mem::replace(&mut ipc_buf.buf, DevicePointer::null());
+        unsafe {
+            match cuda::cuIpcCloseMemHandle(ptr.as_raw_mut() as u64).to_result() {
+                Ok(()) => {
+                    mem::forget(ipc_buf);
+                    Ok(())
+                }
+                Err(e) => Err((e, IpcDeviceBuffer { buf: ptr, len })),
+            }
+        }
+    }
+}
+impl<T> Deref for IpcDeviceBuffer<T> {
+    type Target = DeviceSlice<T>;
+
+    fn deref(&self) -> &DeviceSlice<T> {
+        unsafe { DeviceSlice::from_slice(slice::from_raw_parts(self.buf.as_raw(), self.len)) }
+    }
+}
+impl<T> DerefMut for IpcDeviceBuffer<T> {
+    fn deref_mut(&mut self) -> &mut DeviceSlice<T> {
+        unsafe {
+            DeviceSlice::from_slice_mut(slice::from_raw_parts_mut(self.buf.as_raw_mut(), self.len))
+        }
+    }
+}
+impl<T> Drop for IpcDeviceBuffer<T> {
+    fn drop(&mut self) {
+        if self.buf.is_null() {
+            return;
+        }
+
+        let mut ptr = mem::replace(&mut self.buf, DevicePointer::null());
+        // No choice but to panic if this fails.
+        unsafe {
+            cuda::cuIpcCloseMemHandle(ptr.as_raw_mut() as u64)
+                .to_result()
+                .expect("Failed to close CUDA IPC memory handle.");
+        }
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod test_ipc_buffer {
+    use super::*;
+    use crate::error::CudaError;
+    use crate::memory::device::DeviceBuffer;
+
+    #[test]
+    fn test_handle_round_trip() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::from_slice(&[0u64, 1, 2, 3]).unwrap();
+        let handle = buffer.ipc_handle().unwrap();
+        let bytes = handle.to_bytes();
+        let decoded = unsafe { IpcMemHandle::<u64>::from_bytes(&bytes) }.unwrap();
+        assert_eq!(4, decoded.len());
+        assert_eq!(&handle.handle[..], &decoded.handle[..]);
+    }
+
+    #[test]
+    fn test_zero_length_buffer_has_no_handle() {
+        let _context = crate::quick_init().unwrap();
+        let buffer: DeviceBuffer<u64> = unsafe { DeviceBuffer::uninitialized(0).unwrap() };
+        let err = buffer.ipc_handle().unwrap_err();
+        assert_eq!(CudaError::InvalidMemoryAllocation, err);
+    }
+
+    #[test]
+    fn test_from_bytes_wrong_size() {
+        let result = unsafe { IpcMemHandle::<u64>::from_bytes(&[0u8; 10]) };
+        assert_eq!(CudaError::InvalidValue, result.unwrap_err());
+    }
+
+    #[test]
+    fn test_open_in_same_process_fails() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::from_slice(&[0u64, 1, 2, 3]).unwrap();
+        let handle = buffer.ipc_handle().unwrap();
+        let err = IpcDeviceBuffer::open(&handle).unwrap_err();
+        assert_eq!(CudaError::InvalidContext, err);
+    }
+}
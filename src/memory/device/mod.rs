@@ -4,10 +4,14 @@ use crate::stream::Stream;
 mod device_box;
 mod device_buffer;
 mod device_slice;
+mod device_vec;
+mod ipc_buffer;
 
 pub use self::device_box::*;
 pub use self::device_buffer::*;
 pub use self::device_slice::*;
+pub use self::device_vec::*;
+pub use self::ipc_buffer::*;
 
 /// Sealed trait implemented by types which can be the source or destination when copying data
 /// to/from the device or from one device allocation to another.
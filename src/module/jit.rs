@@ -0,0 +1,323 @@
+use crate::error::CudaError;
+use std::cmp;
+use std::error::Error;
+use std::ffi::c_void;
+use std::fmt;
+use std::os::raw::c_uint;
+
+// Values of `CUjit_option` from `cuda.h`.
+const CU_JIT_MAX_REGISTERS: c_uint = 0;
+const CU_JIT_INFO_LOG_BUFFER: c_uint = 3;
+const CU_JIT_INFO_LOG_BUFFER_SIZE_BYTES: c_uint = 4;
+const CU_JIT_ERROR_LOG_BUFFER: c_uint = 5;
+const CU_JIT_ERROR_LOG_BUFFER_SIZE_BYTES: c_uint = 6;
+const CU_JIT_OPTIMIZATION_LEVEL: c_uint = 7;
+const CU_JIT_TARGET: c_uint = 9;
+const CU_JIT_FALLBACK_STRATEGY: c_uint = 10;
+const CU_JIT_GENERATE_DEBUG_INFO: c_uint = 11;
+const CU_JIT_LOG_VERBOSE: c_uint = 12;
+const CU_JIT_GENERATE_LINE_INFO: c_uint = 13;
+const CU_JIT_CACHE_MODE: c_uint = 14;
+
+/// Which code to use when a module contains both PTX and a binary compatible with the device.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum JitFallback {
+    /// Compile the PTX, even if a compatible binary is available.
+    PreferPtx = 0,
+    /// Use the compatible binary, and only compile the PTX if there is none.
+    PreferBinary = 1,
+}
+
+/// The caching behavior the JIT compiler uses for global memory loads.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum JitCacheMode {
+    /// Do not cache global loads. Equivalent to `-dlcm` not being set.
+    NoCache = 0,
+    /// Cache global loads in L2 only. Equivalent to `-dlcm=cg`.
+    CacheGlobal = 1,
+    /// Cache global loads in L1 and L2. Equivalent to `-dlcm=ca`.
+    CacheAll = 2,
+}
+
+/// Options for the JIT compiler used when loading PTX into a module, as well as the size of the
+/// logs it writes.
+///
+/// Every option defaults to the driver's default, and is set with the builder methods.
+///
+/// # Examples:
+///
+/// ```
+/// use rustacuda::module::{JitCacheMode, JitOptions};
+///
+/// let options = JitOptions::new()
+///     .optimization_level(3)
+///     .max_registers(32)
+///     .generate_line_info(true)
+///     .cache_mode(JitCacheMode::CacheGlobal);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitOptions {
+    optimization_level: Option<u32>,
+    target: Option<(u32, u32)>,
+    max_registers: Option<u32>,
+    generate_debug_info: Option<bool>,
+    generate_line_info: Option<bool>,
+    log_verbose: Option<bool>,
+    fallback: Option<JitFallback>,
+    cache_mode: Option<JitCacheMode>,
+    log_buffer_size: usize,
+}
+impl Default for JitOptions {
+    fn default() -> Self {
+        JitOptions {
+            optimization_level: None,
+            target: None,
+            max_registers: None,
+            generate_debug_info: None,
+            generate_line_info: None,
+            log_verbose: None,
+            fallback: None,
+            cache_mode: None,
+            log_buffer_size: 16 * 1024,
+        }
+    }
+}
+impl JitOptions {
+    /// Create a set of options which leaves every option at the driver's default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the optimization level, from 0 (no optimization) to 4 (the default).
+    pub fn optimization_level(mut self, level: u32) -> Self {
+        self.optimization_level = Some(level);
+        self
+    }
+
+    /// Compile for compute capability `major.minor` rather than for the device of the current
+    /// context.
+    pub fn target(mut self, major: u32, minor: u32) -> Self {
+        self.target = Some((major, minor));
+        self
+    }
+
+    /// Limit the number of registers each thread may use.
+    pub fn max_registers(mut self, registers: u32) -> Self {
+        self.max_registers = Some(registers);
+        self
+    }
+
+    /// Generate debug information.
+    pub fn generate_debug_info(mut self, enabled: bool) -> Self {
+        self.generate_debug_info = Some(enabled);
+        self
+    }
+
+    /// Generate line number information.
+    pub fn generate_line_info(mut self, enabled: bool) -> Self {
+        self.generate_line_info = Some(enabled);
+        self
+    }
+
+    /// Write more detailed information to the info log.
+    pub fn log_verbose(mut self, enabled: bool) -> Self {
+        self.log_verbose = Some(enabled);
+        self
+    }
+
+    /// Set whether to prefer PTX or a compatible binary when both are available.
+    pub fn fallback(mut self, fallback: JitFallback) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Set the caching behavior for global memory loads.
+    pub fn cache_mode(mut self, mode: JitCacheMode) -> Self {
+        self.cache_mode = Some(mode);
+        self
+    }
+
+    /// Set the size in bytes of each of the info and error logs. Longer logs are truncated. The
+    /// default is 16 KiB.
+    pub fn log_buffer_size(mut self, bytes: usize) -> Self {
+        self.log_buffer_size = bytes;
+        self
+    }
+
+    // Returns the raw options and values to pass to the driver, writing the logs to `buffers`.
+    pub(crate) fn to_raw(&self, buffers: &mut JitLogBuffers) -> (Vec<c_uint>, Vec<*mut c_void>) {
+        // Options which are not pointers are passed as values cast to pointers.
+        fn value(value: u32) -> *mut c_void {
+            value as usize as *mut c_void
+        }
+
+        // The log options must come first; see `JitLogBuffers::read`.
+        let mut options = vec![
+            CU_JIT_INFO_LOG_BUFFER,
+            CU_JIT_INFO_LOG_BUFFER_SIZE_BYTES,
+            CU_JIT_ERROR_LOG_BUFFER,
+            CU_JIT_ERROR_LOG_BUFFER_SIZE_BYTES,
+        ];
+        let mut values = vec![
+            buffers.info.as_mut_ptr() as *mut c_void,
+            buffers.info.len() as *mut c_void,
+            buffers.error.as_mut_ptr() as *mut c_void,
+            buffers.error.len() as *mut c_void,
+        ];
+
+        let mut push = |option, raw| {
+            options.push(option);
+            values.push(raw);
+        };
+        if let Some(level) = self.optimization_level {
+            push(CU_JIT_OPTIMIZATION_LEVEL, value(level));
+        }
+        if let Some((major, minor)) = self.target {
+            push(CU_JIT_TARGET, value(major * 10 + minor));
+        }
+        if let Some(registers) = self.max_registers {
+            push(CU_JIT_MAX_REGISTERS, value(registers));
+        }
+        if let Some(enabled) = self.generate_debug_info {
+            push(CU_JIT_GENERATE_DEBUG_INFO, value(enabled as u32));
+        }
+        if let Some(enabled) = self.generate_line_info {
+            push(CU_JIT_GENERATE_LINE_INFO, value(enabled as u32));
+        }
+        if let Some(enabled) = self.log_verbose {
+            push(CU_JIT_LOG_VERBOSE, value(enabled as u32));
+        }
+        if let Some(fallback) = self.fallback {
+            push(CU_JIT_FALLBACK_STRATEGY, value(fallback as u32));
+        }
+        if let Some(mode) = self.cache_mode {
+            push(CU_JIT_CACHE_MODE, value(mode as u32));
+        }
+        (options, values)
+    }
+}
+
+// The buffers the JIT compiler writes its logs to.
+pub(crate) struct JitLogBuffers {
+    info: Vec<u8>,
+    error: Vec<u8>,
+}
+impl JitLogBuffers {
+    pub(crate) fn new(options: &JitOptions) -> Self {
+        // The driver requires space for at least the terminating NUL.
+        let size = cmp::max(options.log_buffer_size, 1);
+        JitLogBuffers {
+            info: vec![0; size],
+            error: vec![0; size],
+        }
+    }
+
+    // Read the logs once the driver has written them. `values` are the option values returned by
+    // `JitOptions::to_raw`, which the driver updates with the number of bytes written.
+    pub(crate) fn read(&self, values: &[*mut c_void]) -> JitLog {
+        fn log(buffer: &[u8], written: *mut c_void) -> String {
+            let written = cmp::min(written as usize, buffer.len());
+            let buffer = &buffer[..written];
+            let end = buffer.iter().position(|&b| b == 0).unwrap_or(written);
+            String::from_utf8_lossy(&buffer[..end]).into_owned()
+        }
+
+        JitLog {
+            info: log(&self.info, values[1]),
+            error: log(&self.error, values[3]),
+        }
+    }
+}
+
+/// The info and error logs written by the JIT compiler.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JitLog {
+    /// Informational messages, such as register usage when `JitOptions::log_verbose` is set.
+    pub info: String,
+    /// Error messages, such as PTX syntax errors.
+    pub error: String,
+}
+
+/// Error returned when JIT compilation or linking fails, with the compiler's logs.
+///
+/// Converts into the underlying `CudaError`, so `?` can still be used in functions returning
+/// `CudaResult`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitError {
+    /// The error returned by the driver, such as `InvalidPtx`.
+    pub error: CudaError,
+    /// The logs written by the JIT compiler, which usually explain the error.
+    pub log: JitLog,
+}
+impl fmt::Display for JitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JIT compilation failed: {}", self.error)?;
+        if !self.log.error.is_empty() {
+            write!(f, "\n{}", self.log.error.trim_end())?;
+        }
+        Ok(())
+    }
+}
+impl Error for JitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+impl From<JitError> for CudaError {
+    fn from(error: JitError) -> CudaError {
+        error.error
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn test_raw_options() {
+        let options = JitOptions::new()
+            .target(7, 5)
+            .generate_debug_info(true)
+            .fallback(JitFallback::PreferBinary)
+            .log_buffer_size(64);
+        let mut buffers = JitLogBuffers::new(&options);
+        let (raw, values) = options.to_raw(&mut buffers);
+        assert_eq!(
+            vec![
+                CU_JIT_INFO_LOG_BUFFER,
+                CU_JIT_INFO_LOG_BUFFER_SIZE_BYTES,
+                CU_JIT_ERROR_LOG_BUFFER,
+                CU_JIT_ERROR_LOG_BUFFER_SIZE_BYTES,
+                CU_JIT_TARGET,
+                CU_JIT_GENERATE_DEBUG_INFO,
+                CU_JIT_FALLBACK_STRATEGY,
+            ],
+            raw
+        );
+        assert_eq!(64, values[1] as usize);
+        assert_eq!(75, values[4] as usize);
+        assert_eq!(1, values[5] as usize);
+        assert_eq!(1, values[6] as usize);
+    }
+
+    #[test]
+    fn test_read_logs() {
+        let options = JitOptions::new().log_buffer_size(16);
+        let mut buffers = JitLogBuffers::new(&options);
+        buffers.info[..5].copy_from_slice(b"info\0");
+        buffers.error[..6].copy_from_slice(b"error!");
+        // The driver reports how many bytes it wrote, which may include the terminating NUL.
+        let values = vec![
+            ptr::null_mut(),
+            5 as *mut c_void,
+            ptr::null_mut(),
+            5 as *mut c_void,
+        ];
+        let log = buffers.read(&values);
+        assert_eq!("info", log.info);
+        assert_eq!("error", log.error);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::error::{CudaError, ToResult};
+use crate::module::{path_to_cstring, JitError, JitLog, JitLogBuffers, JitOptions, Module};
+use cuda_sys::cuda::{self, CUjitInputType, CUjit_option, CUlinkState};
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::os::raw::c_uint;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+/// The kind of an input to a [`Linker`](struct.Linker.html).
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum JitInputType {
+    /// Compiled device code for a specific architecture.
+    Cubin = 0,
+    /// PTX source, which is compiled before linking.
+    Ptx = 1,
+    /// A bundle of cubins and PTX for several architectures, as produced by `nvcc -fatbin`.
+    Fatbinary = 2,
+    /// A host object file with embedded relocatable device code, as produced by `nvcc -rdc`.
+    Object = 3,
+    /// An archive of host object files with embedded relocatable device code, such as
+    /// `libcudadevrt.a`.
+    Library = 4,
+}
+impl JitInputType {
+    fn to_raw(self) -> CUjitInputType {
+        match self {
+            JitInputType::Cubin => CUjitInputType::CU_JIT_INPUT_CUBIN,
+            JitInputType::Ptx => CUjitInputType::CU_JIT_INPUT_PTX,
+            JitInputType::Fatbinary => CUjitInputType::CU_JIT_INPUT_FATBINARY,
+            JitInputType::Object => CUjitInputType::CU_JIT_INPUT_OBJECT,
+            JitInputType::Library => CUjitInputType::CU_JIT_INPUT_LIBRARY,
+        }
+    }
+}
+
+/// Links several pieces of device code, such as PTX files compiled with relocatable device code
+/// and device libraries, into a single cubin.
+///
+/// Inputs are added with [`add_data`](#method.add_data) and [`add_file`](#method.add_file). PTX
+/// inputs are compiled with the `JitOptions` the linker was created with. Once all inputs have
+/// been added, [`complete`](#method.complete) returns the linked cubin, and
+/// [`load`](#method.load) loads it into the current context as a `Module`.
+///
+/// # Examples:
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::module::{JitInputType, JitOptions, Linker};
+///
+/// let mut linker = Linker::new(&JitOptions::new().optimization_level(3))?;
+/// linker.add_data(
+///     JitInputType::Ptx,
+///     include_bytes!("../../resources/add.ptx"),
+///     "add.ptx",
+/// )?;
+/// // Device libraries can be added from files, for example:
+/// // linker.add_file(JitInputType::Library, Path::new("/usr/local/cuda/lib64/libcudadevrt.a"))?;
+/// let (module, log) = linker.load()?;
+/// println!("{}", log.info);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Linker {
+    state: CUlinkState,
+    // The driver writes the logs and their sizes into these until the state is destroyed.
+    options: Vec<c_uint>,
+    values: Vec<*mut c_void>,
+    buffers: JitLogBuffers,
+}
+impl Linker {
+    /// Create a linker which compiles PTX inputs with the given options.
+    ///
+    /// # Errors:
+    ///
+    /// Returns a `JitError` if the driver rejects the options.
+    pub fn new(options: &JitOptions) -> Result<Linker, JitError> {
+        let mut buffers = JitLogBuffers::new(options);
+        let (options, values) = options.to_raw(&mut buffers);
+        let mut linker = Linker {
+            state: ptr::null_mut(),
+            options,
+            values,
+            buffers,
+        };
+        unsafe {
+            let result = cuda::cuLinkCreate_v2(
+                linker.options.len() as c_uint,
+                linker.options.as_mut_ptr() as *mut CUjit_option,
+                linker.values.as_mut_ptr(),
+                &mut linker.state,
+            )
+            .to_result();
+            result.map_err(|error| linker.error(error))?;
+        }
+        Ok(linker)
+    }
+
+    /// Add an input held in memory. `name` is only used in log messages.
+    ///
+    /// PTX inputs do not need to be NUL-terminated.
+    ///
+    /// # Errors:
+    ///
+    /// Returns a `JitError` holding the compiler's logs if the input cannot be added, for example
+    /// because PTX fails to compile. Returns `InvalidValue` if `name` contains a NUL byte.
+    pub fn add_data(
+        &mut self,
+        input_type: JitInputType,
+        data: &[u8],
+        name: &str,
+    ) -> Result<(), JitError> {
+        let name = CString::new(name).map_err(|_| self.error(CudaError::InvalidValue))?;
+
+        // The driver requires PTX to be NUL-terminated.
+        let mut terminated;
+        let data = if input_type == JitInputType::Ptx && data.last() != Some(&0) {
+            terminated = Vec::with_capacity(data.len() + 1);
+            terminated.extend_from_slice(data);
+            terminated.push(0);
+            &terminated[..]
+        } else {
+            data
+        };
+
+        unsafe {
+            cuda::cuLinkAddData_v2(
+                self.state,
+                input_type.to_raw(),
+                data.as_ptr() as *mut c_void,
+                data.len(),
+                name.as_ptr(),
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+            .to_result()
+            .map_err(|error| self.error(error))
+        }
+    }
+
+    /// Add an input read from the file at `path`.
+    ///
+    /// # Errors:
+    ///
+    /// Returns a `JitError` holding the compiler's logs if the input cannot be added, for example
+    /// because the file does not exist or PTX fails to compile. Returns `InvalidValue` if `path`
+    /// contains a NUL byte or, on platforms other than Unix, is not valid UTF-8.
+    pub fn add_file(&mut self, input_type: JitInputType, path: &Path) -> Result<(), JitError> {
+        let path = path_to_cstring(path).ok_or_else(|| self.error(CudaError::InvalidValue))?;
+        unsafe {
+            cuda::cuLinkAddFile_v2(
+                self.state,
+                input_type.to_raw(),
+                path.as_ptr(),
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+            .to_result()
+            .map_err(|error| self.error(error))
+        }
+    }
+
+    /// Returns the logs written by the compiler and linker so far.
+    pub fn log(&self) -> JitLog {
+        self.buffers.read(&self.values)
+    }
+
+    /// Link the inputs added so far and return the linked cubin, along with the logs.
+    ///
+    /// The cubin can be saved, for example to avoid linking again the next time the program runs,
+    /// and loaded with `Module::load_from_bytes`.
+    ///
+    /// # Errors:
+    ///
+    /// Returns a `JitError` holding the logs if linking fails, for example because of an
+    /// unresolved symbol.
+    pub fn complete(self) -> Result<(Vec<u8>, JitLog), JitError> {
+        self.complete_with(|cubin| Ok(cubin.to_vec()))
+    }
+
+    /// Link the inputs added so far and load the result into the current context.
+    ///
+    /// # Errors:
+    ///
+    /// Returns a `JitError` holding the logs if linking or loading fails.
+    pub fn load(self) -> Result<(Module, JitLog), JitError> {
+        self.complete_with(|cubin| unsafe { Module::load_data(cubin.as_ptr() as *const c_void) })
+    }
+
+    // Complete the link and pass the cubin, which is owned by the link state, to `f`.
+    fn complete_with<T, F>(self, f: F) -> Result<(T, JitLog), JitError>
+    where
+        F: FnOnce(&[u8]) -> Result<T, CudaError>,
+    {
+        unsafe {
+            let mut cubin = ptr::null_mut();
+            let mut size = 0;
+            let result = cuda::cuLinkComplete(self.state, &mut cubin, &mut size)
+                .to_result()
+                .and_then(|()| f(slice::from_raw_parts(cubin as *const u8, size)));
+            match result {
+                Ok(value) => Ok((value, self.log())),
+                Err(error) => Err(self.error(error)),
+            }
+        }
+    }
+
+    fn error(&self, error: CudaError) -> JitError {
+        JitError {
+            error,
+            log: self.log(),
+        }
+    }
+}
+impl Drop for Linker {
+    fn drop(&mut self) {
+        if self.state.is_null() {
+            return;
+        }
+        unsafe {
+            // No choice but to panic if this fails...
+            cuda::cuLinkDestroy(self.state)
+                .to_result()
+                .expect("Failed to destroy CUDA link state");
+        }
+    }
+}
+impl fmt::Debug for Linker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Linker")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::quick_init;
+    use std::error::Error;
+
+    #[test]
+    fn test_link_ptx() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let mut linker = Linker::new(&JitOptions::new())?;
+        // Not NUL-terminated.
+        linker.add_data(
+            JitInputType::Ptx,
+            include_bytes!("../../resources/add.ptx"),
+            "add.ptx",
+        )?;
+        let (cubin, _) = linker.complete()?;
+        assert_eq!(b"\x7fELF", &cubin[..4]);
+
+        let mut linker = Linker::new(&JitOptions::new())?;
+        linker.add_data(JitInputType::Cubin, &cubin, "add.cubin")?;
+        let (module, _) = linker.load()?;
+        let _ = module.get_function(&CString::new("sum")?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_errors_carry_logs() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let mut linker = Linker::new(&JitOptions::new())?;
+        let error = linker
+            .add_data(
+                JitInputType::Ptx,
+                b".version 6.0\n.target sm_30\nbogus;\n",
+                "bad",
+            )
+            .unwrap_err();
+        assert_eq!(CudaError::InvalidPtx, error.error);
+        assert!(!error.log.error.is_empty());
+
+        let error = linker
+            .add_file(JitInputType::Ptx, Path::new("./resources/missing.ptx"))
+            .unwrap_err();
+        assert_eq!(CudaError::FileNotFound, error.error);
+        Ok(())
+    }
+}
@@ -0,0 +1,907 @@
+//! Functions and types for working with CUDA modules.
+
+use crate::error::{CudaError, CudaResult, DropResult, ToResult};
+use crate::ffi;
+use crate::function::{Function, Kernel, KernelArgs};
+use crate::memory::{CopyDestination, DeviceCopy, DevicePointer};
+use cuda_sys::cuda;
+use std::cmp;
+use std::convert::TryFrom;
+use std::ffi::{c_void, CStr, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_uint;
+use std::path::Path;
+use std::ptr;
+
+mod jit;
+mod link;
+
+pub use self::jit::*;
+pub use self::link::*;
+
+/// The format of a module image, as detected by [`ImageFormat::detect`](#method.detect).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// PTX source text.
+    Ptx,
+    /// A cubin: an ELF file containing device code for a specific architecture.
+    Cubin,
+    /// A fatbin: a bundle of cubins and PTX for several architectures.
+    Fatbin,
+}
+impl ImageFormat {
+    /// Detect the format of a module image from its first bytes.
+    ///
+    /// Cubins are recognized by the ELF magic number and fatbins by the fatbin magic number.
+    /// Anything else is assumed to be PTX if it is text without NUL bytes, apart from trailing
+    /// ones. Returns `None` otherwise.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use rustacuda::module::ImageFormat;
+    ///
+    /// let ptx = include_bytes!("../../resources/add.ptx");
+    /// assert_eq!(Some(ImageFormat::Ptx), ImageFormat::detect(ptx));
+    /// assert_eq!(Some(ImageFormat::Cubin), ImageFormat::detect(b"\x7fELF\x02\x01\x01"));
+    /// ```
+    pub fn detect(image: &[u8]) -> Option<ImageFormat> {
+        const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+        // 0xBA55ED50, stored little-endian.
+        const FATBIN_MAGIC: [u8; 4] = [0x50, 0xed, 0x55, 0xba];
+
+        if image.starts_with(&ELF_MAGIC) {
+            return Some(ImageFormat::Cubin);
+        }
+        if image.starts_with(&FATBIN_MAGIC) {
+            return Some(ImageFormat::Fatbin);
+        }
+        let end = image
+            .iter()
+            .rposition(|&b| b != 0)
+            .map_or(0, |last| last + 1);
+        let text = &image[..end];
+        if !text.is_empty() && !text.contains(&0) && std::str::from_utf8(text).is_ok() {
+            Some(ImageFormat::Ptx)
+        } else {
+            None
+        }
+    }
+
+    // Returns the number of bytes a cubin or fatbin spans according to its headers, or `None` if
+    // the headers cannot be read. The driver trusts these sizes rather than taking a length.
+    fn binary_len(self, image: &[u8]) -> Option<usize> {
+        let len = match self {
+            ImageFormat::Ptx => return None,
+            ImageFormat::Cubin => elf_len(image)?,
+            ImageFormat::Fatbin => {
+                // struct { u32 magic; u16 version; u16 header_size; u64 fat_size; }
+                let header_size = u64::from(read_u16(image, 6)?);
+                header_size.checked_add(read_u64(image, 8)?)?
+            }
+        };
+        usize::try_from(len).ok()
+    }
+}
+
+// Returns the number of bytes spanned by an ELF file's header, program and section header tables
+// and the contents of its segments and sections.
+fn elf_len(image: &[u8]) -> Option<u64> {
+    const EI_CLASS: usize = 4;
+    const EI_DATA: usize = 5;
+    const ELFDATA2LSB: u8 = 1;
+    const SHT_NOBITS: u32 = 8;
+
+    let is_64 = match *image.get(EI_CLASS)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    if *image.get(EI_DATA)? != ELFDATA2LSB {
+        return None;
+    }
+    // Read an address-sized field.
+    let word = |offset: u64| -> Option<u64> {
+        let offset = usize::try_from(offset).ok()?;
+        if is_64 {
+            read_u64(image, offset)
+        } else {
+            read_u32(image, offset).map(u64::from)
+        }
+    };
+    // Field offsets in the file header and in each program and section header.
+    let (header_len, e_phoff, e_shoff, e_phentsize, p_offset, p_filesz, sh_offset, sh_size) =
+        if is_64 {
+            (64, 0x20, 0x28, 0x36, 0x08, 0x20, 0x18, 0x20)
+        } else {
+            (52, 0x1c, 0x20, 0x2a, 0x04, 0x10, 0x10, 0x14)
+        };
+
+    let phoff = word(e_phoff)?;
+    let shoff = word(e_shoff)?;
+    let phentsize = u64::from(read_u16(image, e_phentsize)?);
+    let phnum = u64::from(read_u16(image, e_phentsize + 2)?);
+    let shentsize = u64::from(read_u16(image, e_phentsize + 4)?);
+    let shnum = u64::from(read_u16(image, e_phentsize + 6)?);
+
+    let mut len = cmp::max(
+        header_len,
+        cmp::max(
+            phoff.checked_add(phentsize.checked_mul(phnum)?)?,
+            shoff.checked_add(shentsize.checked_mul(shnum)?)?,
+        ),
+    );
+    if len > image.len() as u64 {
+        // The tables themselves are cut off.
+        return Some(len);
+    }
+
+    for i in 0..phnum {
+        let header = phoff + i * phentsize;
+        let end = word(header + p_offset)?.checked_add(word(header + p_filesz)?)?;
+        len = cmp::max(len, end);
+    }
+    for i in 0..shnum {
+        let header = shoff + i * shentsize;
+        let sh_type = read_u32(image, usize::try_from(header + 4).ok()?)?;
+        if sh_type != SHT_NOBITS {
+            let end = word(header + sh_offset)?.checked_add(word(header + sh_size)?)?;
+            len = cmp::max(len, end);
+        }
+    }
+    Some(len)
+}
+
+fn read_u16(image: &[u8], offset: usize) -> Option<u16> {
+    let bytes = image.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(image: &[u8], offset: usize) -> Option<u32> {
+    let bytes = image.get(offset..offset.checked_add(4)?)?;
+    let mut raw = [0; 4];
+    raw.copy_from_slice(bytes);
+    Some(u32::from_le_bytes(raw))
+}
+
+fn read_u64(image: &[u8], offset: usize) -> Option<u64> {
+    let bytes = image.get(offset..offset.checked_add(8)?)?;
+    let mut raw = [0; 8];
+    raw.copy_from_slice(bytes);
+    Some(u64::from_le_bytes(raw))
+}
+
+// Convert a path to the NUL-terminated string the driver expects. Unix paths are passed through as
+// raw bytes, so they need not be valid UTF-8.
+#[cfg(unix)]
+pub(crate) fn path_to_cstring(path: &Path) -> Option<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_to_cstring(path: &Path) -> Option<CString> {
+    path.to_str().and_then(|path| CString::new(path).ok())
+}
+
+/// A compiled CUDA module, loaded into a context.
+#[derive(Debug)]
+pub struct Module {
+    inner: cuda::CUmodule,
+}
+impl Module {
+    /// Load a module from the given file name into the current context.
+    ///
+    /// The given file should be either a cubin file, a ptx file, or a fatbin file such as
+    /// those produced by `nvcc`.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let filename = CString::new("./resources/add.ptx")?;
+    /// let module = Module::load_from_file(&filename)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_from_file(filename: &CStr) -> CudaResult<Module> {
+        unsafe {
+            let mut module = Module {
+                inner: ptr::null_mut(),
+            };
+            cuda::cuModuleLoad(&mut module.inner as *mut cuda::CUmodule, filename.as_ptr())
+                .to_result()?;
+            Ok(module)
+        }
+    }
+
+    /// Load a module from a CStr.
+    ///
+    /// This is useful in combination with `include_str!`, to include the device code into the
+    /// compiled executable.
+    ///
+    /// The given CStr must contain the bytes of a cubin file, a ptx file or a fatbin file such as
+    /// those produced by `nvcc`.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let image = CString::new(include_str!("../../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&image)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_from_string(image: &CStr) -> CudaResult<Module> {
+        unsafe { Module::load_data(image.as_ptr() as *const c_void) }
+    }
+
+    /// Load a module from the bytes of a PTX, cubin or fatbin image.
+    ///
+    /// Unlike [`load_from_string`](#method.load_from_string), the image does not need to be
+    /// NUL-terminated and may be binary. Its format is detected with `ImageFormat::detect`, and
+    /// PTX is NUL-terminated if necessary. Cubins and fatbins are checked to be complete, since
+    /// the driver reads as many bytes as their headers say they contain.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidImage` if the format of the image is not recognized or a cubin or fatbin is
+    /// truncated, or any error returned while loading it, such as `InvalidPtx` or
+    /// `NoBinaryForGpu`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    ///
+    /// let module = Module::load_from_bytes(include_bytes!("../../resources/add.ptx"))?;
+    /// let sum = module.get_function_by_name("sum")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_from_bytes(image: &[u8]) -> CudaResult<Module> {
+        match ImageFormat::detect(image) {
+            Some(ImageFormat::Ptx) if image.last() != Some(&0) => {
+                let mut terminated = Vec::with_capacity(image.len() + 1);
+                terminated.extend_from_slice(image);
+                terminated.push(0);
+                unsafe { Module::load_data(terminated.as_ptr() as *const c_void) }
+            }
+            Some(ImageFormat::Ptx) => unsafe { Module::load_data(image.as_ptr() as *const c_void) },
+            Some(format) => match format.binary_len(image) {
+                Some(len) if len <= image.len() => unsafe {
+                    Module::load_data(image.as_ptr() as *const c_void)
+                },
+                _ => Err(CudaError::InvalidImage),
+            },
+            None => Err(CudaError::InvalidImage),
+        }
+    }
+
+    /// Load a module from the file at `path` into the current context.
+    ///
+    /// The file should be a cubin, PTX or fatbin file, as for
+    /// [`load_from_file`](#method.load_from_file).
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `path` contains a NUL byte or, on platforms other than Unix, is
+    /// not valid UTF-8. Returns `FileNotFound` if the file does not exist, or any error returned
+    /// while loading it.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::path::Path;
+    ///
+    /// let module = Module::load_from_path(Path::new("./resources/add.ptx"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_from_path(path: &Path) -> CudaResult<Module> {
+        let path = path_to_cstring(path).ok_or(CudaError::InvalidValue)?;
+        Module::load_from_file(&path)
+    }
+
+    // Load a module from a pointer to a PTX, cubin or fatbin image. PTX must be NUL-terminated.
+    pub(crate) unsafe fn load_data(image: *const c_void) -> CudaResult<Module> {
+        let mut module = Module {
+            inner: ptr::null_mut(),
+        };
+        cuda::cuModuleLoadData(&mut module.inner as *mut cuda::CUmodule, image).to_result()?;
+        Ok(module)
+    }
+
+    /// Load a module from a CStr, passing `options` to the JIT compiler.
+    ///
+    /// The image is the same as for [`load_from_string`](#method.load_from_string). The options
+    /// only affect PTX, which is compiled for the device when it is loaded. The compiler's info
+    /// and error logs are returned along with the module, or with the error if loading fails.
+    ///
+    /// # Errors:
+    ///
+    /// Returns a `JitError` holding the driver's error, such as `InvalidPtx`, and the compiler's
+    /// logs, which usually explain what went wrong.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::{JitOptions, Module};
+    /// use std::ffi::CString;
+    ///
+    /// let image = CString::new(include_str!("../../resources/add.ptx"))?;
+    /// let options = JitOptions::new()
+    ///     .optimization_level(3)
+    ///     .log_verbose(true);
+    /// let (module, log) = Module::load_with_options(&image, &options)?;
+    /// println!("{}", log.info);
+    ///
+    /// let broken = CString::new(".version 6.0\n.target sm_30\nnot ptx")?;
+    /// let error = Module::load_with_options(&broken, &options).unwrap_err();
+    /// println!("{}", error.log.error);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_with_options(
+        image: &CStr,
+        options: &JitOptions,
+    ) -> Result<(Module, JitLog), JitError> {
+        let mut buffers = JitLogBuffers::new(options);
+        let (mut raw_options, mut values) = options.to_raw(&mut buffers);
+        unsafe {
+            let mut module = Module {
+                inner: ptr::null_mut(),
+            };
+            let result = ffi::cuModuleLoadDataEx(
+                &mut module.inner as *mut cuda::CUmodule,
+                image.as_ptr() as *const c_void,
+                raw_options.len() as c_uint,
+                raw_options.as_mut_ptr(),
+                values.as_mut_ptr(),
+            )
+            .to_result();
+            let log = buffers.read(&values);
+            match result {
+                Ok(()) => Ok((module, log)),
+                Err(error) => Err(JitError { error, log }),
+            }
+        }
+    }
+
+    /// Get a reference to a global symbol, which can then be copied to/from.
+    ///
+    /// # Panics:
+    ///
+    /// This function panics if the size of the symbol is not the same as the `mem::sizeof<T>()`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use rustacuda::memory::CopyDestination;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let name = CString::new("my_constant")?;
+    /// let symbol = module.get_global::<u32>(&name)?;
+    /// let mut host_const = 0;
+    /// symbol.copy_to(&mut host_const)?;
+    /// assert_eq!(314, host_const);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_global<'a, T: DeviceCopy>(&'a self, name: &CStr) -> CudaResult<Symbol<'a, T>> {
+        unsafe {
+            let mut ptr: DevicePointer<T> = DevicePointer::null();
+            let mut size: usize = 0;
+
+            cuda::cuModuleGetGlobal_v2(
+                &mut ptr as *mut DevicePointer<T> as *mut cuda::CUdeviceptr,
+                &mut size as *mut usize,
+                self.inner,
+                name.as_ptr(),
+            )
+            .to_result()?;
+            assert_eq!(size, mem::size_of::<T>());
+            Ok(Symbol {
+                ptr,
+                module: PhantomData,
+            })
+        }
+    }
+
+    /// Get a reference to a kernel function which can then be launched.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let name = CString::new("sum")?;
+    /// let function = module.get_function(&name)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_function<'a>(&'a self, name: &CStr) -> CudaResult<Function<'a>> {
+        unsafe {
+            let mut func: cuda::CUfunction = ptr::null_mut();
+
+            cuda::cuModuleGetFunction(
+                &mut func as *mut cuda::CUfunction,
+                self.inner,
+                name.as_ptr(),
+            )
+            .to_result()?;
+            Ok(Function::new(func, self))
+        }
+    }
+
+    /// Get a reference to a global symbol by name, like [`get_global`](#method.get_global).
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `name` contains a NUL byte, or `NotFound` if the module has no
+    /// global with that name.
+    ///
+    /// # Panics:
+    ///
+    /// This function panics if the size of the symbol is not the same as the `mem::sizeof<T>()`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use rustacuda::memory::CopyDestination;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    ///
+    /// let module = Module::load_from_bytes(include_bytes!("../../resources/add.ptx"))?;
+    /// let symbol = module.get_global_by_name::<u32>("my_constant")?;
+    /// let mut host_const = 0;
+    /// symbol.copy_to(&mut host_const)?;
+    /// assert_eq!(314, host_const);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_global_by_name<'a, T: DeviceCopy>(
+        &'a self,
+        name: &str,
+    ) -> CudaResult<Symbol<'a, T>> {
+        let name = CString::new(name).map_err(|_| CudaError::InvalidValue)?;
+        self.get_global(&name)
+    }
+
+    /// Get a reference to a kernel function by name, like [`get_function`](#method.get_function).
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `name` contains a NUL byte, or `NotFound` if the module has no
+    /// function with that name.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    ///
+    /// let module = Module::load_from_bytes(include_bytes!("../../resources/add.ptx"))?;
+    /// let function = module.get_function_by_name("sum")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_function_by_name<'a>(&'a self, name: &str) -> CudaResult<Function<'a>> {
+        let name = CString::new(name).map_err(|_| CudaError::InvalidValue)?;
+        self.get_function(&name)
+    }
+
+    /// Get a handle to a kernel function in this module, typed with its parameter list.
+    ///
+    /// `Args` is a tuple of the kernel's parameter types, in order. The returned
+    /// [`Kernel`](../function/struct.Kernel.html) can only be launched with arguments of exactly
+    /// that type. The parameter list is not recorded in the module, so the driver cannot check
+    /// that it matches the kernel; it is the caller's responsibility to get it right once, here,
+    /// rather than at every launch.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `name` contains a NUL byte, or `NotFound` if the module has no
+    /// function with that name.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::memory::DevicePointer;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let sum = module
+    ///     .get_kernel::<(DevicePointer<f32>, DevicePointer<f32>, DevicePointer<f32>, u32)>("sum")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_kernel<'a, Args: KernelArgs>(&'a self, name: &str) -> CudaResult<Kernel<'a, Args>> {
+        let function = self.get_function_by_name(name)?;
+        unsafe { Ok(Kernel::from_function(function)) }
+    }
+
+    /// Destroy a `Module`, returning an error.
+    ///
+    /// Destroying a module can return errors from previous asynchronous work. This function
+    /// destroys the given module and returns the error and the un-destroyed module on failure.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// match Module::drop(module) {
+    ///     Ok(()) => println!("Successfully destroyed"),
+    ///     Err((e, module)) => {
+    ///         println!("Failed to destroy module: {:?}", e);
+    ///         // Do something with module
+    ///     },
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn drop(mut module: Module) -> DropResult<Module> {
+        if module.inner.is_null() {
+            return Ok(());
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut module.inner, ptr::null_mut());
+            match cuda::cuModuleUnload(inner).to_result() {
+                Ok(()) => {
+                    mem::forget(module);
+                    Ok(())
+                }
+                Err(e) => Err((e, Module { inner })),
+            }
+        }
+    }
+}
+impl Drop for Module {
+    fn drop(&mut self) {
+        if self.inner.is_null() {
+            return;
+        }
+        unsafe {
+            // No choice but to panic if this fails...
+            let module = mem::replace(&mut self.inner, ptr::null_mut());
+            cuda::cuModuleUnload(module)
+                .to_result()
+                .expect("Failed to unload CUDA module");
+        }
+    }
+}
+
+/// Handle to a symbol defined within a CUDA module.
+#[derive(Debug)]
+pub struct Symbol<'a, T: DeviceCopy> {
+    ptr: DevicePointer<T>,
+    module: PhantomData<&'a Module>,
+}
+impl<'a, T: DeviceCopy> crate::private::Sealed for Symbol<'a, T> {}
+impl<'a, T: DeviceCopy> fmt::Pointer for Symbol<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Pointer::fmt(&self.ptr, f)
+    }
+}
+impl<'a, T: DeviceCopy> CopyDestination<T> for Symbol<'a, T> {
+    fn copy_from(&mut self, val: &T) -> CudaResult<()> {
+        let size = mem::size_of::<T>();
+        if size != 0 {
+            unsafe {
+                ffi::cuMemcpyHtoD_v2(
+                    self.ptr.as_raw_mut() as u64,
+                    val as *const T as *const c_void,
+                    size,
+                )
+                .to_result()?
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_to(&self, val: &mut T) -> CudaResult<()> {
+        let size = mem::size_of::<T>();
+        if size != 0 {
+            unsafe {
+                ffi::cuMemcpyDtoH_v2(
+                    val as *const T as *mut c_void,
+                    self.ptr.as_raw() as u64,
+                    size,
+                )
+                .to_result()?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::quick_init;
+    use std::error::Error;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_load_from_file() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+
+        let filename = CString::new("./resources/add.ptx")?;
+        let module = Module::load_from_file(&filename)?;
+        drop(module);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_from_memory() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        drop(module);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_to_cstring() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"./kernels/\xff.ptx"));
+        assert_eq!(
+            b"./kernels/\xff.ptx",
+            path_to_cstring(path).unwrap().as_bytes()
+        );
+        assert_eq!(None, path_to_cstring(Path::new("a\0b.ptx")));
+    }
+
+    #[test]
+    fn test_detect_image_format() {
+        let ptx = include_bytes!("../../resources/add.ptx");
+        assert_eq!(Some(ImageFormat::Ptx), ImageFormat::detect(ptx));
+        assert_eq!(
+            Some(ImageFormat::Ptx),
+            ImageFormat::detect(b".version 6.0\n\0\0")
+        );
+        assert_eq!(
+            Some(ImageFormat::Cubin),
+            ImageFormat::detect(b"\x7fELF\x02\x01\x01\0")
+        );
+        assert_eq!(
+            Some(ImageFormat::Fatbin),
+            ImageFormat::detect(b"\x50\xed\x55\xba\x01\0")
+        );
+        assert_eq!(None, ImageFormat::detect(b""));
+        assert_eq!(None, ImageFormat::detect(b"\0\0"));
+        assert_eq!(None, ImageFormat::detect(b".version\0 6.0"));
+        assert_eq!(None, ImageFormat::detect(b"\xff\xfe\x00\x01"));
+    }
+
+    // A 64-bit ELF header with one section header table entry at `shoff` describing `size`
+    // bytes at `offset`.
+    fn elf_image(shoff: u64, offset: u64, size: u64) -> Vec<u8> {
+        let mut image = vec![0u8; 64];
+        image[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        image[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        image[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        image[0x3c..0x3e].copy_from_slice(&1u16.to_le_bytes());
+        image.resize(shoff as usize + 64, 0);
+        let header = shoff as usize;
+        image[header + 4..header + 8].copy_from_slice(&1u32.to_le_bytes());
+        image[header + 0x18..header + 0x20].copy_from_slice(&offset.to_le_bytes());
+        image[header + 0x20..header + 0x28].copy_from_slice(&size.to_le_bytes());
+        image
+    }
+
+    #[test]
+    fn test_binary_image_len() {
+        let cubin = ImageFormat::Cubin;
+        assert_eq!(None, cubin.binary_len(b"\x7fELF\x02\x01\x01"));
+        assert_eq!(Some(128), cubin.binary_len(&elf_image(64, 0, 64)));
+        assert_eq!(Some(256), cubin.binary_len(&elf_image(64, 128, 128)));
+        assert_eq!(None, cubin.binary_len(&elf_image(64, u64::MAX, 2)));
+        // The section header table itself is cut off.
+        assert_eq!(Some(128), cubin.binary_len(&elf_image(64, 0, 0)[..100]));
+
+        let mut fatbin = vec![0x50, 0xed, 0x55, 0xba, 1, 0, 16, 0];
+        fatbin.extend_from_slice(&32u64.to_le_bytes());
+        assert_eq!(Some(48), ImageFormat::Fatbin.binary_len(&fatbin));
+        assert_eq!(None, ImageFormat::Fatbin.binary_len(&fatbin[..12]));
+    }
+
+    #[test]
+    fn test_load_from_bytes() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx = include_bytes!("../../resources/add.ptx");
+
+        // Not NUL-terminated.
+        let module = Module::load_from_bytes(ptx)?;
+        let _ = module.get_function_by_name("sum")?;
+
+        let mut terminated = ptx.to_vec();
+        terminated.push(0);
+        let module = Module::load_from_bytes(&terminated)?;
+        let _ = module.get_function_by_name("sum")?;
+
+        assert_eq!(
+            CudaError::InvalidImage,
+            Module::load_from_bytes(b"\xff\xfe\x00\x01").unwrap_err()
+        );
+
+        let mut linker = Linker::new(&JitOptions::new())?;
+        linker.add_data(JitInputType::Ptx, ptx, "add.ptx")?;
+        let (cubin, _) = linker.complete()?;
+        let module = Module::load_from_bytes(&cubin)?;
+        let _ = module.get_function_by_name("sum")?;
+        assert_eq!(
+            CudaError::InvalidImage,
+            Module::load_from_bytes(&cubin[..cubin.len() - 1]).unwrap_err()
+        );
+        assert_eq!(
+            CudaError::InvalidImage,
+            Module::load_from_bytes(b"\x7fELF\x02\x01\x01").unwrap_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_from_path() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let module = Module::load_from_path(Path::new("./resources/add.ptx"))?;
+        let _ = module.get_function_by_name("sum")?;
+        assert_eq!(
+            CudaError::FileNotFound,
+            Module::load_from_path(Path::new("./resources/missing.ptx")).unwrap_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_by_name() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let module = Module::load_from_bytes(include_bytes!("../../resources/add.ptx"))?;
+        let symbol = module.get_global_by_name::<u32>("my_constant")?;
+        let mut constant_copy = 0u32;
+        symbol.copy_to(&mut constant_copy)?;
+        assert_eq!(314, constant_copy);
+
+        assert_eq!(
+            CudaError::InvalidValue,
+            module.get_function_by_name("s\0um").unwrap_err()
+        );
+        assert_eq!(
+            CudaError::NotFound,
+            module.get_function_by_name("missing").unwrap_err()
+        );
+        assert_eq!(
+            CudaError::InvalidValue,
+            module
+                .get_global_by_name::<u32>("my\0constant")
+                .unwrap_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_with_options() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../../resources/add.ptx"))?;
+        let options = JitOptions::new()
+            .optimization_level(2)
+            .max_registers(32)
+            .generate_line_info(true)
+            .log_verbose(true)
+            .cache_mode(JitCacheMode::CacheGlobal);
+        let (module, log) = Module::load_with_options(&ptx_text, &options)?;
+        assert!(log.error.is_empty());
+        let _ = module.get_function(&CString::new("sum")?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_with_options_reports_errors() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let broken = CString::new(".version 6.0\n.target sm_30\n.address_size 64\nbogus;\n")?;
+        let error = Module::load_with_options(&broken, &JitOptions::new()).unwrap_err();
+        assert_eq!(CudaError::InvalidPtx, error.error);
+        assert!(!error.log.error.is_empty());
+        assert!(error.to_string().contains(error.log.error.trim_end()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_from_module() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+
+        let ptx = CString::new(include_str!("../../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+
+        let constant_name = CString::new("my_constant")?;
+        let symbol = module.get_global::<u32>(&constant_name)?;
+
+        let mut constant_copy = 0u32;
+        symbol.copy_to(&mut constant_copy)?;
+        assert_eq!(314, constant_copy);
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_to_module() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+
+        let ptx = CString::new(include_str!("../../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+
+        let constant_name = CString::new("my_constant")?;
+        let mut symbol = module.get_global::<u32>(&constant_name)?;
+
+        symbol.copy_from(&100)?;
+
+        let mut constant_copy = 0u32;
+        symbol.copy_to(&mut constant_copy)?;
+        assert_eq!(100, constant_copy);
+        Ok(())
+    }
+}
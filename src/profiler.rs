@@ -0,0 +1,559 @@
+//! Named GPU time ranges measured with events.
+//!
+//! Measuring how long a piece of GPU work takes requires recording a pair of events around it on
+//! its stream, waiting for both to complete and then computing the time between them. A
+//! [`Profiler`](struct.Profiler.html) does this bookkeeping for any number of named ranges, which
+//! may be nested and may span several streams.
+//!
+//! Ranges are opened with `Profiler::range`, which returns a guard that closes the range when it
+//! is dropped. Recording a range does not block the host; the events are only waited on when the
+//! results are requested, for example by `Profiler::summary` or `Profiler::chrome_trace`.
+//!
+//! The results can be exported in the Chrome trace-event JSON format, which can be viewed in
+//! `chrome://tracing` or Perfetto, or as a plain-text summary of the time spent in each range.
+
+use crate::error::{CudaError, CudaResult};
+use crate::event::{Event, EventFlags};
+use crate::stream::Stream;
+use cuda_sys::cuda::CUstream;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// The timing of a single completed range, as returned by `Profiler::ranges`.
+///
+/// All times are in milliseconds. Start times are relative to the earliest range recorded by the
+/// profiler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeTiming {
+    /// The name the range was opened with.
+    pub name: String,
+    /// Index of the stream the range was recorded on, numbered in the order in which the profiler
+    /// first saw each stream.
+    pub stream: usize,
+    /// How many other ranges on the same stream were open when this range was opened.
+    pub depth: usize,
+    /// The start time of the range.
+    pub start_ms: f32,
+    /// The duration of the range.
+    pub duration_ms: f32,
+}
+
+/// Aggregate timings for all completed ranges with the same name, as returned by
+/// `Profiler::stats`.
+///
+/// All times are in milliseconds. Percentiles use the nearest-rank method.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeStats {
+    /// The name shared by the ranges.
+    pub name: String,
+    /// The number of ranges.
+    pub count: usize,
+    /// The sum of the durations of the ranges.
+    pub total_ms: f32,
+    /// The mean duration of the ranges.
+    pub mean_ms: f32,
+    /// The median duration of the ranges.
+    pub p50_ms: f32,
+    /// The 99th percentile duration of the ranges.
+    pub p99_ms: f32,
+}
+
+#[derive(Debug)]
+struct RangeRecord {
+    name: String,
+    stream: usize,
+    depth: usize,
+    start: Option<Event>,
+    end: Option<Event>,
+    // Start time and duration, once both events have completed.
+    timing: Option<(f32, f32)>,
+}
+
+/// Records named GPU time ranges on streams.
+///
+/// See the [module-level documentation](index.html) for more information.
+///
+/// # Examples:
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::memory::*;
+/// use rustacuda::profiler::Profiler;
+/// use rustacuda::stream::{Stream, StreamFlags};
+///
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+/// let profiler = Profiler::new();
+/// let host = LockedBuffer::new(&0u32, 1 << 20)?;
+/// let mut device = DeviceBuffer::from_slice(&host)?;
+///
+/// for _ in 0..10 {
+///     let _step = profiler.range("step", &stream)?;
+///     {
+///         let _upload = profiler.range("upload", &stream)?;
+///         unsafe { device.async_copy_from(&host, &stream)? };
+///     }
+///     // launch kernels ...
+/// }
+///
+/// println!("{}", profiler.summary()?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Profiler {
+    // Recorded before the first range; all start times are measured from it.
+    origin: RefCell<Option<Event>>,
+    ranges: RefCell<Vec<RangeRecord>>,
+    // Known streams, in order of first use, with the number of ranges open on each.
+    streams: RefCell<Vec<(CUstream, usize)>>,
+    // The first error hit while closing a range, reported by the next call which resolves ranges.
+    error: Cell<Option<CudaError>>,
+}
+impl Profiler {
+    /// Create a new profiler with no recorded ranges.
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Open a named range on `stream`.
+    ///
+    /// The range starts once all work previously queued on `stream` has completed and ends when
+    /// the work queued before the returned guard is dropped has completed. Ranges may be nested,
+    /// and ranges with the same name are aggregated in `stats` and `summary`.
+    ///
+    /// # Errors:
+    ///
+    /// If creating or recording the start event fails, returns the error.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::profiler::Profiler;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let profiler = Profiler::new();
+    /// {
+    ///     let _outer = profiler.range("outer", &stream)?;
+    ///     let _inner = profiler.range("inner", &stream)?;
+    ///     // queue work on the stream ...
+    /// }
+    /// let ranges = profiler.ranges()?;
+    /// assert_eq!(2, ranges.len());
+    /// assert_eq!(1, ranges[1].depth);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn range<'a>(&'a self, name: &str, stream: &'a Stream) -> CudaResult<ProfilerRange<'a>> {
+        {
+            let mut origin = self.origin.borrow_mut();
+            if origin.is_none() {
+                let event = Event::new(EventFlags::DEFAULT)?;
+                event.record(stream)?;
+                *origin = Some(event);
+            }
+        }
+
+        let start = Event::new(EventFlags::DEFAULT)?;
+        start.record(stream)?;
+
+        let mut streams = self.streams.borrow_mut();
+        let stream_index = match streams.iter().position(|s| s.0 == stream.as_inner()) {
+            Some(index) => index,
+            None => {
+                streams.push((stream.as_inner(), 0));
+                streams.len() - 1
+            }
+        };
+        let depth = streams[stream_index].1;
+        streams[stream_index].1 += 1;
+
+        let mut ranges = self.ranges.borrow_mut();
+        ranges.push(RangeRecord {
+            name: name.to_owned(),
+            stream: stream_index,
+            depth,
+            start: Some(start),
+            end: None,
+            timing: None,
+        });
+        Ok(ProfilerRange {
+            profiler: self,
+            stream,
+            index: ranges.len() - 1,
+        })
+    }
+
+    fn close(&self, index: usize, stream: &Stream) -> CudaResult<()> {
+        let mut ranges = self.ranges.borrow_mut();
+        let record = &mut ranges[index];
+        self.streams.borrow_mut()[record.stream].1 -= 1;
+
+        let end = Event::new(EventFlags::DEFAULT)?;
+        end.record(stream)?;
+        record.end = Some(end);
+        Ok(())
+    }
+
+    // Waits for all closed ranges to complete and converts their events into times.
+    fn resolve(&self) -> CudaResult<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        let origin = self.origin.borrow();
+        let origin = match *origin {
+            Some(ref origin) => origin,
+            None => return Ok(()),
+        };
+        // Ranges may be recorded on streams which do not wait for each other, so every event
+        // involved in a timing must be waited for on its own.
+        origin.synchronize()?;
+        for record in self.ranges.borrow_mut().iter_mut() {
+            if let (Some(start), Some(end)) = (&record.start, &record.end) {
+                start.synchronize()?;
+                end.synchronize()?;
+                let start_ms = start.elapsed_time_f32(origin)?;
+                let duration_ms = end.elapsed_time_f32(start)?;
+                record.timing = Some((start_ms, duration_ms));
+                record.start = None;
+                record.end = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the timings of all closed ranges, in the order they were opened.
+    ///
+    /// This blocks until the work covered by every closed range has completed. Ranges which are
+    /// still open are not included.
+    ///
+    /// # Errors:
+    ///
+    /// If closing a range failed, or waiting for or timing a range fails, returns the error.
+    pub fn ranges(&self) -> CudaResult<Vec<RangeTiming>> {
+        self.resolve()?;
+        let ranges = self.ranges.borrow();
+        let mut timings: Vec<RangeTiming> = ranges
+            .iter()
+            .filter_map(|record| {
+                record.timing.map(|(start_ms, duration_ms)| RangeTiming {
+                    name: record.name.clone(),
+                    stream: record.stream,
+                    depth: record.depth,
+                    start_ms,
+                    duration_ms,
+                })
+            })
+            .collect();
+
+        // Work on other streams may have started before the origin event.
+        let earliest = timings.iter().map(|t| t.start_ms).fold(0.0f32, f32::min);
+        for timing in &mut timings {
+            timing.start_ms -= earliest;
+        }
+        Ok(timings)
+    }
+
+    /// Returns aggregate timings for each range name, ordered by decreasing total time.
+    ///
+    /// This blocks until the work covered by every closed range has completed.
+    ///
+    /// # Errors:
+    ///
+    /// If closing a range failed, or waiting for or timing a range fails, returns the error.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::profiler::Profiler;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let profiler = Profiler::new();
+    /// for _ in 0..3 {
+    ///     let _range = profiler.range("fft", &stream)?;
+    /// }
+    /// let stats = profiler.stats()?;
+    /// assert_eq!("fft", stats[0].name);
+    /// assert_eq!(3, stats[0].count);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> CudaResult<Vec<RangeStats>> {
+        let mut by_name: HashMap<String, Vec<f32>> = HashMap::new();
+        let mut names = Vec::new();
+        for timing in self.ranges()? {
+            let durations = by_name.entry(timing.name.clone()).or_insert_with(|| {
+                names.push(timing.name.clone());
+                Vec::new()
+            });
+            durations.push(timing.duration_ms);
+        }
+
+        let mut stats: Vec<RangeStats> = names
+            .into_iter()
+            .map(|name| {
+                let mut durations = by_name.remove(&name).unwrap();
+                durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let count = durations.len();
+                let total_ms: f32 = durations.iter().sum();
+                RangeStats {
+                    name,
+                    count,
+                    total_ms,
+                    mean_ms: total_ms / count as f32,
+                    p50_ms: percentile(&durations, 50),
+                    p99_ms: percentile(&durations, 99),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| b.total_ms.partial_cmp(&a.total_ms).unwrap());
+        Ok(stats)
+    }
+
+    /// Returns a plain-text table of the aggregate timings for each range name.
+    ///
+    /// See `stats` for details.
+    ///
+    /// # Errors:
+    ///
+    /// If closing a range failed, or waiting for or timing a range fails, returns the error.
+    pub fn summary(&self) -> CudaResult<String> {
+        let stats = self.stats()?;
+        let width = stats
+            .iter()
+            .map(|s| s.name.len())
+            .chain(Some("name".len()))
+            .max()
+            .unwrap();
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "{:<width$} {:>8} {:>12} {:>12} {:>12} {:>12}",
+            "name",
+            "count",
+            "total (ms)",
+            "mean (ms)",
+            "p50 (ms)",
+            "p99 (ms)",
+            width = width
+        )
+        .unwrap();
+        for s in &stats {
+            writeln!(
+                out,
+                "{:<width$} {:>8} {:>12.3} {:>12.3} {:>12.3} {:>12.3}",
+                s.name,
+                s.count,
+                s.total_ms,
+                s.mean_ms,
+                s.p50_ms,
+                s.p99_ms,
+                width = width
+            )
+            .unwrap();
+        }
+        Ok(out)
+    }
+
+    /// Returns the closed ranges in the Chrome trace-event JSON format.
+    ///
+    /// Each range becomes a complete (`"ph": "X"`) event. Each stream is shown as a separate
+    /// thread, numbered as in `RangeTiming::stream`.
+    ///
+    /// # Errors:
+    ///
+    /// If closing a range failed, or waiting for or timing a range fails, returns the error.
+    pub fn chrome_trace(&self) -> CudaResult<String> {
+        let mut out = String::from("{\"traceEvents\":[");
+        for (i, timing) in self.ranges()?.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("\n{\"name\":");
+            write_json_string(&mut out, &timing.name);
+            write!(
+                out,
+                ",\"cat\":\"gpu\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":0,\"tid\":{}}}",
+                timing.start_ms * 1000.0,
+                timing.duration_ms * 1000.0,
+                timing.stream
+            )
+            .unwrap();
+        }
+        out.push_str("\n],\"displayTimeUnit\":\"ms\"}\n");
+        Ok(out)
+    }
+
+    /// Write the closed ranges in the Chrome trace-event JSON format to the file at `path`.
+    ///
+    /// See `chrome_trace` for details.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `OperatingSystemError` if the file cannot be written, or any error returned by
+    /// `chrome_trace`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::profiler::Profiler;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let profiler = Profiler::new();
+    /// drop(profiler.range("fft", &stream)?);
+    ///
+    /// let path = std::env::temp_dir().join("rustacuda_trace.json");
+    /// profiler.write_chrome_trace(&path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_chrome_trace<P: AsRef<Path>>(&self, path: P) -> CudaResult<()> {
+        let trace = self.chrome_trace()?;
+        fs::write(path, trace).map_err(|_| CudaError::OperatingSystemError)
+    }
+
+    /// Discard all recorded ranges.
+    ///
+    /// Taking `&mut self` ensures that no ranges are open.
+    pub fn clear(&mut self) {
+        *self = Profiler::new();
+    }
+}
+
+/// A guard which closes a profiler range when dropped.
+///
+/// Created by [`Profiler::range`](struct.Profiler.html#method.range).
+#[derive(Debug)]
+pub struct ProfilerRange<'a> {
+    profiler: &'a Profiler,
+    stream: &'a Stream,
+    index: usize,
+}
+impl<'a> Drop for ProfilerRange<'a> {
+    fn drop(&mut self) {
+        if let Err(e) = self.profiler.close(self.index, self.stream) {
+            let first = self.profiler.error.take().unwrap_or(e);
+            self.profiler.error.set(Some(first));
+        }
+    }
+}
+
+// Nearest-rank percentile of a sorted, non-empty slice.
+fn percentile(sorted: &[f32], percent: usize) -> f32 {
+    let rank = (sorted.len() as f32 * percent as f32 / 100.0).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::*;
+    use crate::quick_init;
+    use crate::stream::StreamFlags;
+    use std::error::Error;
+
+    #[test]
+    fn test_nested_ranges() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let profiler = Profiler::new();
+        let host = LockedBuffer::new(&0u64, 1 << 20)?;
+        let mut device = DeviceBuffer::from_slice(&host)?;
+
+        {
+            let _outer = profiler.range("outer", &stream)?;
+            for _ in 0..2 {
+                let _inner = profiler.range("inner", &stream)?;
+                unsafe { device.async_copy_from(&host, &stream)? };
+            }
+            // Not closed until after this point, so not yet reported.
+            assert_eq!(2, profiler.ranges()?.len());
+        }
+
+        let ranges = profiler.ranges()?;
+        assert_eq!(3, ranges.len());
+        assert_eq!(("outer", 0), (&*ranges[0].name, ranges[0].depth));
+        assert_eq!(("inner", 1), (&*ranges[1].name, ranges[1].depth));
+        let inner_total = ranges[1].duration_ms + ranges[2].duration_ms;
+        assert!(ranges[0].duration_ms >= inner_total);
+        assert!(ranges[1].start_ms >= ranges[0].start_ms);
+
+        let stats = profiler.stats()?;
+        assert_eq!(2, stats.len());
+        assert_eq!(("outer", 1), (&*stats[0].name, stats[0].count));
+        assert_eq!(("inner", 2), (&*stats[1].name, stats[1].count));
+        assert!(stats[1].p50_ms <= stats[1].p99_ms);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chrome_trace_and_summary() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let first = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let second = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let mut profiler = Profiler::new();
+
+        drop(profiler.range("a \"quoted\" name", &first)?);
+        drop(profiler.range("b", &second)?);
+
+        let trace = profiler.chrome_trace()?;
+        assert!(trace.starts_with("{\"traceEvents\":["));
+        assert!(trace.contains("\"name\":\"a \\\"quoted\\\" name\""));
+        assert!(trace.contains("\"tid\":1"));
+
+        let summary = profiler.summary()?;
+        assert_eq!(3, summary.lines().count());
+        assert!(summary.lines().any(|line| line.starts_with("b ")));
+
+        profiler.clear();
+        assert!(profiler.ranges()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<f32> = (1..=100).map(|x| x as f32).collect();
+        assert_eq!(50.0, percentile(&values, 50));
+        assert_eq!(99.0, percentile(&values, 99));
+        assert_eq!(3.0, percentile(&[3.0], 99));
+        assert_eq!(1.0, percentile(&[1.0, 2.0], 50));
+    }
+}
@@ -0,0 +1,1066 @@
+//! Host-side inspection of PTX source.
+//!
+//! [`PtxModule::parse`](struct.PtxModule.html#method.parse) reads the module-level structure of
+//! PTX text: the `.version`, `.target` and `.address_size` directives, every `.entry` with its
+//! parameters and every `.global` and `.const` variable, along with their sizes and alignments.
+//! Function bodies are only checked for balanced braces, not parsed.
+//!
+//! Nothing in this module calls the CUDA driver, so it can be used to check kernels before they
+//! are loaded, or in tests on machines without a GPU.
+//!
+//! # Examples:
+//!
+//! ```
+//! use rustacuda::ptx::{PtxModule, PtxType};
+//!
+//! let ptx = PtxModule::parse(include_str!("../resources/add.ptx")).unwrap();
+//! assert_eq!((3, 2), ptx.version);
+//! assert_eq!("sm_20", ptx.target);
+//! assert_eq!(64, ptx.address_size);
+//!
+//! let sum = ptx.entry("sum").unwrap();
+//! assert_eq!(4, sum.params.len());
+//! assert_eq!(PtxType::U32, sum.params[3].ty);
+//! assert_eq!(4, sum.params[3].size);
+//!
+//! let constant = ptx.global("my_constant").unwrap();
+//! assert_eq!(4, constant.size);
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The module-level contents of a PTX file, as returned by `PtxModule::parse`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PtxModule {
+    /// The PTX ISA version, as `(major, minor)`.
+    pub version: (u32, u32),
+    /// The target architecture, such as `sm_20`.
+    pub target: String,
+    /// Any further `.target` options, such as `texmode_independent` or `debug`.
+    pub target_options: Vec<String>,
+    /// The size of addresses in bits. PTX without an `.address_size` directive uses 32.
+    pub address_size: u32,
+    /// The kernels declared or defined in the module, in order.
+    pub entries: Vec<PtxEntry>,
+    /// The `.global` and `.const` variables declared in the module, in order.
+    pub globals: Vec<PtxVariable>,
+}
+impl PtxModule {
+    /// Parse PTX source text.
+    ///
+    /// # Errors:
+    ///
+    /// Returns a `PtxParseError` with the line number of the first syntax error found, such as an
+    /// unknown type, a missing semicolon, an unclosed brace or a missing `.version` or `.target`
+    /// directive.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use rustacuda::ptx::PtxModule;
+    ///
+    /// let error = PtxModule::parse(".version 6.0\n.target sm_30\n.global .u33 x;\n").unwrap_err();
+    /// assert_eq!(3, error.line);
+    /// assert_eq!("line 3: expected a type, found `.u33`", error.to_string());
+    /// ```
+    pub fn parse(text: &str) -> Result<PtxModule, PtxParseError> {
+        Parser::new(text)?.module()
+    }
+
+    /// Returns the entry named `name`, if there is one.
+    pub fn entry(&self, name: &str) -> Option<&PtxEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Returns the `.global` or `.const` variable named `name`, if there is one.
+    pub fn global(&self, name: &str) -> Option<&PtxVariable> {
+        self.globals.iter().find(|global| global.name == name)
+    }
+}
+impl FromStr for PtxModule {
+    type Err = PtxParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        PtxModule::parse(text)
+    }
+}
+
+/// A kernel declared with `.entry`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PtxEntry {
+    /// The name of the kernel.
+    pub name: String,
+    /// The kernel's parameters, in order.
+    pub params: Vec<PtxVariable>,
+    /// True if the kernel is only declared with `.extern` and defined in another module.
+    pub external: bool,
+    /// The line the kernel is declared on.
+    pub line: usize,
+}
+
+/// A kernel parameter or a module-level variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PtxVariable {
+    /// The name of the variable.
+    pub name: String,
+    /// The state space the variable is declared in.
+    pub space: PtxStateSpace,
+    /// The type of each element.
+    pub ty: PtxType,
+    /// The number of elements in each vector for `.v2` and `.v4` types, or 1.
+    pub vector_len: usize,
+    /// The length of each array dimension, which is empty if the variable is not an array.
+    pub dims: Vec<usize>,
+    /// The size of the variable in bytes.
+    pub size: usize,
+    /// The alignment of the variable in bytes.
+    pub align: usize,
+    /// The line the variable is declared on.
+    pub line: usize,
+}
+
+/// The state spaces reported by `PtxModule::parse`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PtxStateSpace {
+    /// Kernel parameters, `.param`.
+    Param,
+    /// Global memory, `.global`.
+    Global,
+    /// Constant memory, `.const`.
+    Const,
+}
+
+/// The fundamental and opaque types a variable may have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum PtxType {
+    B8,
+    B16,
+    B32,
+    B64,
+    B128,
+    U8,
+    U16,
+    U32,
+    U64,
+    S8,
+    S16,
+    S32,
+    S64,
+    F16,
+    F16x2,
+    Bf16,
+    Bf16x2,
+    F32,
+    F64,
+    Texref,
+    Samplerref,
+    Surfref,
+}
+impl PtxType {
+    const ALL: [PtxType; 22] = [
+        PtxType::B8,
+        PtxType::B16,
+        PtxType::B32,
+        PtxType::B64,
+        PtxType::B128,
+        PtxType::U8,
+        PtxType::U16,
+        PtxType::U32,
+        PtxType::U64,
+        PtxType::S8,
+        PtxType::S16,
+        PtxType::S32,
+        PtxType::S64,
+        PtxType::F16,
+        PtxType::F16x2,
+        PtxType::Bf16,
+        PtxType::Bf16x2,
+        PtxType::F32,
+        PtxType::F64,
+        PtxType::Texref,
+        PtxType::Samplerref,
+        PtxType::Surfref,
+    ];
+
+    /// Returns the size of the type in bytes. Opaque types are handles of 8 bytes.
+    pub fn size(self) -> usize {
+        match self {
+            PtxType::B8 | PtxType::U8 | PtxType::S8 => 1,
+            PtxType::B16 | PtxType::U16 | PtxType::S16 | PtxType::F16 | PtxType::Bf16 => 2,
+            PtxType::B32
+            | PtxType::U32
+            | PtxType::S32
+            | PtxType::F32
+            | PtxType::F16x2
+            | PtxType::Bf16x2 => 4,
+            PtxType::B64
+            | PtxType::U64
+            | PtxType::S64
+            | PtxType::F64
+            | PtxType::Texref
+            | PtxType::Samplerref
+            | PtxType::Surfref => 8,
+            PtxType::B128 => 16,
+        }
+    }
+
+    /// Returns the type as it is written in PTX, such as `.u32`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PtxType::B8 => ".b8",
+            PtxType::B16 => ".b16",
+            PtxType::B32 => ".b32",
+            PtxType::B64 => ".b64",
+            PtxType::B128 => ".b128",
+            PtxType::U8 => ".u8",
+            PtxType::U16 => ".u16",
+            PtxType::U32 => ".u32",
+            PtxType::U64 => ".u64",
+            PtxType::S8 => ".s8",
+            PtxType::S16 => ".s16",
+            PtxType::S32 => ".s32",
+            PtxType::S64 => ".s64",
+            PtxType::F16 => ".f16",
+            PtxType::F16x2 => ".f16x2",
+            PtxType::Bf16 => ".bf16",
+            PtxType::Bf16x2 => ".bf16x2",
+            PtxType::F32 => ".f32",
+            PtxType::F64 => ".f64",
+            PtxType::Texref => ".texref",
+            PtxType::Samplerref => ".samplerref",
+            PtxType::Surfref => ".surfref",
+        }
+    }
+
+    fn from_directive(text: &str) -> Option<PtxType> {
+        PtxType::ALL.iter().cloned().find(|ty| ty.as_str() == text)
+    }
+}
+impl fmt::Display for PtxType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error returned when PTX text cannot be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PtxParseError {
+    /// The line the error was found on, starting from 1.
+    pub line: usize,
+    /// A description of the error.
+    pub message: String,
+}
+impl fmt::Display for PtxParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+impl Error for PtxParseError {}
+
+#[derive(Clone, Copy, Debug)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+// Split PTX text into directives (`.entry`), identifiers (`%r1`), numbers (`3.2`, `0x1f`),
+// strings and single punctuation characters, dropping whitespace and comments.
+fn tokenize(text: &str) -> Result<Vec<Token<'_>>, PtxParseError> {
+    fn is_word(c: u8) -> bool {
+        c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+    }
+
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        match c {
+            b'\n' => {
+                line += 1;
+                i += 1;
+                continue;
+            }
+            _ if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let start_line = line;
+                i += 2;
+                loop {
+                    match bytes.get(i) {
+                        None => return Err(error(start_line, "unterminated comment")),
+                        Some(b'*') if bytes.get(i + 1) == Some(&b'/') => break,
+                        Some(b'\n') => line += 1,
+                        Some(_) => {}
+                    }
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            b'"' => {
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        None | Some(b'\n') => return Err(error(line, "unterminated string")),
+                        Some(b'"') => break,
+                        Some(b'\\') => i += 2,
+                        Some(_) => i += 1,
+                    }
+                }
+                i += 1;
+            }
+            b'.' if matches!(bytes.get(i + 1), Some(c) if c.is_ascii_alphabetic() || *c == b'_') => {
+                i += 1;
+                while i < bytes.len() && is_word(bytes[i]) {
+                    i += 1;
+                }
+            }
+            b'0'..=b'9' => {
+                while i < bytes.len() && (is_word(bytes[i]) || bytes[i] == b'.') {
+                    i += 1;
+                }
+            }
+            _ if c.is_ascii_alphabetic() || c == b'_' || c == b'$' || c == b'%' => {
+                i += 1;
+                while i < bytes.len() && is_word(bytes[i]) {
+                    i += 1;
+                }
+            }
+            _ if c.is_ascii_punctuation() => i += 1,
+            _ => {
+                let found = text[i..].chars().next().unwrap_or_default();
+                return Err(error(line, format!("unexpected character `{}`", found)));
+            }
+        }
+        tokens.push(Token {
+            text: &text[start..i],
+            line,
+        });
+    }
+    Ok(tokens)
+}
+
+fn error(line: usize, message: impl Into<String>) -> PtxParseError {
+    PtxParseError {
+        line,
+        message: message.into(),
+    }
+}
+
+// Parse a decimal, hexadecimal or octal integer literal, with an optional `U` suffix.
+fn parse_int(text: &str) -> Option<u64> {
+    let text = text.strip_suffix('U').unwrap_or(text);
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        u64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    // The line reported for errors at the end of the input.
+    last_line: usize,
+}
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Result<Self, PtxParseError> {
+        Ok(Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+            last_line: text.lines().count().max(1),
+        })
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|token| token.text)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, PtxParseError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| error(self.last_line, "unexpected end of input"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        if self.peek() == Some(text) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> PtxParseError {
+        match self.tokens.get(self.pos) {
+            Some(token) => error(
+                token.line,
+                format!("expected {}, found `{}`", expected, token.text),
+            ),
+            None => error(
+                self.last_line,
+                format!("expected {}, found end of input", expected),
+            ),
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), PtxParseError> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", text)))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<Token<'a>, PtxParseError> {
+        match self.peek() {
+            Some(text)
+                if text.starts_with(|c: char| c.is_ascii_alphabetic() || "_$%".contains(c)) =>
+            {
+                self.next()
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    fn integer(&mut self) -> Result<usize, PtxParseError> {
+        match self.peek().and_then(parse_int) {
+            Some(value) => {
+                self.pos += 1;
+                Ok(value as usize)
+            }
+            None => Err(self.unexpected("an integer")),
+        }
+    }
+
+    // Parse a comma-separated list of integers, such as the dimensions given to `.maxntid`.
+    fn integer_list(&mut self) -> Result<(), PtxParseError> {
+        loop {
+            let _ = self.integer()?;
+            if !self.eat(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn alignment(&mut self) -> Result<usize, PtxParseError> {
+        let line = self.tokens.get(self.pos).map_or(self.last_line, |t| t.line);
+        let align = self.integer()?;
+        if align.is_power_of_two() {
+            Ok(align)
+        } else {
+            Err(error(
+                line,
+                format!("alignment {} is not a power of two", align),
+            ))
+        }
+    }
+
+    // Skip a block whose opening brace has already been consumed, including nested blocks.
+    fn skip_block(&mut self, open: Token<'a>) -> Result<(), PtxParseError> {
+        let mut depth = 1;
+        while depth > 0 {
+            let token = self.next().map_err(|_| error(open.line, "unclosed `{`"))?;
+            match token.text {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Skip to the end of a statement, consuming the terminating semicolon.
+    fn skip_statement(&mut self) -> Result<(), PtxParseError> {
+        loop {
+            let token = self.next()?;
+            match token.text {
+                ";" => return Ok(()),
+                "{" => self.skip_block(token)?,
+                _ => {}
+            }
+        }
+    }
+
+    fn module(&mut self) -> Result<PtxModule, PtxParseError> {
+        self.expect(".version")?;
+        let version = self.version()?;
+        self.expect(".target")?;
+        let target = self.identifier()?.text.to_owned();
+        let mut target_options = Vec::new();
+        while self.eat(",") {
+            target_options.push(self.identifier()?.text.to_owned());
+        }
+        let mut address_size = 32;
+        if self.eat(".address_size") {
+            let line = self.tokens[self.pos - 1].line;
+            address_size = self.integer()? as u32;
+            if address_size != 32 && address_size != 64 {
+                return Err(error(
+                    line,
+                    format!("invalid address size {}", address_size),
+                ));
+            }
+        }
+
+        let mut module = PtxModule {
+            version,
+            target,
+            target_options,
+            address_size,
+            entries: Vec::new(),
+            globals: Vec::new(),
+        };
+        while self.pos < self.tokens.len() {
+            self.declaration(&mut module)?;
+        }
+        Ok(module)
+    }
+
+    fn version(&mut self) -> Result<(u32, u32), PtxParseError> {
+        let token = self.next()?;
+        let mut parts = token.text.splitn(2, '.');
+        match (
+            parts.next().and_then(|major| major.parse().ok()),
+            parts.next().and_then(|minor| minor.parse().ok()),
+        ) {
+            (Some(major), Some(minor)) => Ok((major, minor)),
+            _ => Err(error(
+                token.line,
+                format!("invalid PTX version `{}`", token.text),
+            )),
+        }
+    }
+
+    fn declaration(&mut self, module: &mut PtxModule) -> Result<(), PtxParseError> {
+        let mut external = false;
+        loop {
+            match self.peek() {
+                Some(".extern") => external = true,
+                Some(".visible") | Some(".weak") | Some(".common") => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        match self.peek() {
+            Some(".entry") => {
+                let entry = self.entry(external)?;
+                module.entries.push(entry);
+            }
+            Some(".func") => self.skip_function()?,
+            Some(".global") | Some(".const") | Some(".shared") | Some(".local") => {
+                self.variables(external, &mut module.globals)?;
+            }
+            Some(".file") => {
+                self.pos += 1;
+                let _ = self.integer()?;
+                let token = self.next()?;
+                if !token.text.starts_with('"') {
+                    return Err(error(
+                        token.line,
+                        format!("expected a file name, found `{}`", token.text),
+                    ));
+                }
+                if self.eat(",") {
+                    self.integer_list()?;
+                }
+            }
+            Some(".section") => {
+                self.pos += 1;
+                let _ = self.next()?;
+                if self.peek() != Some("{") {
+                    return Err(self.unexpected("`{`"));
+                }
+                let open = self.next()?;
+                self.skip_block(open)?;
+            }
+            Some(".pragma") | Some(".alias") => {
+                self.pos += 1;
+                self.skip_statement()?;
+            }
+            _ => return Err(self.unexpected("a declaration")),
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, external: bool) -> Result<PtxEntry, PtxParseError> {
+        self.expect(".entry")?;
+        let name = self.identifier()?;
+        let mut params = Vec::new();
+        if self.eat("(") && !self.eat(")") {
+            loop {
+                params.push(self.param()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        // Performance tuning directives such as `.maxntid 256, 1, 1` may precede the body.
+        loop {
+            match self.peek() {
+                Some("{") => {
+                    let open = self.next()?;
+                    self.skip_block(open)?;
+                    break;
+                }
+                Some(";") => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(text) if text.starts_with('.') && PtxType::from_directive(text).is_none() => {
+                    self.pos += 1;
+                    // Some directives, such as `.explicitcluster`, take no operands.
+                    if self.peek().and_then(parse_int).is_some() {
+                        self.integer_list()?;
+                    }
+                }
+                _ => return Err(self.unexpected("`{` or `;`")),
+            }
+        }
+
+        Ok(PtxEntry {
+            name: name.text.to_owned(),
+            params,
+            external,
+            line: name.line,
+        })
+    }
+
+    fn param(&mut self) -> Result<PtxVariable, PtxParseError> {
+        self.expect(".param")?;
+        let mut align = None;
+        if self.eat(".align") {
+            align = Some(self.alignment()?);
+        }
+        let (ty, vector_len) = self.ty()?;
+        // Pointer attributes describe what the parameter points to, not the parameter itself.
+        if self.eat(".ptr") {
+            match self.peek() {
+                Some(".global") | Some(".const") | Some(".shared") | Some(".local") => {
+                    self.pos += 1
+                }
+                _ => {}
+            }
+            if self.eat(".align") {
+                let _ = self.alignment()?;
+            }
+        }
+        let name = self.identifier()?;
+        let mut dims = Vec::new();
+        while self.eat("[") {
+            dims.push(self.integer()?);
+            self.expect("]")?;
+        }
+        variable(name, PtxStateSpace::Param, ty, vector_len, dims, align)
+    }
+
+    fn ty(&mut self) -> Result<(PtxType, usize), PtxParseError> {
+        let vector_len = if self.eat(".v2") {
+            2
+        } else if self.eat(".v4") {
+            4
+        } else {
+            1
+        };
+        match self.peek().and_then(PtxType::from_directive) {
+            Some(ty) => {
+                self.pos += 1;
+                Ok((ty, vector_len))
+            }
+            None => Err(self.unexpected("a type")),
+        }
+    }
+
+    // Parse a variable declaration in any state space, keeping only `.global` and `.const`
+    // variables.
+    fn variables(
+        &mut self,
+        external: bool,
+        globals: &mut Vec<PtxVariable>,
+    ) -> Result<(), PtxParseError> {
+        let space = match self.next()?.text {
+            ".global" => Some(PtxStateSpace::Global),
+            ".const" => Some(PtxStateSpace::Const),
+            _ => None,
+        };
+        let mut align = None;
+        loop {
+            if self.eat(".align") {
+                align = Some(self.alignment()?);
+            } else if self.eat(".attribute") {
+                self.attribute_list()?;
+            } else {
+                break;
+            }
+        }
+        let (ty, vector_len) = self.ty()?;
+
+        loop {
+            let name = self.identifier()?;
+            let mut dims = Vec::new();
+            let mut unsized_dim = false;
+            while self.eat("[") {
+                if self.eat("]") {
+                    if !dims.is_empty() {
+                        return Err(error(
+                            name.line,
+                            "only the first array dimension may be left unsized",
+                        ));
+                    }
+                    unsized_dim = true;
+                    dims.push(0);
+                } else {
+                    dims.push(self.integer()?);
+                    self.expect("]")?;
+                }
+            }
+
+            if self.eat("=") {
+                let len = self.initializer()?;
+                if unsized_dim {
+                    dims[0] = len.ok_or_else(|| {
+                        error(
+                            name.line,
+                            format!("unsized array `{}` needs a braced initializer", name.text),
+                        )
+                    })?;
+                }
+            } else if unsized_dim && !external {
+                return Err(error(
+                    name.line,
+                    format!("unsized array `{}` needs an initializer", name.text),
+                ));
+            }
+
+            if let Some(space) = space {
+                globals.push(variable(name, space, ty, vector_len, dims, align)?);
+            }
+            if self.eat(";") {
+                return Ok(());
+            }
+            if !self.eat(",") {
+                return Err(self.unexpected("`;`"));
+            }
+        }
+    }
+
+    // Skip the parenthesized list following `.attribute`, such as `(.managed)`.
+    fn attribute_list(&mut self) -> Result<(), PtxParseError> {
+        if self.peek() != Some("(") {
+            return Err(self.unexpected("`(`"));
+        }
+        let open = self.next()?;
+        let mut depth = 1;
+        while depth > 0 {
+            let token = self.next().map_err(|_| error(open.line, "unclosed `(`"))?;
+            match token.text {
+                "(" => depth += 1,
+                ")" => depth -= 1,
+                ";" => return Err(error(token.line, "expected `)`, found `;`")),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Skip an initializer, returning the number of top-level elements if it is braced.
+    fn initializer(&mut self) -> Result<Option<usize>, PtxParseError> {
+        if self.peek() != Some("{") {
+            while !matches!(self.peek(), Some(";") | Some(",") | None) {
+                self.pos += 1;
+            }
+            return Ok(None);
+        }
+
+        let open = self.next()?;
+        let mut depth = 1;
+        let mut len = 1;
+        while depth > 0 {
+            let token = self.next().map_err(|_| error(open.line, "unclosed `{`"))?;
+            match token.text {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                "," if depth == 1 => len += 1,
+                ";" => return Err(error(token.line, "expected `}`, found `;`")),
+                _ => {}
+            }
+        }
+        Ok(Some(len))
+    }
+
+    fn skip_function(&mut self) -> Result<(), PtxParseError> {
+        self.expect(".func")?;
+        loop {
+            let token = self.next()?;
+            match token.text {
+                ";" => return Ok(()),
+                "{" => return self.skip_block(token),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn variable(
+    name: Token<'_>,
+    space: PtxStateSpace,
+    ty: PtxType,
+    vector_len: usize,
+    dims: Vec<usize>,
+    align: Option<usize>,
+) -> Result<PtxVariable, PtxParseError> {
+    let element_size = ty.size() * vector_len;
+    let size = dims
+        .iter()
+        .try_fold(element_size, |size, &dim| size.checked_mul(dim))
+        .ok_or_else(|| error(name.line, format!("`{}` is too large", name.text)))?;
+    Ok(PtxVariable {
+        name: name.text.to_owned(),
+        space,
+        ty,
+        vector_len,
+        size,
+        dims,
+        align: align.unwrap_or(element_size),
+        line: name.line,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_error(text: &str) -> PtxParseError {
+        PtxModule::parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_add_ptx() {
+        let ptx = PtxModule::parse(include_str!("../resources/add.ptx")).unwrap();
+        assert_eq!((3, 2), ptx.version);
+        assert_eq!("sm_20", ptx.target);
+        assert!(ptx.target_options.is_empty());
+        assert_eq!(64, ptx.address_size);
+
+        assert_eq!(1, ptx.entries.len());
+        let sum = &ptx.entries[0];
+        assert_eq!("sum", sum.name);
+        assert!(!sum.external);
+        assert_eq!(16, sum.line);
+        let params: Vec<_> = sum
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), p.ty, p.size, p.align))
+            .collect();
+        assert_eq!(
+            vec![
+                ("sum_param_0", PtxType::U64, 8, 8),
+                ("sum_param_1", PtxType::U64, 8, 8),
+                ("sum_param_2", PtxType::U64, 8, 8),
+                ("sum_param_3", PtxType::U32, 4, 4),
+            ],
+            params
+        );
+        assert!(sum.params.iter().all(|p| p.space == PtxStateSpace::Param));
+
+        assert_eq!(
+            vec![PtxVariable {
+                name: "my_constant".to_owned(),
+                space: PtxStateSpace::Const,
+                ty: PtxType::U32,
+                vector_len: 1,
+                dims: vec![],
+                size: 4,
+                align: 4,
+                line: 14,
+            }],
+            ptx.globals
+        );
+    }
+
+    #[test]
+    fn test_parse_declarations() {
+        let text = r#"
+            .version 7.5 /* a
+            multi-line comment */
+            .target sm_75, debug
+            .address_size 64
+            .file 1 "kernel.cu", 1600000000, 1024
+
+            .extern .shared .align 16 .b8 scratch[];
+            .global .align 8 .b8 table[] = {1, 2, 3, 4, 5};
+            .global .v4 .f32 colors[2][3];
+            .const .f64 a = 0d3FF0000000000000, b;
+            .extern .global .u32 counter;
+            .global .texref tex;
+            .global .attribute(.managed) .align 4 .u32 managed;
+
+            .func (.param .b32 ret) helper(.param .b32 x)
+            {
+                ret;
+            }
+
+            .extern .entry declared(.param .u32 n);
+
+            .visible .entry kernel(
+                .param .align 16 .b8 kernel_param_0[24],
+                .param .u64 .ptr .global .align 16 kernel_param_1,
+                .param .f16x2 kernel_param_2
+            )
+            .maxntid 256, 1, 1
+            .minnctapersm 2
+            .explicitcluster
+            .reqnctapercluster 2, 1, 1
+            {
+                .reg .b32 %r<2>;
+                { .reg .b32 inner; }
+                ret;
+            }
+
+            .section .debug_abbrev { .b8 17 }
+        "#;
+        let ptx = PtxModule::parse(text).unwrap();
+        assert_eq!((7, 5), ptx.version);
+        assert_eq!("sm_75", ptx.target);
+        assert_eq!(vec!["debug".to_owned()], ptx.target_options);
+
+        let globals: Vec<_> = ptx
+            .globals
+            .iter()
+            .map(|g| (g.name.as_str(), g.space, g.size, g.align))
+            .collect();
+        assert_eq!(
+            vec![
+                ("table", PtxStateSpace::Global, 5, 8),
+                ("colors", PtxStateSpace::Global, 96, 16),
+                ("a", PtxStateSpace::Const, 8, 8),
+                ("b", PtxStateSpace::Const, 8, 8),
+                ("counter", PtxStateSpace::Global, 4, 4),
+                ("tex", PtxStateSpace::Global, 8, 8),
+                ("managed", PtxStateSpace::Global, 4, 4),
+            ],
+            globals
+        );
+        assert_eq!(vec![2, 3], ptx.global("colors").unwrap().dims);
+        assert_eq!(4, ptx.global("colors").unwrap().vector_len);
+
+        assert_eq!(2, ptx.entries.len());
+        assert!(ptx.entry("declared").unwrap().external);
+        let kernel = ptx.entry("kernel").unwrap();
+        assert!(!kernel.external);
+        let params: Vec<_> = kernel
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), p.ty, p.size, p.align))
+            .collect();
+        assert_eq!(
+            vec![
+                ("kernel_param_0", PtxType::B8, 24, 16),
+                ("kernel_param_1", PtxType::U64, 8, 8),
+                ("kernel_param_2", PtxType::F16x2, 4, 4),
+            ],
+            params
+        );
+        assert!(ptx.entry("helper").is_none());
+
+        let error =
+            parse_error(".version 6.0\n.target sm_30\n.global .b8 x[4294967296][4294967296];\n");
+        assert_eq!("line 3: `x` is too large", error.to_string());
+    }
+
+    #[test]
+    fn test_address_size_defaults_to_32() {
+        let ptx: PtxModule = ".version 1.4\n.target sm_13\n".parse().unwrap();
+        assert_eq!(32, ptx.address_size);
+        assert!(ptx.entries.is_empty());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let header = ".version 6.0\n.target sm_30\n";
+        let cases = [
+            ("", "line 1: expected `.version`, found end of input"),
+            (
+                ".target sm_30\n",
+                "line 1: expected `.version`, found `.target`",
+            ),
+            (".version 6\n", "line 1: invalid PTX version `6`"),
+            (
+                ".version 6.0\n.target sm_30\n.address_size 48\n",
+                "line 3: invalid address size 48",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.global .u32 x\n.global .u32 y;\n",
+                "line 4: expected `;`, found `.global`",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.global .align 3 .u32 x;\n",
+                "line 3: alignment 3 is not a power of two",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.global .u32 x[];\n",
+                "line 3: unsized array `x` needs an initializer",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.global .attribute(.managed .u32 x;\n",
+                "line 3: expected `)`, found `;`",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.entry k(.param .u32 a .param .u32 b) {}\n",
+                "line 3: expected `,`, found `.param`",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.entry k()\n{\n  ret;\n",
+                "line 4: unclosed `{`",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.entry k() ret;\n",
+                "line 3: expected `{` or `;`, found `ret`",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n/* never closed\n",
+                "line 3: unterminated comment",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.pragma \"nounroll;\n",
+                "line 3: unterminated string",
+            ),
+            (
+                ".version 6.0\n.target sm_30\nmov.u32 %r1, 0;\n",
+                "line 3: expected a declaration, found `mov`",
+            ),
+            (
+                ".version 6.0\n.target sm_30\n.const .u32 x = \u{e9};\n",
+                "line 3: unexpected character `\u{e9}`",
+            ),
+        ];
+        for &(text, message) in &cases {
+            assert_eq!(message, parse_error(text).to_string(), "{:?}", text);
+        }
+        let error = parse_error(&format!("{}.global .u32 x = {{1, 2;\n", header));
+        assert_eq!(3, error.line);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::ffi;
+use crate::graph::Graph;
+use crate::stream::Stream;
+use cuda_sys::cuda::CUstream;
+use std::ptr;
+
+/// Controls which potentially unsafe API calls are prohibited while a stream is being captured.
+///
+/// Some CUDA calls, such as synchronous memory allocation, implicitly synchronize with work that
+/// is being captured and would not behave as expected. The capture mode decides which threads are
+/// prevented from making such calls during capture; prohibited calls fail with
+/// `CudaError::StreamCaptureUnsupported` and invalidate the capture.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CaptureMode {
+    /// Prohibit unsafe calls on any thread while this capture is active, unless the thread has
+    /// opted out. This is the safest option.
+    Global = 0,
+    /// Prohibit unsafe calls only on the thread which began the capture.
+    ThreadLocal = 1,
+    /// Do not prohibit any calls. The caller is responsible for avoiding calls which would
+    /// interfere with the capture.
+    Relaxed = 2,
+}
+
+/// Capture state of a stream, as returned by `Stream::capture_status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CaptureStatus {
+    /// The stream is not being captured.
+    NotCapturing,
+    /// The stream is being captured, and work submitted to it is recorded into a graph.
+    Active,
+    /// The stream is being captured, but the capture has been invalidated by an error. It must
+    /// still be ended with `end_capture`, which will return `StreamCaptureInvalidated`.
+    Invalidated,
+}
+
+impl Stream {
+    /// Begin capturing work submitted to this stream into a graph.
+    ///
+    /// While the stream is being captured, work submitted to it - kernel launches, asynchronous
+    /// copies, event records and so on - is recorded instead of executed. Call
+    /// [`end_capture`](#method.end_capture) to finish the capture and obtain the recorded
+    /// [`Graph`](../graph/struct.Graph.html), which can then be instantiated and launched
+    /// repeatedly.
+    ///
+    /// Memory referenced by captured work is recorded by address, so it must remain valid for as
+    /// long as the resulting graph may be launched.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `StreamCaptureUnsupported` if this stream cannot be captured, for example because
+    /// it is the legacy default stream.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{CaptureMode, Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let src = DeviceBuffer::from_slice(&[1u32, 2, 3])?;
+    /// let mut dst = DeviceBuffer::from_slice(&[0u32; 3])?;
+    ///
+    /// stream.begin_capture(CaptureMode::Global)?;
+    /// unsafe {
+    ///     dst.async_copy_from(&src, &stream)?;
+    /// }
+    /// let graph = stream.end_capture()?;
+    ///
+    /// let exec = graph.instantiate()?;
+    /// exec.launch(&stream)?;
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn begin_capture(&self, mode: CaptureMode) -> CudaResult<()> {
+        unsafe { ffi::cuStreamBeginCapture_v2(self.as_inner(), mode as u32).to_result() }
+    }
+
+    /// Finish capturing this stream and return the recorded graph.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `StreamCaptureInvalidated` if an error occurred during capture, or
+    /// `StreamCaptureUnjoined` if work forked onto other streams was not joined back into this
+    /// one. In both cases the capture is ended.
+    pub fn end_capture(&self) -> CudaResult<Graph> {
+        unsafe {
+            let mut graph = ptr::null_mut();
+            ffi::cuStreamEndCapture(self.as_inner(), &mut graph).to_result()?;
+            if graph.is_null() {
+                return Err(CudaError::StreamCaptureInvalidated);
+            }
+            Ok(Graph::from_raw(graph))
+        }
+    }
+
+    /// Returns whether this stream is currently being captured.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{CaptureMode, CaptureStatus, Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// assert_eq!(CaptureStatus::NotCapturing, stream.capture_status()?);
+    /// stream.begin_capture(CaptureMode::ThreadLocal)?;
+    /// assert_eq!(CaptureStatus::Active, stream.capture_status()?);
+    /// let _graph = stream.end_capture()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn capture_status(&self) -> CudaResult<CaptureStatus> {
+        raw_capture_status(self.as_inner())
+    }
+}
+
+fn raw_capture_status(stream: CUstream) -> CudaResult<CaptureStatus> {
+    unsafe {
+        let mut status = 0;
+        ffi::cuStreamIsCapturing(stream, &mut status).to_result()?;
+        match status {
+            0 => Ok(CaptureStatus::NotCapturing),
+            1 => Ok(CaptureStatus::Active),
+            2 => Ok(CaptureStatus::Invalidated),
+            _ => Err(CudaError::UnknownError),
+        }
+    }
+}
+
+// Host functions and callbacks free their data the first time they run, but a captured graph
+// runs its host nodes on every launch, so they must not be queued on a stream being captured.
+pub(crate) fn reject_capture(stream: CUstream) -> CudaResult<()> {
+    match raw_capture_status(stream)? {
+        CaptureStatus::NotCapturing => Ok(()),
+        _ => Err(CudaError::StreamCaptureUnsupported),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::*;
+    use crate::quick_init;
+    use crate::stream::StreamFlags;
+    use std::error::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_capture_and_replay() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let mut host = LockedBuffer::new(&0u64, 4)?;
+        let mut device = DeviceBuffer::from_slice(&[0u64; 4])?;
+
+        stream.begin_capture(CaptureMode::Global)?;
+        unsafe {
+            device.async_copy_from(&host, &stream)?;
+            device.async_copy_to(&mut host, &stream)?;
+        }
+        let graph = stream.end_capture()?;
+        assert_eq!(2, graph.node_count()?);
+        assert_eq!(CaptureStatus::NotCapturing, stream.capture_status()?);
+
+        let exec = graph.instantiate()?;
+        host.as_mut_slice().copy_from_slice(&[1, 2, 3, 4]);
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+
+        let mut check = [0u64; 4];
+        device.copy_to(&mut check[..])?;
+        assert_eq!([1, 2, 3, 4], check);
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_functions_are_not_captured() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let host = LockedBuffer::new(&1u64, 4)?;
+        let mut device = DeviceBuffer::from_slice(&[0u64; 4])?;
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        stream.begin_capture(CaptureMode::Global)?;
+        unsafe {
+            device.async_copy_from(&host, &stream)?;
+        }
+        let counter = calls.clone();
+        assert_eq!(
+            Err(CudaError::StreamCaptureUnsupported),
+            stream.launch_host_fn(move || {
+                let _ = counter.fetch_add(1, Ordering::SeqCst);
+            })
+        );
+        let counter = calls.clone();
+        assert_eq!(
+            Err(CudaError::StreamCaptureUnsupported),
+            stream.add_callback(Box::new(move |_| {
+                let _ = counter.fetch_add(1, Ordering::SeqCst);
+            }))
+        );
+        // The rejected closures were dropped, not leaked.
+        assert_eq!(1, Arc::strong_count(&calls));
+
+        let graph = stream.end_capture()?;
+        assert_eq!(1, graph.node_count()?);
+        let exec = graph.instantiate()?;
+        exec.launch(&stream)?;
+        exec.launch(&stream)?;
+        stream.synchronize()?;
+        assert_eq!(0, calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[test]
+    fn test_synchronize_invalidates_capture() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        stream.begin_capture(CaptureMode::Global)?;
+        assert_eq!(
+            Err(CudaError::StreamCaptureUnsupported),
+            stream.synchronize()
+        );
+        assert_eq!(CaptureStatus::Invalidated, stream.capture_status()?);
+        assert_eq!(
+            CudaError::StreamCaptureInvalidated,
+            stream.end_capture().unwrap_err()
+        );
+        Ok(())
+    }
+}
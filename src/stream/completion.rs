@@ -0,0 +1,209 @@
+use crate::error::{CudaResult, ToResult};
+use crate::ffi;
+use crate::stream::capture::reject_capture;
+use crate::stream::Stream;
+use cuda_sys::cuda::CUstream;
+use std::ffi::c_void;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Default)]
+struct CompletionState {
+    result: Option<CudaResult<()>>,
+    waker: Option<Waker>,
+}
+
+/// Future which resolves when all work queued on a stream before it was created has completed.
+///
+/// Created by [`Stream::completion`](struct.Stream.html#method.completion) or
+/// [`Event::wait_async`](../event/struct.Event.html#method.wait_async). The future is resolved from
+/// a host callback run by the CUDA driver, so it does not block a thread while waiting and does not
+/// depend on any particular executor. It resolves to the status reported to the callback, which
+/// will be an error if any earlier work in the stream failed.
+///
+/// The future does not borrow the stream, and will still resolve if the stream is dropped before
+/// the work completes.
+pub struct Completion {
+    state: Arc<Mutex<CompletionState>>,
+}
+impl Completion {
+    /// Register a completion callback on the given raw stream.
+    pub(crate) fn on_stream(stream: CUstream) -> Completion {
+        if let Err(e) = reject_capture(stream) {
+            return Completion::ready(Err(e));
+        }
+        let state = Arc::new(Mutex::new(CompletionState::default()));
+        let user_data = Arc::into_raw(state.clone()) as *mut c_void;
+        let registered = unsafe {
+            ffi::cuStreamAddCallback(stream, Some(completion_callback), user_data, 0).to_result()
+        };
+        if let Err(e) = registered {
+            // The callback will never run, so reclaim the reference given to the driver.
+            unsafe {
+                drop(Arc::from_raw(user_data as *const Mutex<CompletionState>));
+            }
+            return Completion::ready(Err(e));
+        }
+        Completion { state }
+    }
+
+    /// Create a completion which has already resolved to the given result.
+    pub(crate) fn ready(result: CudaResult<()>) -> Completion {
+        Completion {
+            state: Arc::new(Mutex::new(CompletionState {
+                result: Some(result),
+                waker: None,
+            })),
+        }
+    }
+}
+impl Future for Completion {
+    type Output = CudaResult<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<CudaResult<()>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.result {
+            Some(result) => Poll::Ready(result),
+            None => {
+                match state.waker {
+                    Some(ref waker) if waker.will_wake(cx.waker()) => {}
+                    _ => state.waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+impl fmt::Debug for Completion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("Completion")
+            .field("result", &state.result)
+            .finish()
+    }
+}
+
+unsafe extern "C" fn completion_callback(
+    _stream: CUstream,
+    status: ffi::CUresult,
+    user_data: *mut c_void,
+) {
+    let state = Arc::from_raw(user_data as *const Mutex<CompletionState>);
+    let waker = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.result = Some(status.to_result());
+        state.waker.take()
+    };
+    // Wake outside the lock in case the executor polls the future immediately. Executors are not
+    // expected to panic here, but a panic must not unwind across the FFI boundary.
+    if let Some(waker) = waker {
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| waker.wake()));
+    }
+}
+
+impl Stream {
+    /// Returns a future which resolves when all work currently queued on this stream has completed.
+    ///
+    /// Unlike `synchronize`, this does not block the calling thread, so it can be awaited from
+    /// async code without stalling the executor. Work queued on the stream after the future is
+    /// created is not waited for. The future resolves to any error reported by the driver for
+    /// earlier work, or to the error from registering the callback if that fails. In particular,
+    /// it resolves to `StreamCaptureUnsupported` if the stream is being captured.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// // ... queue up some work on the stream
+    ///
+    /// let completion = stream.completion();
+    /// // Await `completion` from an async function using any executor.
+    /// # drop(completion);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn completion(&self) -> Completion {
+        Completion::on_stream(self.as_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::{Event, EventFlags};
+    use crate::quick_init;
+    use crate::stream::StreamFlags;
+    use std::error::Error;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker {
+        thread: Thread,
+        woken: AtomicBool,
+    }
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.woken.store(true, Ordering::SeqCst);
+            self.thread.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Arc::new(ThreadWaker {
+            thread: thread::current(),
+            woken: AtomicBool::new(false),
+        });
+        let task_waker = Waker::from(waker.clone());
+        let mut cx = Context::from_waker(&task_waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            while !waker.woken.swap(false, Ordering::SeqCst) {
+                thread::park();
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_completion() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        block_on(stream.completion())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_completion_outlives_stream() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let completion = stream.completion();
+        drop(stream);
+        block_on(completion)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_wait_async() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let event = Event::new(EventFlags::DEFAULT)?;
+        event.record(&stream)?;
+        let waiter = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        block_on(event.wait_async(&waiter))?;
+        assert_eq!(crate::event::EventStatus::Ready, event.query()?);
+        Ok(())
+    }
+}
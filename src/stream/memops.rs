@@ -0,0 +1,453 @@
+use crate::context::CurrentContext;
+use crate::device::DeviceAttribute;
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::ffi::{
+    self, CUstreamBatchMemOpParams, CUstreamMemOpFlushRemoteWritesParams, CUstreamMemOpValue,
+    CUstreamMemOpValueParams,
+};
+use crate::memory::{DeviceCopy, DevicePointer, LockedBuffer};
+use crate::stream::Stream;
+use cuda_sys::cuda::{self, CUdeviceptr};
+use std::ffi::c_void;
+use std::fmt;
+use std::marker::PhantomData;
+
+const WAIT_VALUE_32: u32 = 1;
+const WRITE_VALUE_32: u32 = 2;
+const FLUSH_REMOTE_WRITES: u32 = 3;
+const WAIT_VALUE_64: u32 = 4;
+const WRITE_VALUE_64: u32 = 5;
+
+/// Condition for `Stream::wait_value` to stop waiting.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WaitCondition {
+    /// Wait until `*ptr >= value`, using a cyclic comparison which ignores wraparound.
+    Geq = 0,
+    /// Wait until `*ptr == value`.
+    Eq = 1,
+    /// Wait until `(*ptr & value) != 0`.
+    And = 2,
+    /// Wait until `!(*ptr | value) != 0`. Requires `DeviceAttribute::CanUseStreamWaitValueNor`.
+    Nor = 3,
+}
+
+bitflags! {
+    /// Bit flags for `Stream::wait_value`.
+    pub struct WaitValueFlags: u32 {
+        /// No flags set.
+        const DEFAULT = 0x0;
+
+        /// Flush remote writes to the waiting device's memory once the wait completes, so that
+        /// work after the wait observes all writes which happened before the value was written.
+        const FLUSH = 0x4000_0000;
+    }
+}
+
+bitflags! {
+    /// Bit flags for `Stream::write_value`.
+    pub struct WriteValueFlags: u32 {
+        /// No flags set. The write waits for all previous work in the stream, and all of that
+        /// work's memory writes are visible before the value is.
+        const DEFAULT = 0x0;
+
+        /// Allow the write to become visible before previous work's memory writes.
+        const NO_MEMORY_BARRIER = 0x1;
+    }
+}
+
+/// Values which can be waited on or written by stream memory operations.
+///
+/// Implemented for `u32` and `u64`. 64-bit operations require
+/// `DeviceAttribute::CanUse64BitStreamMemOps`. This trait is sealed and cannot be implemented
+/// outside of RustaCUDA.
+pub trait MemOpValue: DeviceCopy + crate::private::Sealed {
+    #[doc(hidden)]
+    const IS_64_BIT: bool;
+    #[doc(hidden)]
+    fn to_raw(self) -> CUstreamMemOpValue;
+}
+impl MemOpValue for u32 {
+    const IS_64_BIT: bool = false;
+    fn to_raw(self) -> CUstreamMemOpValue {
+        CUstreamMemOpValue { value: self }
+    }
+}
+impl MemOpValue for u64 {
+    const IS_64_BIT: bool = true;
+    fn to_raw(self) -> CUstreamMemOpValue {
+        CUstreamMemOpValue { value64: self }
+    }
+}
+
+/// Device-visible address of a value used by stream memory operations.
+///
+/// Created from a `DevicePointer`, or from an element of a page-locked
+/// [`LockedBuffer`](../memory/struct.LockedBuffer.html), which lets the host signal the device or
+/// observe its progress.
+pub struct MemOpPointer<T> {
+    addr: CUdeviceptr,
+    _marker: PhantomData<*mut T>,
+}
+impl<T: MemOpValue> MemOpPointer<T> {
+    /// Returns the device-visible address of `buffer[index]`.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Errors:
+    ///
+    /// If the buffer cannot be mapped into the current context's address space, returns the error.
+    pub fn from_locked(buffer: &LockedBuffer<T>, index: usize) -> CudaResult<Self> {
+        let host = &buffer[index] as *const T as *mut c_void;
+        unsafe {
+            let mut addr = 0;
+            cuda::cuMemHostGetDevicePointer_v2(&mut addr, host, 0).to_result()?;
+            Ok(MemOpPointer {
+                addr,
+                _marker: PhantomData,
+            })
+        }
+    }
+}
+impl<T: MemOpValue> From<DevicePointer<T>> for MemOpPointer<T> {
+    fn from(ptr: DevicePointer<T>) -> Self {
+        MemOpPointer {
+            addr: ptr.as_raw() as CUdeviceptr,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<T> Clone for MemOpPointer<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for MemOpPointer<T> {}
+impl<T> fmt::Debug for MemOpPointer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("MemOpPointer")
+            .field(&(self.addr as *const c_void))
+            .finish()
+    }
+}
+
+/// A single operation for `Stream::batch_mem_ops`.
+#[derive(Clone, Copy)]
+pub struct MemOp {
+    raw: CUstreamBatchMemOpParams,
+    kind: MemOpKind,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MemOpKind {
+    Wait { is_64_bit: bool, nor: bool },
+    Write { is_64_bit: bool },
+    Flush,
+}
+impl MemOp {
+    /// Wait until the value at `ptr` satisfies `condition` with respect to `value`.
+    ///
+    /// See `Stream::wait_value`.
+    pub fn wait_value<T, P>(
+        ptr: P,
+        value: T,
+        condition: WaitCondition,
+        flags: WaitValueFlags,
+    ) -> Self
+    where
+        T: MemOpValue,
+        P: Into<MemOpPointer<T>>,
+    {
+        let params = CUstreamMemOpValueParams {
+            operation: if T::IS_64_BIT {
+                WAIT_VALUE_64
+            } else {
+                WAIT_VALUE_32
+            },
+            address: ptr.into().addr,
+            value: value.to_raw(),
+            flags: condition as u32 | flags.bits(),
+            alias: 0,
+        };
+        MemOp {
+            raw: CUstreamBatchMemOpParams { waitValue: params },
+            kind: MemOpKind::Wait {
+                is_64_bit: T::IS_64_BIT,
+                nor: condition == WaitCondition::Nor,
+            },
+        }
+    }
+
+    /// Write `value` to `ptr`.
+    ///
+    /// See `Stream::write_value`.
+    pub fn write_value<T, P>(ptr: P, value: T, flags: WriteValueFlags) -> Self
+    where
+        T: MemOpValue,
+        P: Into<MemOpPointer<T>>,
+    {
+        let params = CUstreamMemOpValueParams {
+            operation: if T::IS_64_BIT {
+                WRITE_VALUE_64
+            } else {
+                WRITE_VALUE_32
+            },
+            address: ptr.into().addr,
+            value: value.to_raw(),
+            flags: flags.bits(),
+            alias: 0,
+        };
+        MemOp {
+            raw: CUstreamBatchMemOpParams { writeValue: params },
+            kind: MemOpKind::Write {
+                is_64_bit: T::IS_64_BIT,
+            },
+        }
+    }
+
+    /// Flush remote writes to the device's memory, so that later work observes them.
+    pub fn flush_remote_writes() -> Self {
+        MemOp {
+            raw: CUstreamBatchMemOpParams {
+                flushRemoteWrites: CUstreamMemOpFlushRemoteWritesParams {
+                    operation: FLUSH_REMOTE_WRITES,
+                    flags: 0,
+                },
+            },
+            kind: MemOpKind::Flush,
+        }
+    }
+
+    fn check_supported(&self) -> CudaResult<()> {
+        match self.kind {
+            MemOpKind::Wait { is_64_bit, nor } => check_supported(is_64_bit, nor),
+            MemOpKind::Write { is_64_bit } => check_supported(is_64_bit, false),
+            MemOpKind::Flush => check_supported(false, false),
+        }
+    }
+}
+impl fmt::Debug for MemOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemOp").field("kind", &self.kind).finish()
+    }
+}
+
+impl Stream {
+    /// Make the stream wait until the value at `ptr` satisfies `condition` with respect to
+    /// `value`.
+    ///
+    /// Work queued on the stream after this call does not start until the condition is met. The
+    /// value is typically written by another stream with `write_value`, by another device, or by
+    /// the host through a mapped `LockedBuffer`, allowing synchronization without involving the
+    /// host thread.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `NotSupported` if the current device does not support stream memory operations,
+    /// 64-bit operations (for `u64`) or `WaitCondition::Nor`, as reported by the
+    /// `CanUseStreamMemOps`, `CanUse64BitStreamMemOps` and `CanUseStreamWaitValueNor` device
+    /// attributes.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must remain valid until the wait has completed. If the condition is never met, the
+    /// stream will never make progress.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// # use rustacuda::context::CurrentContext;
+    /// use rustacuda::device::DeviceAttribute;
+    /// use rustacuda::memory::DeviceBox;
+    /// use rustacuda::stream::*;
+    ///
+    /// # if CurrentContext::get_device()?.get_attribute(DeviceAttribute::CanUseStreamMemOps)? == 0 {
+    /// #     return Ok(());
+    /// # }
+    /// let producer = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let consumer = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let mut flag = DeviceBox::new(&0u32)?;
+    ///
+    /// unsafe {
+    ///     consumer.wait_value(flag.as_device_ptr(), 1u32, WaitCondition::Eq, WaitValueFlags::DEFAULT)?;
+    ///     // ... work on `consumer` which depends on `producer` ...
+    ///     producer.write_value(flag.as_device_ptr(), 1u32, WriteValueFlags::DEFAULT)?;
+    /// }
+    /// consumer.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn wait_value<T, P>(
+        &self,
+        ptr: P,
+        value: T,
+        condition: WaitCondition,
+        flags: WaitValueFlags,
+    ) -> CudaResult<()>
+    where
+        T: MemOpValue,
+        P: Into<MemOpPointer<T>>,
+    {
+        check_supported(T::IS_64_BIT, condition == WaitCondition::Nor)?;
+        let addr = ptr.into().addr;
+        let flags = condition as u32 | flags.bits();
+        if T::IS_64_BIT {
+            ffi::cuStreamWaitValue64(self.as_inner(), addr, value.to_raw().value64, flags)
+                .to_result()
+        } else {
+            ffi::cuStreamWaitValue32(self.as_inner(), addr, value.to_raw().value, flags).to_result()
+        }
+    }
+
+    /// Write `value` to `ptr` once all previous work in the stream has completed.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `NotSupported` if the current device does not support stream memory operations,
+    /// or 64-bit operations for `u64`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must remain valid until the write has completed, and must not be accessed by other
+    /// work in a way which races with the write.
+    pub unsafe fn write_value<T, P>(
+        &self,
+        ptr: P,
+        value: T,
+        flags: WriteValueFlags,
+    ) -> CudaResult<()>
+    where
+        T: MemOpValue,
+        P: Into<MemOpPointer<T>>,
+    {
+        check_supported(T::IS_64_BIT, false)?;
+        let addr = ptr.into().addr;
+        if T::IS_64_BIT {
+            ffi::cuStreamWriteValue64(self.as_inner(), addr, value.to_raw().value64, flags.bits())
+                .to_result()
+        } else {
+            ffi::cuStreamWriteValue32(self.as_inner(), addr, value.to_raw().value, flags.bits())
+                .to_result()
+        }
+    }
+
+    /// Queue several memory operations at once.
+    ///
+    /// This is equivalent to calling `wait_value` and `write_value` for each operation in turn,
+    /// but with less overhead.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `NotSupported` if any operation is not supported by the current device.
+    ///
+    /// # Safety
+    ///
+    /// Every address used by `ops` must remain valid until the operations have completed.
+    pub unsafe fn batch_mem_ops(&self, ops: &[MemOp]) -> CudaResult<()> {
+        for op in ops {
+            op.check_supported()?;
+        }
+        let mut raw: Vec<CUstreamBatchMemOpParams> = ops.iter().map(|op| op.raw).collect();
+        ffi::cuStreamBatchMemOp(self.as_inner(), raw.len() as u32, raw.as_mut_ptr(), 0).to_result()
+    }
+}
+
+fn check_supported(is_64_bit: bool, nor: bool) -> CudaResult<()> {
+    let device = CurrentContext::get_device()?;
+    let mut required = vec![DeviceAttribute::CanUseStreamMemOps];
+    if is_64_bit {
+        required.push(DeviceAttribute::CanUse64BitStreamMemOps);
+    }
+    if nor {
+        required.push(DeviceAttribute::CanUseStreamWaitValueNor);
+    }
+    for attr in required {
+        if device.get_attribute(attr)? == 0 {
+            return Err(CudaError::NotSupported);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::*;
+    use crate::quick_init;
+    use crate::stream::StreamFlags;
+    use std::error::Error;
+
+    fn supported(attr: DeviceAttribute) -> CudaResult<bool> {
+        Ok(CurrentContext::get_device()?.get_attribute(attr)? != 0)
+    }
+
+    #[test]
+    fn test_write_then_wait_value() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let mut flag = DeviceBox::new(&0u32)?;
+
+        if !supported(DeviceAttribute::CanUseStreamMemOps)? {
+            let result =
+                unsafe { stream.write_value(flag.as_device_ptr(), 1u32, WriteValueFlags::DEFAULT) };
+            assert_eq!(Err(CudaError::NotSupported), result);
+            return Ok(());
+        }
+
+        unsafe {
+            stream.write_value(flag.as_device_ptr(), 7u32, WriteValueFlags::DEFAULT)?;
+            stream.wait_value(
+                flag.as_device_ptr(),
+                5u32,
+                WaitCondition::Geq,
+                WaitValueFlags::DEFAULT,
+            )?;
+        }
+        stream.synchronize()?;
+        let mut value = 0u32;
+        flag.copy_to(&mut value)?;
+        assert_eq!(7, value);
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_mem_ops_on_locked_buffer() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        if !supported(DeviceAttribute::CanUseStreamMemOps)?
+            || !supported(DeviceAttribute::CanUse64BitStreamMemOps)?
+        {
+            return Ok(());
+        }
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let buffer = LockedBuffer::new(&0u64, 2)?;
+
+        unsafe {
+            stream.batch_mem_ops(&[
+                MemOp::write_value(
+                    MemOpPointer::from_locked(&buffer, 0)?,
+                    1u64 << 40,
+                    WriteValueFlags::DEFAULT,
+                ),
+                MemOp::wait_value(
+                    MemOpPointer::from_locked(&buffer, 0)?,
+                    1u64 << 40,
+                    WaitCondition::And,
+                    WaitValueFlags::DEFAULT,
+                ),
+                MemOp::write_value(
+                    MemOpPointer::from_locked(&buffer, 1)?,
+                    3u64,
+                    WriteValueFlags::DEFAULT,
+                ),
+            ])?;
+        }
+        stream.synchronize()?;
+        assert_eq!(&[1u64 << 40, 3], buffer.as_slice());
+        Ok(())
+    }
+}
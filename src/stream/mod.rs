@@ -0,0 +1,789 @@
+//! Streams of work for the device to perform.
+//!
+//! In CUDA, most work is performed asynchronously. Even tasks such as memory copying can be
+//! scheduled by the host and performed when ready. Scheduling this work is done using a Stream.
+//!
+//! A stream is required for all asynchronous tasks in CUDA, such as kernel launches and
+//! asynchronous memory copying. Each task in a stream is performed in the order it was scheduled,
+//! and tasks within a stream cannot overlap. Tasks scheduled in multiple streams may interleave or
+//! execute concurrently. Sequencing between multiple streams can be achieved using events, which
+//! are not currently supported by RustaCUDA. Finally, the host can wait for all work scheduled in
+//! a stream to be completed.
+
+use crate::device::DeviceAttribute;
+use crate::error::{CudaError, CudaResult, DropResult, ToResult};
+use crate::event::Event;
+use crate::ffi;
+use crate::function::{
+    in_stream_context, BlockSize, Function, GridSize, LaunchConfig, LaunchConfigError,
+};
+use crate::memory::DeviceCopy;
+use cuda_sys::cuda::{self, CUstream};
+use std::cmp;
+use std::ffi::c_void;
+use std::mem;
+use std::ops::Deref;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod capture;
+mod completion;
+mod memops;
+mod pool;
+mod scope;
+mod task;
+
+pub use self::capture::*;
+pub use self::completion::*;
+pub use self::memops::*;
+pub use self::pool::*;
+pub use self::scope::*;
+pub use self::task::*;
+
+// Special handles for the default streams, from cuda.h.
+const CU_STREAM_LEGACY: CUstream = 0x1 as CUstream;
+const CU_STREAM_PER_THREAD: CUstream = 0x2 as CUstream;
+
+// Handles to the same default stream share one panic flag, so that a panic in a host function
+// queued through one handle is reported by `synchronize` on any other.
+static LEGACY_HOST_FN_PANICKED: AtomicBool = AtomicBool::new(false);
+thread_local! {
+    static PER_THREAD_HOST_FN_PANICKED: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+}
+
+// Set by host functions and callbacks which panic, and reported by the next synchronize.
+#[derive(Clone, Debug)]
+enum PanicFlag {
+    Owned(Arc<AtomicBool>),
+    Static(&'static AtomicBool),
+}
+impl Deref for PanicFlag {
+    type Target = AtomicBool;
+
+    fn deref(&self) -> &AtomicBool {
+        match self {
+            PanicFlag::Owned(flag) => flag,
+            PanicFlag::Static(flag) => flag,
+        }
+    }
+}
+
+bitflags! {
+    /// Bit flags for configuring a CUDA Stream.
+    pub struct StreamFlags: u32 {
+        /// No flags set.
+        const DEFAULT = 0x00;
+
+        /// This stream does not synchronize with the NULL stream.
+        ///
+        /// Note that the name is chosen to correspond to CUDA documentation, but is nevertheless
+        /// misleading. All work within a single stream is ordered and asynchronous regardless
+        /// of whether this flag is set. All streams in RustaCUDA may execute work concurrently,
+        /// regardless of the flag. However, for legacy reasons, CUDA has a notion of a NULL stream,
+        /// which is used as the default when no other stream is provided. Work on other streams
+        /// may not be executed concurrently with work on the NULL stream unless this flag is set.
+        /// The NULL stream is only used by RustaCUDA through `Stream::legacy_default`, so this flag
+        /// has no effect in most circumstances. However, it is recommended to use it anyway, as
+        /// some other crate in this binary may be using the NULL stream directly.
+        const NON_BLOCKING = 0x01;
+    }
+}
+
+/// Status enum that represents the current status of a stream.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StreamStatus {
+    /// All work submitted to the stream has been completed.
+    Ready,
+
+    /// Work submitted to the stream is still in progress.
+    NotReady,
+}
+
+bitflags! {
+    /// Bit flags for configuring a CUDA Stream waiting on an CUDA Event.
+    ///
+    /// Current versions of CUDA support only the default flag.
+    pub struct StreamWaitEventFlags: u32 {
+        /// No flags set.
+        const DEFAULT = 0x0;
+    }
+}
+
+/// A stream of work for the device to perform.
+///
+/// See the module-level documentation for more information.
+#[derive(Debug)]
+pub struct Stream {
+    inner: CUstream,
+    host_fn_panicked: PanicFlag,
+}
+impl Stream {
+    /// Create a new stream with the given flags and optional priority.
+    ///
+    /// By convention, `priority` follows a convention where lower numbers represent greater
+    /// priorities. That is, work in a stream with a lower priority number may pre-empt work in
+    /// a stream with a higher priority number. `Context::get_stream_priority_range` can be used
+    /// to get the range of valid priority values; if priority is set outside that range, it will
+    /// be automatically clamped to the lowest or highest number in the range.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// // With default priority
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// // With specific priority
+    /// let priority = Stream::new(StreamFlags::NON_BLOCKING, 1i32.into())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(flags: StreamFlags, priority: Option<i32>) -> CudaResult<Self> {
+        unsafe {
+            let mut stream = Stream {
+                inner: ptr::null_mut(),
+                host_fn_panicked: PanicFlag::Owned(Arc::new(AtomicBool::new(false))),
+            };
+            cuda::cuStreamCreateWithPriority(
+                &mut stream.inner as *mut CUstream,
+                flags.bits(),
+                priority.unwrap_or(0),
+            )
+            .to_result()?;
+            Ok(stream)
+        }
+    }
+
+    /// Returns a handle to the legacy default stream (also known as the NULL stream).
+    ///
+    /// Work on the legacy default stream waits for all work on blocking streams in the context,
+    /// and blocking streams wait for it; streams created with `StreamFlags::NON_BLOCKING` do not
+    /// synchronize with it. This is the stream used by other CUDA code when no stream is given.
+    ///
+    /// The handle does not own the stream: dropping it does not destroy anything, and the stream
+    /// is always available while the context is current. It can be used anywhere a `Stream` can.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::Stream;
+    ///
+    /// let stream = Stream::legacy_default();
+    ///
+    /// // ... queue up some work on the stream
+    ///
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn legacy_default() -> Stream {
+        Stream {
+            inner: CU_STREAM_LEGACY,
+            host_fn_panicked: PanicFlag::Static(&LEGACY_HOST_FN_PANICKED),
+        }
+    }
+
+    /// Returns a handle to the calling thread's per-thread default stream.
+    ///
+    /// Each host thread has its own per-thread default stream, which does not synchronize with
+    /// other streams except the legacy default stream. It is the stream used by code compiled
+    /// with `--default-stream per-thread` when no stream is given.
+    ///
+    /// Like `legacy_default`, the handle does not own the stream and dropping it does nothing.
+    /// The handle refers to the default stream of whichever thread uses it.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::Stream;
+    ///
+    /// let stream = Stream::per_thread_default();
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn per_thread_default() -> Stream {
+        Stream {
+            inner: CU_STREAM_PER_THREAD,
+            host_fn_panicked: PanicFlag::Owned(PER_THREAD_HOST_FN_PANICKED.with(Arc::clone)),
+        }
+    }
+
+    // Returns true if this is a handle to a default stream, which must not be destroyed.
+    fn is_default(&self) -> bool {
+        self.inner == CU_STREAM_LEGACY || self.inner == CU_STREAM_PER_THREAD
+    }
+
+    /// Return the flags which were used to create this stream.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// assert_eq!(StreamFlags::NON_BLOCKING, stream.get_flags().unwrap());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_flags(&self) -> CudaResult<StreamFlags> {
+        unsafe {
+            let mut bits = 0u32;
+            cuda::cuStreamGetFlags(self.inner, &mut bits as *mut u32).to_result()?;
+            Ok(StreamFlags::from_bits_truncate(bits))
+        }
+    }
+
+    /// Return the priority of this stream.
+    ///
+    /// If this stream was created without a priority, returns the default priority.
+    /// If the stream was created with a priority outside the valid range, returns the clamped
+    /// priority.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, 1i32.into())?;
+    /// println!("{}", stream.get_priority()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_priority(&self) -> CudaResult<i32> {
+        unsafe {
+            let mut priority = 0i32;
+            cuda::cuStreamGetPriority(self.inner, &mut priority as *mut i32).to_result()?;
+            Ok(priority)
+        }
+    }
+
+    /// Add a callback to a stream.
+    ///
+    /// The callback will be executed after all previously queued
+    /// items in the stream have been completed. Subsequently queued
+    /// items will not execute until the callback is finished.
+    ///
+    /// Callbacks must not make any CUDA API calls.
+    ///
+    /// The callback may run after this call returns, so it must own everything it uses.
+    ///
+    /// The callback will be passed a `CudaResult<()>` indicating the
+    /// current state of the device with `Ok(())` denoting normal operation.
+    ///
+    /// If the callback panics, the panic is caught and the next call to
+    /// `synchronize` on this stream returns `CudaError::HostFunctionPanicked`.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `StreamCaptureUnsupported` if the stream is being captured, since a graph may run
+    /// the callback more than once.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, 1i32.into())?;
+    ///
+    /// // ... queue up some work on the stream
+    ///
+    /// stream.add_callback(Box::new(|status| {
+    ///     println!("Device status is {:?}", status);
+    /// }));
+    ///
+    /// // ... queue up some more work on the stream
+    /// # Ok(())
+    /// # }
+    pub fn add_callback<T>(&self, callback: Box<T>) -> CudaResult<()>
+    where
+        T: FnOnce(CudaResult<()>) + Send + 'static,
+    {
+        reject_capture(self.inner)?;
+        let user_data = Box::into_raw(Box::new(HostFnData {
+            f: callback,
+            panicked: self.host_fn_panicked.clone(),
+        }));
+        unsafe {
+            let result = ffi::cuStreamAddCallback(
+                self.inner,
+                Some(callback_wrapper::<T>),
+                user_data as *mut c_void,
+                0,
+            )
+            .to_result();
+            if result.is_err() {
+                // The callback will never be called, so it must be freed here.
+                drop(Box::from_raw(user_data));
+            }
+            result
+        }
+    }
+
+    /// Launch a function on the host in stream order.
+    ///
+    /// The function will be executed after all previously queued items in the stream have been
+    /// completed, and subsequently queued items will not execute until it has finished. Unlike
+    /// `add_callback`, the function is not told whether earlier work succeeded, and is not run at
+    /// all if the context is in an error state.
+    ///
+    /// The function runs on a thread owned by the CUDA driver and must not make any CUDA API
+    /// calls. If it panics, the panic is caught and the next call to `synchronize` on this stream
+    /// returns `CudaError::HostFunctionPanicked`.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `StreamCaptureUnsupported` if the stream is being captured, since a graph may run
+    /// the function more than once. Use `Graph::add_host_node` to add host functions to a graph.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// // ... queue up some work on the stream
+    ///
+    /// stream.launch_host_fn(|| println!("Work is done"))?;
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn launch_host_fn<F>(&self, f: F) -> CudaResult<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        reject_capture(self.inner)?;
+        let user_data = Box::into_raw(Box::new(HostFnData {
+            f,
+            panicked: self.host_fn_panicked.clone(),
+        }));
+        unsafe {
+            let result = ffi::cuLaunchHostFunc(
+                self.inner,
+                Some(host_fn_wrapper::<F>),
+                user_data as *mut c_void,
+            )
+            .to_result();
+            if result.is_err() {
+                // The function will never be called, so it must be freed here.
+                drop(Box::from_raw(user_data));
+            }
+            result
+        }
+    }
+
+    /// Wait until a stream's tasks are completed.
+    ///
+    /// Waits until the device has completed all operations scheduled for this stream.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, 1i32.into())?;
+    ///
+    /// // ... queue up some work on the stream
+    ///
+    /// // Wait for the work to be completed.
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn synchronize(&self) -> CudaResult<()> {
+        unsafe { ffi::cuStreamSynchronize(self.inner).to_result()? }
+        self.take_host_fn_panic()
+    }
+
+    /// Query whether all work submitted to the stream has been completed, without blocking.
+    ///
+    /// # Errors:
+    ///
+    /// Returns any error reported by the driver for work already submitted to the stream.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags, StreamStatus};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// // ... queue up some work on the stream
+    /// # stream.synchronize()?;
+    ///
+    /// if stream.query()? == StreamStatus::Ready {
+    ///     // All work is done
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query(&self) -> CudaResult<StreamStatus> {
+        let result = unsafe { ffi::cuStreamQuery(self.inner).to_result() };
+
+        match result {
+            Ok(()) => Ok(StreamStatus::Ready),
+            Err(CudaError::NotReady) => Ok(StreamStatus::NotReady),
+            Err(other) => Err(other),
+        }
+    }
+
+    /// Wait until a stream's tasks are completed, or until `timeout` has elapsed.
+    ///
+    /// Unlike `synchronize`, this polls the stream with `query`, sleeping for exponentially
+    /// increasing intervals (up to one millisecond) between polls. This keeps the thread
+    /// responsive if a kernel never finishes, at the cost of slightly higher latency than
+    /// `synchronize`.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `CudaError::Timeout` if the work has not completed when `timeout` elapses. The
+    /// work is not cancelled. Also returns any error reported by the driver for work submitted
+    /// to the stream.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// use std::time::Duration;
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// // ... queue up some work on the stream
+    ///
+    /// stream.synchronize_timeout(Duration::from_secs(10))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn synchronize_timeout(&self, timeout: Duration) -> CudaResult<()> {
+        const INITIAL_BACKOFF: Duration = Duration::from_micros(1);
+        const MAX_BACKOFF: Duration = Duration::from_millis(1);
+
+        let start = Instant::now();
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            if self.query()? == StreamStatus::Ready {
+                return self.take_host_fn_panic();
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Err(CudaError::Timeout);
+            }
+            thread::sleep(cmp::min(backoff, timeout - elapsed));
+            backoff = cmp::min(backoff * 2, MAX_BACKOFF);
+        }
+    }
+
+    /// Make the stream wait on an event.
+    ///
+    /// All future work submitted to the stream will wait for the event to
+    /// complete. Synchronization is performed on the device, if possible. The
+    /// event may originate from different context or device than the stream.
+    ///
+    /// The stream waits for the work captured by the most recent `record` of the event at the
+    /// time `wait_event` is called, so the same event may be waited on by several streams, and
+    /// may be recorded again afterwards without affecting this wait.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// # use rustacuda::quick_init;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _context = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags, StreamWaitEventFlags};
+    /// use rustacuda::event::{Event, EventFlags};
+    ///
+    /// let stream_0 = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let stream_1 = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let event = Event::new(EventFlags::DEFAULT)?;
+    ///
+    /// // do some work on stream_0 ...
+    ///
+    /// // record an event
+    /// event.record(&stream_0)?;
+    ///
+    /// // wait until the work on stream_0 is finished before continuing stream_1
+    /// stream_1.wait_event(&event, StreamWaitEventFlags::DEFAULT)?;
+    /// # Ok(())
+    /// }
+    /// ```
+    pub fn wait_event(&self, event: &Event, flags: StreamWaitEventFlags) -> CudaResult<()> {
+        unsafe { ffi::cuStreamWaitEvent(self.inner, event.as_inner(), flags.bits()).to_result() }
+    }
+
+    // Hidden implementation detail function used by the `launch!` macro to check the type of
+    // each kernel argument. Any `DeviceCopy` value may be passed to a kernel on a stream.
+    #[doc(hidden)]
+    pub fn check_launch_arg<T: DeviceCopy>(&self, _arg: &T) {}
+
+    // Hidden implementation detail function. Highly unsafe. Use the `launch!` macro instead.
+    #[doc(hidden)]
+    pub unsafe fn launch<G, B>(
+        &self,
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<()>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        let grid_size: GridSize = grid_size.into();
+        let block_size: BlockSize = block_size.into();
+
+        ffi::cuLaunchKernel(
+            func.to_inner(),
+            grid_size.x,
+            grid_size.y,
+            grid_size.z,
+            block_size.x,
+            block_size.y,
+            block_size.z,
+            shared_mem_bytes,
+            self.inner,
+            args.as_ptr() as *mut _,
+            ptr::null_mut(),
+        )
+        .to_result()
+    }
+
+    // Hidden implementation detail function. Highly unsafe. Use `Kernel::launch` with a struct
+    // deriving `KernelArgs` instead.
+    #[doc(hidden)]
+    pub unsafe fn launch_packed<G, B>(
+        &self,
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        params: &[u8],
+    ) -> CudaResult<()>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        let grid_size: GridSize = grid_size.into();
+        let block_size: BlockSize = block_size.into();
+
+        let mut size = params.len();
+        let mut extra = [
+            ffi::CU_LAUNCH_PARAM_BUFFER_POINTER as *mut c_void,
+            params.as_ptr() as *mut c_void,
+            ffi::CU_LAUNCH_PARAM_BUFFER_SIZE as *mut c_void,
+            &mut size as *mut usize as *mut c_void,
+            ffi::CU_LAUNCH_PARAM_END as *mut c_void,
+        ];
+        ffi::cuLaunchKernel(
+            func.to_inner(),
+            grid_size.x,
+            grid_size.y,
+            grid_size.z,
+            block_size.x,
+            block_size.y,
+            block_size.z,
+            shared_mem_bytes,
+            self.inner,
+            ptr::null_mut(),
+            extra.as_mut_ptr(),
+        )
+        .to_result()
+    }
+
+    // Hidden implementation detail function. Highly unsafe. Use the `launch_cooperative!` macro
+    // instead.
+    #[doc(hidden)]
+    pub unsafe fn launch_cooperative<G, B>(
+        &self,
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<()>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        let config = LaunchConfig::new(grid_size, block_size).shared_mem_bytes(shared_mem_bytes);
+        // Check the stream's own device, which need not be the current one.
+        in_stream_context(self, |device| {
+            config
+                .check_co_resident(func, device, DeviceAttribute::CooperativeLaunch)
+                .map_err(LaunchConfigError::into_cuda_error)
+        })?;
+
+        ffi::cuLaunchCooperativeKernel(
+            func.to_inner(),
+            config.get_grid().x,
+            config.get_grid().y,
+            config.get_grid().z,
+            config.get_block().x,
+            config.get_block().y,
+            config.get_block().z,
+            shared_mem_bytes,
+            self.inner,
+            args.as_ptr() as *mut _,
+        )
+        .to_result()
+    }
+
+    // Report (and clear) a panic in a host function or callback queued on this stream.
+    fn take_host_fn_panic(&self) -> CudaResult<()> {
+        if self.host_fn_panicked.swap(false, Ordering::SeqCst) {
+            Err(CudaError::HostFunctionPanicked)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Get the inner `CUstream` from the `Stream`.
+    //
+    // Necessary for certain CUDA functions outside of this
+    // module that expect a bare `CUstream`.
+    pub(crate) fn as_inner(&self) -> CUstream {
+        self.inner
+    }
+
+    /// Destroy a `Stream`, returning an error.
+    ///
+    /// Destroying a stream can return errors from previous asynchronous work. This function
+    /// destroys the given stream and returns the error and the un-destroyed stream on failure.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, 1i32.into())?;
+    /// match Stream::drop(stream) {
+    ///     Ok(()) => println!("Successfully destroyed"),
+    ///     Err((e, stream)) => {
+    ///         println!("Failed to destroy stream: {:?}", e);
+    ///         // Do something with stream
+    ///     },
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn drop(mut stream: Stream) -> DropResult<Stream> {
+        if stream.inner.is_null() || stream.is_default() {
+            return Ok(());
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut stream.inner, ptr::null_mut());
+            match cuda::cuStreamDestroy_v2(inner).to_result() {
+                Ok(()) => {
+                    mem::forget(stream);
+                    Ok(())
+                }
+                Err(e) => Err((
+                    e,
+                    Stream {
+                        inner,
+                        host_fn_panicked: stream.host_fn_panicked.clone(),
+                    },
+                )),
+            }
+        }
+    }
+}
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if self.inner.is_null() || self.is_default() {
+            return;
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut self.inner, ptr::null_mut());
+            // No choice but to panic here.
+            cuda::cuStreamDestroy_v2(inner)
+                .to_result()
+                .expect("Failed to destroy CUDA stream.");
+        }
+    }
+}
+struct HostFnData<F> {
+    f: F,
+    panicked: PanicFlag,
+}
+
+unsafe extern "C" fn callback_wrapper<T>(
+    _stream: CUstream,
+    status: ffi::CUresult,
+    user_data: *mut c_void,
+) where
+    T: FnOnce(CudaResult<()>) + Send + 'static,
+{
+    let data: Box<HostFnData<Box<T>>> = Box::from_raw(user_data as *mut HostFnData<Box<T>>);
+    let HostFnData { f, panicked } = *data;
+    // Stop panics from unwinding across the FFI
+    if panic::catch_unwind(AssertUnwindSafe(|| f(status.to_result()))).is_err() {
+        panicked.store(true, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn host_fn_wrapper<F>(user_data: *mut c_void)
+where
+    F: FnOnce() + Send,
+{
+    let data: Box<HostFnData<F>> = Box::from_raw(user_data as *mut HostFnData<F>);
+    let HostFnData { f, panicked } = *data;
+    // Stop panics from unwinding across the FFI
+    if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+        panicked.store(true, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::error::{CudaError, CudaResult};
+use crate::event::{Event, EventFlags};
+use crate::stream::{Stream, StreamFlags, StreamStatus, StreamWaitEventFlags};
+use std::cell::Cell;
+use std::slice;
+
+/// A fixed set of streams which are created up front and handed out for reuse.
+///
+/// Creating and destroying streams is comparatively expensive. A `StreamPool` creates its streams
+/// once and hands out references to them, either in round-robin order with
+/// [`next`](#method.next) or by preferring idle streams with [`least_busy`](#method.least_busy).
+/// Work spread across the pool can be gathered back onto a single stream with
+/// [`join`](#method.join).
+///
+/// The streams are destroyed when the pool is dropped.
+///
+/// # Examples:
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::memory::*;
+/// use rustacuda::stream::{Stream, StreamFlags, StreamPool};
+///
+/// let pool = StreamPool::new(4, StreamFlags::NON_BLOCKING, None)?;
+/// let output = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+///
+/// let host = LockedBuffer::new(&1u32, 1024)?;
+/// let mut chunks = Vec::new();
+/// for chunk in host.chunks(256) {
+///     let mut device = DeviceBuffer::from_slice(&[0u32; 256])?;
+///     unsafe {
+///         device.async_copy_from(chunk, pool.next())?;
+///     }
+///     chunks.push(device);
+/// }
+///
+/// // Work queued on `output` after this point runs after all work queued on the pool so far.
+/// pool.join(&output)?;
+/// output.synchronize()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StreamPool {
+    streams: Vec<Stream>,
+    // One event per stream, recorded by `join`.
+    events: Vec<Event>,
+    next: Cell<usize>,
+}
+impl StreamPool {
+    /// Create a pool of `count` streams, all with the given flags and priority.
+    ///
+    /// See [`Stream::new`](struct.Stream.html#method.new) for the meaning of the flags and
+    /// priority.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `count` is zero, or any error returned while creating the streams.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{StreamFlags, StreamPool};
+    ///
+    /// let pool = StreamPool::new(4, StreamFlags::NON_BLOCKING, None)?;
+    /// assert_eq!(4, pool.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(count: usize, flags: StreamFlags, priority: Option<i32>) -> CudaResult<Self> {
+        StreamPool::with_priorities(flags, &vec![priority; count])
+    }
+
+    /// Create a pool with one stream per entry of `priorities`, all with the given flags.
+    ///
+    /// This allows a pool to mix high- and low-priority streams. Streams are handed out by `next`
+    /// in the same order as the priorities are given.
+    ///
+    /// # Errors:
+    ///
+    /// Returns `InvalidValue` if `priorities` is empty, or any error returned while creating the
+    /// streams.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{StreamFlags, StreamPool};
+    ///
+    /// let pool = StreamPool::with_priorities(StreamFlags::NON_BLOCKING, &[Some(-1), None, None])?;
+    /// assert_eq!(3, pool.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_priorities(flags: StreamFlags, priorities: &[Option<i32>]) -> CudaResult<Self> {
+        if priorities.is_empty() {
+            return Err(CudaError::InvalidValue);
+        }
+
+        let mut streams = Vec::with_capacity(priorities.len());
+        let mut events = Vec::with_capacity(priorities.len());
+        for &priority in priorities {
+            streams.push(Stream::new(flags, priority)?);
+            events.push(Event::new(EventFlags::DISABLE_TIMING)?);
+        }
+        Ok(StreamPool {
+            streams,
+            events,
+            next: Cell::new(0),
+        })
+    }
+
+    /// Returns the number of streams in the pool.
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Returns true if the pool contains no streams. Pools are never empty, so this always
+    /// returns false.
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Returns the stream at `index`, or `None` if it is out of range.
+    pub fn get(&self, index: usize) -> Option<&Stream> {
+        self.streams.get(index)
+    }
+
+    /// Returns an iterator over the streams in the pool.
+    pub fn iter(&self) -> slice::Iter<'_, Stream> {
+        self.streams.iter()
+    }
+
+    /// Returns the next stream in round-robin order.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{StreamFlags, StreamPool};
+    ///
+    /// let pool = StreamPool::new(2, StreamFlags::NON_BLOCKING, None)?;
+    /// let first = pool.next() as *const _;
+    /// let second = pool.next() as *const _;
+    /// assert_ne!(first, second);
+    /// assert_eq!(first, pool.next() as *const _);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn next(&self) -> &Stream {
+        let index = self.next.get();
+        self.next.set((index + 1) % self.streams.len());
+        &self.streams[index]
+    }
+
+    /// Returns a stream which has no outstanding work, falling back to round-robin order if every
+    /// stream is busy.
+    ///
+    /// Streams are queried starting from the next stream in round-robin order, so repeated calls
+    /// spread work across idle streams rather than always returning the first one.
+    ///
+    /// # Errors:
+    ///
+    /// If querying a stream returns an error from previous asynchronous work, that error is
+    /// returned.
+    pub fn least_busy(&self) -> CudaResult<&Stream> {
+        let start = self.next.get();
+        let len = self.streams.len();
+        for offset in 0..len {
+            let index = (start + offset) % len;
+            if self.streams[index].query()? == StreamStatus::Ready {
+                self.next.set((index + 1) % len);
+                return Ok(&self.streams[index]);
+            }
+        }
+        Ok(self.next())
+    }
+
+    /// Make all work subsequently queued on `stream` wait for the work queued on every stream in
+    /// the pool so far.
+    ///
+    /// An event is recorded on each stream of the pool and `stream` is made to wait on it. This
+    /// does not block the host. `stream` may itself be one of the pool's streams.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{StreamFlags, StreamPool};
+    ///
+    /// let pool = StreamPool::new(4, StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// // queue work on the pool's streams ...
+    ///
+    /// let target = pool.next();
+    /// pool.join(target)?;
+    /// target.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn join(&self, stream: &Stream) -> CudaResult<()> {
+        for (pool_stream, event) in self.streams.iter().zip(&self.events) {
+            if pool_stream.as_inner() == stream.as_inner() {
+                continue;
+            }
+            event.record(pool_stream)?;
+            stream.wait_event(event, StreamWaitEventFlags::DEFAULT)?;
+        }
+        Ok(())
+    }
+
+    /// Wait until all work queued on every stream in the pool has completed.
+    ///
+    /// # Errors:
+    ///
+    /// Returns the first error returned by synchronizing one of the streams. The remaining streams
+    /// are still synchronized.
+    pub fn synchronize(&self) -> CudaResult<()> {
+        let mut result = Ok(());
+        for stream in &self.streams {
+            let status = stream.synchronize();
+            if result.is_ok() {
+                result = status;
+            }
+        }
+        result
+    }
+}
+impl<'a> IntoIterator for &'a StreamPool {
+    type Item = &'a Stream;
+    type IntoIter = slice::Iter<'a, Stream>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::CurrentContext;
+    use crate::device::DeviceAttribute;
+    use crate::memory::*;
+    use crate::quick_init;
+    use crate::stream::{MemOpPointer, WaitCondition, WaitValueFlags};
+    use std::error::Error;
+    use std::ptr;
+
+    #[test]
+    fn test_round_robin_cycles_through_pool() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let pool = StreamPool::new(3, StreamFlags::NON_BLOCKING, None)?;
+        let handed_out: Vec<_> = (0..6).map(|_| pool.next().as_inner()).collect();
+        let expected: Vec<_> = pool.iter().chain(&pool).map(Stream::as_inner).collect();
+        assert_eq!(expected, handed_out);
+        Ok(())
+    }
+
+    #[test]
+    fn test_least_busy_prefers_idle_streams() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        if CurrentContext::get_device()?.get_attribute(DeviceAttribute::CanUseStreamMemOps)? == 0 {
+            return Ok(());
+        }
+        let pool = StreamPool::new(2, StreamFlags::NON_BLOCKING, None)?;
+        let mut flag = LockedBuffer::new(&0u32, 1)?;
+
+        // Block the first stream until the host releases it.
+        let busy = pool.get(0).unwrap();
+        unsafe {
+            busy.wait_value(
+                MemOpPointer::from_locked(&flag, 0)?,
+                1u32,
+                WaitCondition::Eq,
+                WaitValueFlags::DEFAULT,
+            )?;
+        }
+        for _ in 0..4 {
+            assert_eq!(
+                pool.get(1).unwrap().as_inner(),
+                pool.least_busy()?.as_inner()
+            );
+        }
+
+        unsafe { ptr::write_volatile(flag.as_mut_ptr(), 1) };
+        pool.synchronize()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_join() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let pool = StreamPool::with_priorities(StreamFlags::NON_BLOCKING, &[Some(-1), None])?;
+        let target = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let host = LockedBuffer::new(&7u64, 256)?;
+        let mut device = DeviceBuffer::from_slice(&[0u64; 256])?;
+        let mut output = LockedBuffer::new(&0u64, 256)?;
+
+        unsafe {
+            device.async_copy_from(&host, pool.next())?;
+        }
+        pool.join(&target)?;
+        unsafe {
+            device.async_copy_to(&mut output, &target)?;
+        }
+        target.synchronize()?;
+        assert!(output.iter().all(|&x| x == 7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_pool_is_rejected() {
+        let _context = quick_init().unwrap();
+        assert_eq!(
+            CudaError::InvalidValue,
+            StreamPool::new(0, StreamFlags::DEFAULT, None).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,512 @@
+use crate::error::CudaResult;
+use crate::function::{BlockSize, Function, GridSize};
+use crate::memory::{
+    AsyncCopyDestination, DeviceBox, DeviceBuffer, DeviceCopy, DevicePointer, DeviceSlice,
+};
+use crate::stream::Stream;
+use std::ffi::c_void;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A scope in which work can be queued on a stream using borrowed data.
+///
+/// Created by [`Stream::scope`](struct.Stream.html#method.scope). Host and device memory must be
+/// borrowed into the scope using `borrow` or `borrow_mut` before it can be used by asynchronous
+/// copies or kernel launches. The borrows last until the end of the scope, and the scope
+/// synchronizes the stream before returning, so the borrowed memory cannot be freed, modified or
+/// read by the host while the device may still be using it.
+///
+/// Work within a single stream executes in order, so the same borrow can be used by several
+/// operations in the scope.
+///
+/// Borrowed memory cannot be touched by the host until the scope ends:
+///
+/// ```compile_fail
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// use rustacuda::stream::{Stream, StreamFlags};
+///
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+/// let device = DeviceBuffer::from_slice(&[1u64, 2, 3]).unwrap();
+/// let mut output = LockedBuffer::new(&0u64, 3).unwrap();
+/// stream.scope(|s| {
+///     s.copy_to(s.borrow(&*device), s.borrow_mut(&mut output))?;
+///     output[0] = 4; // Error: `output` is borrowed by the pending copy
+///     Ok(())
+/// }).unwrap();
+/// ```
+///
+/// Kernels launched within the scope only accept arguments implementing
+/// [`ScopedArg`](trait.ScopedArg.html), so device memory must be borrowed into the scope and
+/// passed using the `as_device_ptr` methods of `ScopedRef` and `ScopedMut`:
+///
+/// ```compile_fail
+/// # let _context = rustacuda::quick_init().unwrap();
+/// # use rustacuda::launch;
+/// use rustacuda::memory::*;
+/// use rustacuda::module::Module;
+/// use rustacuda::stream::{Stream, StreamFlags};
+/// use std::ffi::CString;
+///
+/// let ptx = CString::new(include_str!("../../resources/add.ptx")).unwrap();
+/// let module = Module::load_from_string(&ptx).unwrap();
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+/// let mut x = DeviceBuffer::from_slice(&[1.0f32; 16]).unwrap();
+/// stream.scope(|s| {
+///     let ptr = x.as_device_ptr(); // Error: not borrowed into the scope
+///     unsafe { launch!(module.sum<<<1, 16, 0, s>>>(ptr, ptr, ptr, 16usize))? };
+///     Ok(())
+/// }).unwrap();
+/// ```
+pub struct StreamScope<'scope, 'env: 'scope> {
+    stream: &'scope Stream,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+/// Shared borrow of a value for the duration of a [`StreamScope`](struct.StreamScope.html).
+///
+/// The host may continue to read the value while the scope is active, but it cannot be modified.
+pub struct ScopedRef<'scope, T: ?Sized> {
+    ptr: *const T,
+    borrow: PhantomData<&'scope T>,
+}
+
+/// Exclusive borrow of a value for the duration of a [`StreamScope`](struct.StreamScope.html).
+///
+/// The host cannot access the value at all while the scope is active.
+pub struct ScopedMut<'scope, T: ?Sized> {
+    ptr: *mut T,
+    borrow: PhantomData<&'scope mut T>,
+}
+
+/// Device pointer to memory borrowed for the duration of a
+/// [`StreamScope`](struct.StreamScope.html).
+///
+/// Returned by the `as_device_ptr` methods of `ScopedRef` and `ScopedMut`. It has the same layout
+/// as a `DevicePointer`, so it is passed to kernels in the same way.
+#[repr(transparent)]
+pub struct ScopedPointer<'scope, T> {
+    ptr: DevicePointer<T>,
+    scope: PhantomData<&'scope ()>,
+}
+
+/// Kernel arguments which can be passed to kernels launched within a
+/// [`StreamScope`](struct.StreamScope.html).
+///
+/// This is implemented for scalars and for `ScopedPointer`, so any device memory a scoped kernel
+/// uses must have been borrowed into the scope.
+///
+/// # Safety
+///
+/// Types implementing this trait must not contain device pointers to memory which is not borrowed
+/// for at least `'scope`.
+pub unsafe trait ScopedArg<'scope>: DeviceCopy {}
+
+impl Stream {
+    /// Create a scope for queuing work on this stream which borrows host or device memory.
+    ///
+    /// Similar to `std::thread::scope`, this calls `f` with a
+    /// [`StreamScope`](struct.StreamScope.html) that can be used to queue asynchronous copies and
+    /// kernel launches on this stream. Memory must be borrowed into the scope with
+    /// `StreamScope::borrow` or `StreamScope::borrow_mut`, and those borrows last until the scope
+    /// ends. Before returning, `scope` waits until all work in the stream has completed, so
+    /// asynchronous copies can be performed without `unsafe`.
+    ///
+    /// The stream is synchronized even if `f` returns an error or panics.
+    ///
+    /// # Errors:
+    ///
+    /// Returns the error returned by `f`, or the error returned by synchronizing the stream.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let input = LockedBuffer::from_slice(&[1u64, 2, 3])?;
+    /// let mut output = LockedBuffer::new(&0u64, 3)?;
+    /// let mut device = DeviceBuffer::from_slice(&[0u64; 3])?;
+    ///
+    /// stream.scope(|s| {
+    ///     let input = s.borrow(&input);
+    ///     let output = s.borrow_mut(&mut output);
+    ///     let device = s.borrow_mut(&mut *device);
+    ///
+    ///     s.copy_from(device, input)?;
+    ///     s.copy_to(device.into(), output)
+    /// })?;
+    ///
+    /// assert_eq!(&[1, 2, 3], output.as_slice());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn scope<'env, F, R>(&self, f: F) -> CudaResult<R>
+    where
+        F: for<'scope> FnOnce(&'scope StreamScope<'scope, 'env>) -> CudaResult<R>,
+    {
+        // Synchronizes the stream if `f` panics, so that borrowed memory is not freed while the
+        // device is still using it.
+        struct SyncOnUnwind<'a>(&'a Stream);
+        impl<'a> Drop for SyncOnUnwind<'a> {
+            fn drop(&mut self) {
+                let _ = self.0.synchronize();
+            }
+        }
+
+        let scope = StreamScope {
+            stream: self,
+            scope: PhantomData,
+            env: PhantomData,
+        };
+        let guard = SyncOnUnwind(self);
+        let result = f(&scope);
+        std::mem::forget(guard);
+        let sync = self.synchronize();
+        let value = result?;
+        sync?;
+        Ok(value)
+    }
+}
+
+impl<'scope, 'env> StreamScope<'scope, 'env> {
+    /// Returns the stream this scope queues work on.
+    pub fn stream(&self) -> &'scope Stream {
+        self.stream
+    }
+
+    /// Borrow a value for the rest of the scope, allowing it to be used as the source of
+    /// asynchronous copies or as a read-only kernel argument.
+    pub fn borrow<T: ?Sized>(&self, value: &'scope T) -> ScopedRef<'scope, T> {
+        ScopedRef {
+            ptr: value,
+            borrow: PhantomData,
+        }
+    }
+
+    /// Mutably borrow a value for the rest of the scope, allowing it to be used as the destination
+    /// of asynchronous copies or as a kernel argument which the kernel writes to.
+    pub fn borrow_mut<T: ?Sized>(&self, value: &'scope mut T) -> ScopedMut<'scope, T> {
+        ScopedMut {
+            ptr: value,
+            borrow: PhantomData,
+        }
+    }
+
+    /// Asynchronously copy `source` into `dest` on the scope's stream.
+    ///
+    /// This is the scoped equivalent of `AsyncCopyDestination::async_copy_from`, and is used to
+    /// copy from host memory to the device or between two device allocations. Host memory should
+    /// be page-locked, otherwise the copy may be performed synchronously.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if `dest` and `source` refer to the same memory, or if they are different sizes.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    pub fn copy_from<D, S>(
+        &self,
+        dest: ScopedMut<'scope, D>,
+        source: ScopedRef<'scope, S>,
+    ) -> CudaResult<()>
+    where
+        D: AsyncCopyDestination<S> + ?Sized,
+        S: ?Sized,
+    {
+        assert_distinct(dest.ptr, source.ptr);
+        // Both borrows are held until the end of the scope, which synchronizes the stream, so the
+        // memory cannot be freed or accessed by the host until the copy is complete.
+        unsafe { (*dest.ptr).async_copy_from(&*source.ptr, self.stream) }
+    }
+
+    /// Asynchronously copy `source` into `dest` on the scope's stream.
+    ///
+    /// This is the scoped equivalent of `AsyncCopyDestination::async_copy_to`, and is used to copy
+    /// from the device back to host memory. Host memory should be page-locked, otherwise the copy
+    /// may be performed synchronously.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if `dest` and `source` refer to the same memory, or if they are different sizes.
+    ///
+    /// # Errors:
+    ///
+    /// If a CUDA error occurs, returns the error.
+    pub fn copy_to<S, D>(
+        &self,
+        source: ScopedRef<'scope, S>,
+        dest: ScopedMut<'scope, D>,
+    ) -> CudaResult<()>
+    where
+        S: AsyncCopyDestination<D> + ?Sized,
+        D: ?Sized,
+    {
+        assert_distinct(dest.ptr, source.ptr);
+        unsafe { (*source.ptr).async_copy_to(&mut *dest.ptr, self.stream) }
+    }
+
+    // Hidden implementation detail function used by the `launch!` macro to ensure that scoped
+    // launches only use memory borrowed into the scope.
+    #[doc(hidden)]
+    pub fn check_launch_arg<T: ScopedArg<'scope>>(&self, _arg: &T) {}
+
+    // Hidden implementation detail function used by the `launch!` macro, which allows the scope
+    // to be used in place of a stream.
+    #[doc(hidden)]
+    pub unsafe fn launch<G, B>(
+        &self,
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<()>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        self.stream
+            .launch(func, grid_size, block_size, shared_mem_bytes, args)
+    }
+
+    // Hidden implementation detail function used by the `launch_cooperative!` macro.
+    #[doc(hidden)]
+    pub unsafe fn launch_cooperative<G, B>(
+        &self,
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<()>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        self.stream
+            .launch_cooperative(func, grid_size, block_size, shared_mem_bytes, args)
+    }
+}
+
+fn assert_distinct<D: ?Sized, S: ?Sized>(dest: *mut D, source: *const S) {
+    assert!(
+        dest as *const u8 != source as *const u8,
+        "cannot copy a value into itself"
+    );
+}
+
+impl<'scope, 'env> fmt::Debug for StreamScope<'scope, 'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamScope")
+            .field("stream", &self.stream)
+            .finish()
+    }
+}
+
+impl<'scope, T: ?Sized> Clone for ScopedRef<'scope, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'scope, T: ?Sized> Copy for ScopedRef<'scope, T> {}
+impl<'scope, T: ?Sized> fmt::Debug for ScopedRef<'scope, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ScopedRef")
+            .field(&(self.ptr as *const u8))
+            .finish()
+    }
+}
+impl<'scope, T: ?Sized> From<ScopedMut<'scope, T>> for ScopedRef<'scope, T> {
+    fn from(borrow: ScopedMut<'scope, T>) -> Self {
+        ScopedRef {
+            ptr: borrow.ptr,
+            borrow: PhantomData,
+        }
+    }
+}
+impl<'scope, T> ScopedRef<'scope, DeviceSlice<T>> {
+    /// Returns a device pointer to the borrowed slice, for use as a kernel argument.
+    ///
+    /// The kernel must not write through this pointer.
+    pub fn as_device_ptr(&self) -> ScopedPointer<'scope, T> {
+        ScopedPointer::new(unsafe { DevicePointer::wrap((*self.ptr).as_ptr() as *mut T) })
+    }
+}
+impl<'scope, T> ScopedRef<'scope, DeviceBuffer<T>> {
+    /// Returns a device pointer to the borrowed buffer, for use as a kernel argument.
+    ///
+    /// The kernel must not write through this pointer.
+    pub fn as_device_ptr(&self) -> ScopedPointer<'scope, T> {
+        ScopedPointer::new(unsafe { DevicePointer::wrap((*self.ptr).as_ptr() as *mut T) })
+    }
+}
+impl<'scope, T> ScopedRef<'scope, DeviceBox<T>> {
+    /// Returns a device pointer to the borrowed box, for use as a kernel argument.
+    ///
+    /// The kernel must not write through this pointer.
+    pub fn as_device_ptr(&self) -> ScopedPointer<'scope, T> {
+        ScopedPointer::new(unsafe { (*self.ptr).device_ptr() })
+    }
+}
+
+impl<'scope, T: ?Sized> Clone for ScopedMut<'scope, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'scope, T: ?Sized> Copy for ScopedMut<'scope, T> {}
+impl<'scope, T: ?Sized> fmt::Debug for ScopedMut<'scope, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ScopedMut")
+            .field(&(self.ptr as *const u8))
+            .finish()
+    }
+}
+impl<'scope, T> ScopedMut<'scope, DeviceSlice<T>> {
+    /// Returns a device pointer to the borrowed slice, for use as a kernel argument.
+    pub fn as_device_ptr(&self) -> ScopedPointer<'scope, T> {
+        ScopedPointer::new(unsafe { (*self.ptr).as_device_ptr() })
+    }
+}
+impl<'scope, T> ScopedMut<'scope, DeviceBuffer<T>> {
+    /// Returns a device pointer to the borrowed buffer, for use as a kernel argument.
+    pub fn as_device_ptr(&self) -> ScopedPointer<'scope, T> {
+        ScopedPointer::new(unsafe { (*self.ptr).as_device_ptr() })
+    }
+}
+impl<'scope, T> ScopedMut<'scope, DeviceBox<T>> {
+    /// Returns a device pointer to the borrowed box, for use as a kernel argument.
+    pub fn as_device_ptr(&self) -> ScopedPointer<'scope, T> {
+        ScopedPointer::new(unsafe { (*self.ptr).as_device_ptr() })
+    }
+}
+
+impl<'scope, T> ScopedPointer<'scope, T> {
+    fn new(ptr: DevicePointer<T>) -> Self {
+        ScopedPointer {
+            ptr,
+            scope: PhantomData,
+        }
+    }
+
+    /// Returns the underlying device pointer.
+    ///
+    /// The pointer is only valid until the end of the scope.
+    pub fn as_raw(self) -> *const T {
+        self.ptr.as_raw()
+    }
+}
+impl<'scope, T> Clone for ScopedPointer<'scope, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'scope, T> Copy for ScopedPointer<'scope, T> {}
+impl<'scope, T> fmt::Debug for ScopedPointer<'scope, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ScopedPointer")
+            .field(&self.ptr.as_raw())
+            .finish()
+    }
+}
+unsafe impl<'scope, T> DeviceCopy for ScopedPointer<'scope, T> {}
+unsafe impl<'scope, T> ScopedArg<'scope> for ScopedPointer<'scope, T> {}
+
+macro_rules! impl_scoped_arg {
+    ($($t:ty)*) => {
+        $(
+            unsafe impl<'scope> ScopedArg<'scope> for $t {}
+        )*
+    }
+}
+impl_scoped_arg!(
+    usize u8 u16 u32 u64 u128
+    isize i8 i16 i32 i64 i128
+    f32 f64
+    bool char
+);
+
+#[cfg(test)]
+mod test {
+    use crate::launch;
+    use crate::memory::*;
+    use crate::module::Module;
+    use crate::quick_init;
+    use crate::stream::{Stream, StreamFlags};
+    use std::error::Error;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_scoped_copies() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let input = LockedBuffer::from_slice(&[1u64, 2, 3, 4])?;
+        let mut output = LockedBuffer::new(&0u64, 4)?;
+        let mut first = DeviceBuffer::from_slice(&[0u64; 4])?;
+        let mut second = DeviceBuffer::from_slice(&[0u64; 4])?;
+
+        stream.scope(|s| {
+            let first = s.borrow_mut(&mut *first);
+            let second = s.borrow_mut(&mut *second);
+            s.copy_from(first, s.borrow(&input))?;
+            s.copy_from(second, first.into())?;
+            s.copy_to(second.into(), s.borrow_mut(&mut output))
+        })?;
+        assert_eq!(&[1, 2, 3, 4], output.as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoped_launch() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let ptx = CString::new(include_str!("../../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let x = LockedBuffer::from_slice(&[1.0f32; 16])?;
+        let y = LockedBuffer::from_slice(&[2.0f32; 16])?;
+        let mut result = LockedBuffer::new(&0.0f32, 16)?;
+        let mut dx = DeviceBuffer::from_slice(&[0.0f32; 16])?;
+        let mut dy = DeviceBuffer::from_slice(&[0.0f32; 16])?;
+        let mut dout = DeviceBuffer::from_slice(&[0.0f32; 16])?;
+
+        stream.scope(|s| {
+            let dx = s.borrow_mut(&mut *dx);
+            let dy = s.borrow_mut(&mut *dy);
+            let dout = s.borrow_mut(&mut *dout);
+            s.copy_from(dx, s.borrow(&x))?;
+            s.copy_from(dy, s.borrow(&y))?;
+            unsafe {
+                launch!(module.sum<<<1, 16, 0, s>>>(
+                    dx.as_device_ptr(),
+                    dy.as_device_ptr(),
+                    dout.as_device_ptr(),
+                    16usize
+                ))?;
+            }
+            s.copy_to(dout.into(), s.borrow_mut(&mut result))
+        })?;
+
+        for value in result.iter() {
+            assert_eq!(3.0, *value);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scope_returns_closure_error() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let result: Result<(), _> = stream.scope(|_| Err(crate::error::CudaError::InvalidValue));
+        assert_eq!(Err(crate::error::CudaError::InvalidValue), result);
+        Ok(())
+    }
+}
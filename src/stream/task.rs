@@ -0,0 +1,324 @@
+use crate::error::CudaResult;
+use crate::event::{Event, EventFlags};
+use crate::stream::{Stream, StreamWaitEventFlags};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Source of the unique ids which tie each `TaskId` to the graph that created it.
+static NEXT_GRAPH_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Identifies a task within a [`TaskGraph`](struct.TaskGraph.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TaskId {
+    graph: usize,
+    index: usize,
+}
+
+type TaskFn<'a> = Box<dyn FnOnce(&Stream) -> CudaResult<()> + 'a>;
+
+struct Task<'a> {
+    stream: &'a Stream,
+    dependencies: Vec<TaskId>,
+    work: TaskFn<'a>,
+}
+
+/// A host-side graph of tasks spread over several streams.
+///
+/// Each task is a closure which queues work on the stream it is bound to, and may depend on
+/// earlier tasks. When the graph is executed, the tasks are run in the order they were added and
+/// each dependency on a task bound to a different stream becomes an event recorded after that
+/// task and waited on before the dependent one. Dependencies between tasks on the same stream are
+/// already ordered by the stream, and dependencies which are implied by earlier waits are skipped,
+/// so only the synchronization the graph needs is inserted.
+///
+/// Tasks can only depend on tasks which have already been added, so the graph is always acyclic.
+///
+/// Unlike a [`Graph`](../graph/struct.Graph.html), a task graph is not recorded by the driver;
+/// its closures run on the host once, each time queueing work as usual.
+///
+/// # Examples:
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::memory::*;
+/// use rustacuda::stream::{Stream, StreamFlags, TaskGraph};
+///
+/// let upload = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+/// let compute = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+///
+/// let host = LockedBuffer::new(&1u32, 256)?;
+/// let mut input = DeviceBuffer::from_slice(&[0u32; 256])?;
+/// let mut output = DeviceBuffer::from_slice(&[0u32; 256])?;
+/// let mut result = LockedBuffer::new(&0u32, 256)?;
+///
+/// let mut tasks = TaskGraph::new();
+/// let copy_in = tasks.add_task(&upload, &[], |s| unsafe { input.async_copy_from(&host, s) });
+/// let clear = tasks.add_task(&compute, &[], |s| unsafe { output.async_copy_from(&result, s) });
+/// let _ = tasks.add_task(&compute, &[copy_in, clear], |s| {
+///     // launch kernels on `s` which read the uploaded input ...
+///     Ok(())
+/// });
+/// tasks.execute()?;
+///
+/// compute.synchronize()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TaskGraph<'a> {
+    id: usize,
+    tasks: Vec<Task<'a>>,
+}
+impl<'a> TaskGraph<'a> {
+    /// Create an empty task graph.
+    pub fn new() -> Self {
+        TaskGraph {
+            id: NEXT_GRAPH_ID.fetch_add(1, Ordering::Relaxed),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Returns the number of tasks in the graph.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns true if the graph contains no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Add a task which queues work on `stream` once all of `dependencies` have completed.
+    ///
+    /// `work` is called with `stream` when the graph is executed. Any work it queues on that
+    /// stream will not start before the work queued by the tasks it depends on has finished.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if any of `dependencies` does not belong to this graph.
+    pub fn add_task<F>(&mut self, stream: &'a Stream, dependencies: &[TaskId], work: F) -> TaskId
+    where
+        F: FnOnce(&Stream) -> CudaResult<()> + 'a,
+    {
+        for dependency in dependencies {
+            assert_eq!(
+                self.id, dependency.graph,
+                "Task dependency does not belong to this graph"
+            );
+        }
+        self.tasks.push(Task {
+            stream,
+            dependencies: dependencies.to_vec(),
+            work: Box::new(work),
+        });
+        TaskId {
+            graph: self.id,
+            index: self.tasks.len() - 1,
+        }
+    }
+
+    /// Run every task and insert the events needed between their streams.
+    ///
+    /// This only queues work; it does not wait for it to complete. Synchronize the streams the
+    /// tasks are bound to in order to wait for the results.
+    ///
+    /// # Errors:
+    ///
+    /// If a task returns an error, or recording or waiting on an event fails, execution stops and
+    /// the error is returned. Tasks which have already run are not undone.
+    pub fn execute(self) -> CudaResult<()> {
+        let waits = self.plan();
+
+        let mut events: Vec<Option<Event>> = Vec::with_capacity(self.tasks.len());
+        let mut recorded = vec![false; self.tasks.len()];
+        for task_waits in &waits {
+            for &dependency in task_waits {
+                recorded[dependency] = true;
+            }
+        }
+
+        for (index, task) in self.tasks.into_iter().enumerate() {
+            for &dependency in &waits[index] {
+                let event = events[dependency].as_ref().unwrap();
+                task.stream
+                    .wait_event(event, StreamWaitEventFlags::DEFAULT)?;
+            }
+            (task.work)(task.stream)?;
+
+            if recorded[index] {
+                let event = Event::new(EventFlags::DISABLE_TIMING)?;
+                event.record(task.stream)?;
+                events.push(Some(event));
+            } else {
+                events.push(None);
+            }
+        }
+        Ok(())
+    }
+
+    // Returns, for each task, the tasks whose events it must wait on before it runs.
+    //
+    // For each stream we track how far along every other stream it is already known to be ordered
+    // after. A dependency needs a wait only if it is later than that, and if it is not implied by
+    // waiting on another of the task's dependencies. After a wait, the stream also inherits
+    // everything the dependency was ordered after.
+    fn plan(&self) -> Vec<Vec<usize>> {
+        let mut streams = Vec::new();
+        let mut stream_of = Vec::with_capacity(self.tasks.len());
+        let mut position = Vec::with_capacity(self.tasks.len());
+        let mut lengths: Vec<usize> = Vec::new();
+        for task in &self.tasks {
+            let index = match streams.iter().position(|&s| s == task.stream.as_inner()) {
+                Some(index) => index,
+                None => {
+                    streams.push(task.stream.as_inner());
+                    lengths.push(0);
+                    streams.len() - 1
+                }
+            };
+            stream_of.push(index);
+            lengths[index] += 1;
+            position.push(lengths[index]);
+        }
+
+        // known[s][o] is the number of tasks on stream o which stream s is ordered after.
+        let mut known = vec![vec![0; streams.len()]; streams.len()];
+        // The value of known[s] just after each task ran, where s is the task's stream.
+        let mut known_after: Vec<Vec<usize>> = Vec::with_capacity(self.tasks.len());
+        let mut waits = Vec::with_capacity(self.tasks.len());
+        for (index, task) in self.tasks.iter().enumerate() {
+            let s = stream_of[index];
+            let mut candidates: Vec<usize> = task
+                .dependencies
+                .iter()
+                .map(|dependency| dependency.index)
+                .filter(|&d| stream_of[d] != s && known[s][stream_of[d]] < position[d])
+                .collect();
+            candidates.sort_unstable();
+            candidates.dedup();
+
+            let task_waits: Vec<usize> = candidates
+                .iter()
+                .cloned()
+                .filter(|&d| {
+                    !candidates
+                        .iter()
+                        .any(|&e| e != d && known_after[e][stream_of[d]] >= position[d])
+                })
+                .collect();
+            for &d in &task_waits {
+                for (k, &inherited) in known[s].iter_mut().zip(&known_after[d]) {
+                    *k = (*k).max(inherited);
+                }
+            }
+            known[s][s] = position[index];
+            known_after.push(known[s].clone());
+            waits.push(task_waits);
+        }
+        waits
+    }
+}
+impl<'a> Default for TaskGraph<'a> {
+    fn default() -> Self {
+        TaskGraph::new()
+    }
+}
+impl<'a> fmt::Debug for TaskGraph<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskGraph")
+            .field("tasks", &self.tasks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::*;
+    use crate::quick_init;
+    use crate::stream::StreamFlags;
+    use std::cell::RefCell;
+    use std::error::Error;
+
+    fn noop(_: &Stream) -> CudaResult<()> {
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_skips_redundant_waits() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let a = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let b = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let c = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let mut graph = TaskGraph::new();
+        let a0 = graph.add_task(&a, &[], noop);
+        let a1 = graph.add_task(&a, &[a0], noop);
+        let b0 = graph.add_task(&b, &[a0], noop);
+        // Already ordered after a0 by the previous wait.
+        let b1 = graph.add_task(&b, &[a0, b0], noop);
+        // Needs a1; a0 is implied by it.
+        let c0 = graph.add_task(&c, &[a0, a1], noop);
+        // Only b1 is new; a0 is implied by the wait for a1.
+        let _c1 = graph.add_task(&c, &[b1, c0, a0], noop);
+
+        assert_eq!(
+            vec![vec![], vec![], vec![0], vec![], vec![1], vec![3]],
+            graph.plan()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_orders_work_across_streams() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let first = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let second = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let host = LockedBuffer::new(&5u64, 1 << 16)?;
+        let device = RefCell::new(DeviceBuffer::from_slice(&[0u64; 1 << 16])?);
+        let mut output = LockedBuffer::new(&0u64, 1 << 16)?;
+
+        let mut graph = TaskGraph::new();
+        let upload = graph.add_task(&first, &[], |s| unsafe {
+            device.borrow_mut().async_copy_from(&host, s)
+        });
+        let _ = graph.add_task(&second, &[upload], |s| unsafe {
+            device.borrow().async_copy_to(&mut output, s)
+        });
+        assert_eq!(2, graph.len());
+        graph.execute()?;
+
+        second.synchronize()?;
+        assert!(output.iter().all(|&x| x == 5));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_foreign_dependency_panics() {
+        let _context = quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let mut other = TaskGraph::new();
+        let _ = other.add_task(&stream, &[], noop);
+        let foreign = other.add_task(&stream, &[], noop);
+
+        let mut graph = TaskGraph::new();
+        let _ = graph.add_task(&stream, &[foreign], noop);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_foreign_dependency_in_range_panics() {
+        let _context = quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let mut other = TaskGraph::new();
+        let foreign = other.add_task(&stream, &[], noop);
+
+        let mut graph = TaskGraph::new();
+        let _ = graph.add_task(&stream, &[], noop);
+        let _ = graph.add_task(&stream, &[foreign], noop);
+    }
+}
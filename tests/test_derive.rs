@@ -4,6 +4,14 @@
 extern crate rustacuda;
 extern crate rustacuda_core;
 
+use rustacuda::function::{KernelArgs, LaunchConfig};
+use rustacuda::memory::{CopyDestination, DeviceBuffer, DevicePointer};
+use rustacuda::module::Module;
+use rustacuda::quick_init;
+use rustacuda::stream::{Stream, StreamFlags};
+use std::error::Error;
+use std::ffi::CString;
+
 #[derive(Clone, DeviceCopy)]
 struct ZeroSizedStruct;
 
@@ -62,3 +70,77 @@ fn test_hidden_functions() {
     __verify_GenericEnum_can_implement_DeviceCopy::<u64>(&GenericEnum::Unit);
     __verify_TestUnion_can_implement_DeviceCopy(&TestUnion { u: 0u64 });
 }
+
+#[derive(Clone, Copy, KernelArgs)]
+struct SumArgs {
+    x: DevicePointer<f32>,
+    y: DevicePointer<f32>,
+    out: DevicePointer<f32>,
+    len: u32,
+}
+
+#[derive(Clone, Copy, KernelArgs)]
+struct MixedArgs(u8, u64, f32, u16, f64);
+
+#[derive(Clone, Copy, KernelArgs)]
+struct GenericArgs<T> {
+    flag: u8,
+    value: T,
+}
+
+#[derive(Clone, Copy, KernelArgs)]
+struct NoArgs;
+
+#[test]
+fn test_kernel_args_packing() {
+    let args = MixedArgs(1, 2, 3.0, 4, 5.0);
+    let buffer = args.param_buffer().unwrap();
+    let bytes = buffer.as_bytes();
+    // Each field is aligned to its own alignment: u8 at 0, u64 at 8, f32 at 16, u16 at 20 and
+    // f64 at 24.
+    assert_eq!(32, bytes.len());
+    assert_eq!(1, bytes[0]);
+    assert_eq!(&2u64.to_ne_bytes(), &bytes[8..16]);
+    assert_eq!(&3.0f32.to_ne_bytes(), &bytes[16..20]);
+    assert_eq!(&4u16.to_ne_bytes(), &bytes[20..22]);
+    assert_eq!(&5.0f64.to_ne_bytes(), &bytes[24..32]);
+    assert_eq!(5, args.kernel_params().len());
+
+    let generic = GenericArgs {
+        flag: 1,
+        value: 7u32,
+    };
+    assert_eq!(8, generic.param_buffer().unwrap().as_bytes().len());
+
+    assert!(NoArgs.param_buffer().unwrap().as_bytes().is_empty());
+    assert!(NoArgs.kernel_params().is_empty());
+}
+
+#[test]
+fn test_kernel_args_launch() -> Result<(), Box<dyn Error>> {
+    let _context = quick_init()?;
+    let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+    let module = Module::load_from_string(&ptx_text)?;
+    let sum = module.get_kernel::<SumArgs>("sum")?;
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+    let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 128])?;
+    let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 128])?;
+    let mut out = DeviceBuffer::from_slice(&[0.0f32; 128])?;
+    let args = SumArgs {
+        x: in_x.as_device_ptr(),
+        y: in_y.as_device_ptr(),
+        out: out.as_device_ptr(),
+        len: 128,
+    };
+    unsafe {
+        sum.launch(&stream, LaunchConfig::new(1, 64), args)?;
+        sum.launch(&stream, LaunchConfig::new(2, 64), args)?;
+    }
+    stream.synchronize()?;
+
+    let mut out_host = [0f32; 128];
+    out.copy_to(&mut out_host[..])?;
+    assert!(out_host.iter().all(|&x| x == 3.0));
+    Ok(())
+}
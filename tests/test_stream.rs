@@ -10,19 +10,22 @@ fn test_stream_callbacks_execution_order() {
     let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
 
     let (order_sender, order_receiver) = sync_channel(0);
+    let sender = order_sender.clone();
     stream
-        .add_callback(Box::new(|_| {
-            order_sender.send(1).unwrap();
+        .add_callback(Box::new(move |_| {
+            sender.send(1).unwrap();
         }))
         .unwrap();
+    let sender = order_sender.clone();
     stream
-        .add_callback(Box::new(|_| {
-            order_sender.send(2).unwrap();
+        .add_callback(Box::new(move |_| {
+            sender.send(2).unwrap();
         }))
         .unwrap();
+    let sender = order_sender.clone();
     stream
-        .add_callback(Box::new(|_| {
-            order_sender.send(3).unwrap();
+        .add_callback(Box::new(move |_| {
+            sender.send(3).unwrap();
         }))
         .unwrap();
     for expected in &[1, 2, 3] {
@@ -38,7 +41,7 @@ fn test_stream_callbacks_environment_capture() {
     let (capture_sender, capture_receiver) = sync_channel(0);
     let magic_numbers = (42, Box::new(1337));
     stream
-        .add_callback(Box::new(|_| {
+        .add_callback(Box::new(move |_| {
             capture_sender.send(magic_numbers).unwrap();
         }))
         .unwrap();
@@ -54,9 +57,110 @@ fn test_stream_callbacks_status_propagation() {
 
     let (status_sender, status_receiver) = sync_channel(0);
     stream
-        .add_callback(Box::new(|status| {
+        .add_callback(Box::new(move |status| {
             status_sender.send(status).unwrap();
         }))
         .unwrap();
     assert_eq!(Ok(()), status_receiver.recv().unwrap())
 }
+
+#[test]
+fn test_stream_query_and_synchronize_timeout() {
+    use rustacuda::error::CudaError;
+    use rustacuda::stream::StreamStatus;
+    use std::time::Duration;
+
+    let _ctx = quick_init();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    assert_eq!(StreamStatus::Ready, stream.query().unwrap());
+
+    // Block the stream in a callback until the test releases it.
+    let (release_sender, release_receiver) = sync_channel::<()>(0);
+    stream
+        .add_callback(Box::new(move |_| {
+            release_receiver.recv().unwrap();
+        }))
+        .unwrap();
+    assert_eq!(StreamStatus::NotReady, stream.query().unwrap());
+    assert_eq!(
+        Err(CudaError::Timeout),
+        stream.synchronize_timeout(Duration::from_millis(10))
+    );
+
+    release_sender.send(()).unwrap();
+    stream.synchronize_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(StreamStatus::Ready, stream.query().unwrap());
+}
+
+#[test]
+fn test_launch_host_fn() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let _ctx = quick_init();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let counter = Arc::new(AtomicUsize::new(0));
+    for _ in 0..3 {
+        let counter = counter.clone();
+        stream
+            .launch_host_fn(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+    }
+    stream.synchronize().unwrap();
+    assert_eq!(3, counter.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_host_fn_panic_is_reported_on_synchronize() {
+    use rustacuda::error::CudaError;
+
+    let _ctx = quick_init();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    stream.launch_host_fn(|| panic!("host function")).unwrap();
+    assert_eq!(Err(CudaError::HostFunctionPanicked), stream.synchronize());
+    // The panic is only reported once.
+    stream.synchronize().unwrap();
+
+    stream
+        .add_callback(Box::new(|_| panic!("callback")))
+        .unwrap();
+    assert_eq!(Err(CudaError::HostFunctionPanicked), stream.synchronize());
+}
+
+#[test]
+fn test_default_stream_handles() {
+    use rustacuda::memory::{AsyncCopyDestination, DeviceBuffer, LockedBuffer};
+
+    let _ctx = quick_init();
+    for stream in &[Stream::legacy_default(), Stream::per_thread_default()] {
+        let host = LockedBuffer::from_slice(&[1u32, 2, 3]).unwrap();
+        let mut device = DeviceBuffer::from_slice(&[0u32; 3]).unwrap();
+        unsafe {
+            device.async_copy_from(&host, stream).unwrap();
+        }
+        stream.synchronize().unwrap();
+        let mut check = [0u32; 3];
+        device.copy_to(&mut check[..]).unwrap();
+        assert_eq!([1, 2, 3], check);
+    }
+
+    // Dropping a handle does not destroy the stream.
+    Stream::drop(Stream::legacy_default()).unwrap();
+    drop(Stream::per_thread_default());
+    Stream::legacy_default().synchronize().unwrap();
+    Stream::per_thread_default().synchronize().unwrap();
+
+    // A panic in a host function is reported through any handle to the same default stream.
+    for make_handle in &[Stream::legacy_default, Stream::per_thread_default] {
+        make_handle()
+            .launch_host_fn(|| panic!("host function"))
+            .unwrap();
+        assert_eq!(
+            Err(rustacuda::error::CudaError::HostFunctionPanicked),
+            make_handle().synchronize()
+        );
+        make_handle().synchronize().unwrap();
+    }
+}